@@ -13,8 +13,7 @@ program:
 	
 	; Call malloc
 	mov R1 [payload_end - payload]
-	mov R0 pc, lea R0 4, restrict R0 E
-	jmp R33
+	jal R33 R0
 	
 	mov R2 R1
 	
@@ -28,8 +27,7 @@ program:
 	restrict R1 RO
 	
 	; Call memcpy
-	mov R0 pc, lea R0 4, restrict R0 E
-	jmp R32
+	jal R32 R0
 	
 	mov R42 '-'
 	halt
@@ -1,4 +1,4 @@
-; PROGRAM LENGTH: 33 rows
+; PROGRAM LENGTH: 25 rows
 
 ; r0: return
 ; r1: source memory (RO, a, b, a)
@@ -6,24 +6,16 @@
 
 memcpy:
 	; make sure that address of r1 is =base
-	geta r3 r1
-	getb r4 r1
-	sub r3 r4 r3 ; if address is bigger than base by x, now r3=-x
-	lea r1 r3 ; done
+	getb r3 r1
+	seta r1 r3
 	
 	; make sure that address of r2 is =base
-	geta r3 r2
-	getb r4 r2
-	sub r3 r4 r3 ; if address is bigger than base by x, now r3=-x
-	lea r2 r3 ; done
+	getb r3 r2
+	seta r2 r3
 	
 	; check that the size of the memory zones r1 and r2 are the same
-	getb r3 r1
-	gete r4 r1
-	sub r3 r4 r3 ; r3=end-base
-	getb r4 r2
-	gete r5 r2
-	sub r4 r5 r4 ; r4=end-base
+	getl r3 r1
+	getl r4 r2
 	sub r3 r4 r3 ; r3 should now be zero. if not, fail
 		mov r4 PC
 		lea r4 4
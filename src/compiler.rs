@@ -23,8 +23,8 @@ pub mod tokens;
 --------------------------------------------------------------------------------
 */
 
-pub fn compile_unwrapped(source: &str) -> Program {
-	compile(source)
+pub fn compile_unwrapped(source: &str, file: Option<&str>) -> Program {
+	compile_with_file(source, file)
 		.map_err(|CompilationError { ctx, msg, span }| {
 			let mut colors = ColorGenerator::new();
 			let a = colors.next();
@@ -42,6 +42,12 @@ pub fn compile_unwrapped(source: &str) -> Program {
 
 /// Compile the given source code string
 pub fn compile(source: &str) -> Result<Program, CompilationError> {
+	compile_with_file(source, None)
+}
+
+/// Compile the given source code string, read from the given file.
+/// The file only ends up in the debug info of the program.
+pub fn compile_with_file(source: &str, file: Option<&str>) -> Result<Program, CompilationError> {
 	// Create the logos lexer.
 	// The lexer is responsible for converting the source code to individual tokens that are easier to parse
 	let mut lexer = Token::lexer(source);
@@ -61,7 +67,7 @@ pub fn compile(source: &str) -> Result<Program, CompilationError> {
 	}
 
 	// Generate the program in internal representation
-	let program = generator::generate_program_from_ast(ast)?;
+	let program = generator::generate_program_from_ast(ast, source, file)?;
 
 	Ok(program)
 }
@@ -89,4 +95,14 @@ impl CompilationError {
 			span,
 		}
 	}
+
+	/// What went wrong, e.g. "error while parsing register or word: unexpected token, expected value".
+	pub fn message(&self) -> String {
+		format!("error while {}: {}", self.ctx, self.msg)
+	}
+
+	/// Where in the source it went wrong.
+	pub fn span(&self) -> &Span {
+		&self.span
+	}
 }
@@ -36,6 +36,7 @@ pub enum AstWord {
 	Expression(AstExpression),
 	Char(WordChar),
 	Capability(Capability),
+	Permission(Permission),
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -47,16 +48,18 @@ pub enum AstInstruction {
 	Halt,
 	/// mov r ρ
 	Mov     (Register, AstRegisterOrWord),
-	/// load r1 r2
-	Load    (Register, Register),
-	/// store r ρ
-	Store   (Register, AstRegisterOrWord),
+	/// load r1 r2 ρ
+	Load    (Register, Register, AstRegisterOrWord),
+	/// store r ρ1 ρ2
+	Store   (Register, AstRegisterOrWord, AstRegisterOrWord),
 	/// jmp r
 	Jmp     (Register),
 	/// jnz r1 r2
 	Jnz     (Register, Register),
+	/// jal r1 r2
+	Jal     (Register, Register),
 	/// restrict r ρ
-	Restrict(Register, Permission),
+	Restrict(Register, AstRegisterOrWord),
 	/// subseg r ρ1 ρ2
 	Subseg  (Register, AstRegisterOrWord, AstRegisterOrWord),
 	/// lea r ρ
@@ -77,6 +80,20 @@ pub enum AstInstruction {
 	Geta    (Register, Register),
 	/// isptr r1 r2
 	Isptr   (Register, Register),
+	/// getl r1 r2
+	Getl    (Register, Register),
+	/// geto r1 r2
+	Geto    (Register, Register),
+	/// seta r ρ
+	Seta    (Register, AstRegisterOrWord),
+	/// setbounds r ρ
+	Setbounds(Register, AstRegisterOrWord),
+	/// subset r1 r2 r3
+	Subset  (Register, Register, Register),
+	/// ceq r1 r2 r3
+	Ceq     (Register, Register, Register),
+	/// flows r ρ1 ρ2
+	Flows   (Register, AstRegisterOrWord, AstRegisterOrWord),
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
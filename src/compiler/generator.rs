@@ -1,6 +1,10 @@
+use std::collections::HashMap;
+
+use logos::Span;
+
 use crate::emulator::{
 	instruction::{Instruction, RegisterOrWord},
-	program::{Program, Row, Word},
+	program::{Address, LabelIdentifier, Program, Row, SourceLocation, Word},
 	signed::Signed,
 };
 
@@ -9,8 +13,29 @@ use super::{
 	CompilationError,
 };
 
-pub fn generate_program_from_ast(ast: Ast) -> Result<Program, CompilationError> {
+/// Generates the program from a processed AST.
+/// The source and the file it was read from (if any) are used to generate the debug info of the program.
+pub fn generate_program_from_ast(ast: Ast, source: &str, file: Option<&str>) -> Result<Program, CompilationError> {
+	// Skip the temporary labels generated when desugaring gotos
+	let labels = ast
+		.labels
+		.into_iter()
+		.filter(|(label, _)| !label.starts_with(':'))
+		.collect::<HashMap<_, _>>();
+
+	let mut sorted_labels = labels
+		.iter()
+		.map(|(label, address)| (*address, label))
+		.collect::<Vec<_>>();
+	sorted_labels.sort();
+
 	Ok(Program {
+		debug_info: ast
+			.rows
+			.iter()
+			.enumerate()
+			.map(|(i, (_, span))| generate_source_location(source, file, span, &sorted_labels, Address(i)))
+			.collect(),
 		rows: ast
 			.rows
 			.into_iter()
@@ -24,15 +49,40 @@ pub fn generate_program_from_ast(ast: Ast) -> Result<Program, CompilationError>
 				}
 			})
 			.collect(),
+		labels,
+		encoding_version: None,
 	})
 }
 
+fn generate_source_location(
+	source: &str,
+	file: Option<&str>,
+	span: &Span,
+	sorted_labels: &[(Address, &LabelIdentifier)],
+	address: Address,
+) -> SourceLocation {
+	let before = &source[..span.start.min(source.len())];
+	let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+	SourceLocation {
+		file: file.map(str::to_owned),
+		line: before.matches('\n').count() + 1,
+		column: before[line_start..].chars().count() + 1,
+		label: sorted_labels
+			.iter()
+			.rev()
+			.find(|(label_address, _)| *label_address <= address)
+			.map(|(_, label)| (*label).clone()),
+	}
+}
+
 #[rustfmt::skip]
 fn generate_word(word: AstWord) -> Word {
 	match word {
 		AstWord::Integer(i)    => Word::Integer(i),
 		AstWord::Char(c)       => Word::Char(c),
 		AstWord::Capability(c) => Word::Capability(Signed::new_unsigned(c)),
+		AstWord::Permission(p) => Word::Permission(p),
 		_ => panic!("Unprocessed elements in AST! This should not happen, AST was not processed properly."),
 	}
 }
@@ -43,11 +93,12 @@ fn generate_instruction(instruction: AstInstruction) -> Instruction {
 		AstInstruction::Fail              => Instruction::Fail,
 		AstInstruction::Halt              => Instruction::Halt,
 		AstInstruction::Mov(r, p)         => Instruction::Mov(r, generate_reg_or_word(p)),
-		AstInstruction::Load(r1, r2)      => Instruction::Load(r1, r2),
-		AstInstruction::Store(r, p)       => Instruction::Store(r, generate_reg_or_word(p)),
+		AstInstruction::Load(r1, r2, p)   => Instruction::Load(r1, r2, generate_reg_or_word(p)),
+		AstInstruction::Store(r, p1, p2)  => Instruction::Store(r, generate_reg_or_word(p1), generate_reg_or_word(p2)),
 		AstInstruction::Jmp(r)            => Instruction::Jmp(r),
 		AstInstruction::Jnz(r1, r2)       => Instruction::Jnz(r1, r2),
-		AstInstruction::Restrict(r, p)    => Instruction::Restrict(r, p),
+		AstInstruction::Jal(r1, r2)       => Instruction::Jal(r1, r2),
+		AstInstruction::Restrict(r, p)    => Instruction::Restrict(r, generate_reg_or_word(p)),
 		AstInstruction::Subseg(r, p1, p2) => Instruction::Subseg(r, generate_reg_or_word(p1), generate_reg_or_word(p2)),
 		AstInstruction::Lea(r, p)         => Instruction::Lea(r, generate_reg_or_word(p)),
 		AstInstruction::Add(r, p1, p2)    => Instruction::Add(r, generate_reg_or_word(p1), generate_reg_or_word(p2)),
@@ -58,6 +109,13 @@ fn generate_instruction(instruction: AstInstruction) -> Instruction {
 		AstInstruction::Gete(r1, r2)      => Instruction::Gete(r1, r2),
 		AstInstruction::Geta(r1, r2)      => Instruction::Geta(r1, r2),
 		AstInstruction::Isptr(r1, r2)     => Instruction::Isptr(r1, r2),
+		AstInstruction::Getl(r1, r2)      => Instruction::Getl(r1, r2),
+		AstInstruction::Geto(r1, r2)      => Instruction::Geto(r1, r2),
+		AstInstruction::Seta(r, p)        => Instruction::Seta(r, generate_reg_or_word(p)),
+		AstInstruction::Setbounds(r, p)   => Instruction::Setbounds(r, generate_reg_or_word(p)),
+		AstInstruction::Subset(r1, r2, r3) => Instruction::Subset(r1, r2, r3),
+		AstInstruction::Ceq(r1, r2, r3)   => Instruction::Ceq(r1, r2, r3),
+		AstInstruction::Flows(r, p1, p2)  => Instruction::Flows(r, generate_reg_or_word(p1), generate_reg_or_word(p2)),
 	}
 }
 
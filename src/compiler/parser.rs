@@ -91,7 +91,10 @@ fn parse_row(first_token: Token, l: &mut Lexer<'_, Token>) -> Result<(AstRow, Sp
 		Token::Integer(i)     => AstRow::Word(AstWord::Integer(i)),
 		Token::LeftBracket    => AstRow::Word(AstWord::Expression(parse_expression(l)?)),
 		Token::String(s)      => AstRow::String(s),
-		_ => return Err(CompilationError::new("parsing row", "unexpected token, expected instruction or data", l.span())),
+		_ => match permission_from_token(&first_token) {
+			Some(p)           => AstRow::Word(AstWord::Permission(p)),
+			None => return Err(CompilationError::new("parsing row", "unexpected token, expected instruction or data", l.span())),
+		},
 	};
 
 	let row_end = l.span().end;
@@ -126,11 +129,12 @@ fn parse_instruction(instruction_token: Token, l: &mut Lexer<'_, Token>) -> Resu
 		Token::Instruction(InstructionToken::Fail)     => Ok(AstInstruction::Fail),
 		Token::Instruction(InstructionToken::Halt)     => Ok(AstInstruction::Halt),
 		Token::Instruction(InstructionToken::Mov)      => Ok(AstInstruction::Mov     (parse_reg(l)?, parse_reg_or_word(l)?)),
-		Token::Instruction(InstructionToken::Load)     => Ok(AstInstruction::Load    (parse_reg(l)?, parse_reg(l)?)),
-		Token::Instruction(InstructionToken::Store)    => Ok(AstInstruction::Store   (parse_reg(l)?, parse_reg_or_word(l)?)),
+		Token::Instruction(InstructionToken::Load)     => Ok(AstInstruction::Load    (parse_reg(l)?, parse_reg(l)?, parse_offset(l)?)),
+		Token::Instruction(InstructionToken::Store)    => parse_store(l),
 		Token::Instruction(InstructionToken::Jmp)      => Ok(AstInstruction::Jmp     (parse_reg(l)?)),
 		Token::Instruction(InstructionToken::Jnz)      => Ok(AstInstruction::Jnz     (parse_reg(l)?, parse_reg(l)?)),
-		Token::Instruction(InstructionToken::Restrict) => Ok(AstInstruction::Restrict(parse_reg(l)?, parse_permission(l)?)),
+		Token::Instruction(InstructionToken::Jal)      => Ok(AstInstruction::Jal     (parse_reg(l)?, parse_reg(l)?)),
+		Token::Instruction(InstructionToken::Restrict) => Ok(AstInstruction::Restrict(parse_reg(l)?, parse_reg_or_word(l)?)),
 		Token::Instruction(InstructionToken::Subseg)   => Ok(AstInstruction::Subseg  (parse_reg(l)?, parse_reg_or_word(l)?, parse_reg_or_word(l)?)),
 		Token::Instruction(InstructionToken::Lea)      => Ok(AstInstruction::Lea     (parse_reg(l)?, parse_reg_or_word(l)?)),
 		Token::Instruction(InstructionToken::Add)      => Ok(AstInstruction::Add     (parse_reg(l)?, parse_reg_or_word(l)?, parse_reg_or_word(l)?)),
@@ -141,10 +145,43 @@ fn parse_instruction(instruction_token: Token, l: &mut Lexer<'_, Token>) -> Resu
 		Token::Instruction(InstructionToken::Gete)     => Ok(AstInstruction::Gete    (parse_reg(l)?, parse_reg(l)?)),
 		Token::Instruction(InstructionToken::Geta)     => Ok(AstInstruction::Geta    (parse_reg(l)?, parse_reg(l)?)),
 		Token::Instruction(InstructionToken::Isptr)    => Ok(AstInstruction::Isptr   (parse_reg(l)?, parse_reg(l)?)),
+		Token::Instruction(InstructionToken::Getl)     => Ok(AstInstruction::Getl    (parse_reg(l)?, parse_reg(l)?)),
+		Token::Instruction(InstructionToken::Geto)     => Ok(AstInstruction::Geto    (parse_reg(l)?, parse_reg(l)?)),
+		Token::Instruction(InstructionToken::Seta)     => Ok(AstInstruction::Seta    (parse_reg(l)?, parse_reg_or_word(l)?)),
+		Token::Instruction(InstructionToken::Setbounds) => Ok(AstInstruction::Setbounds(parse_reg(l)?, parse_reg_or_word(l)?)),
+		Token::Instruction(InstructionToken::Subset)   => Ok(AstInstruction::Subset  (parse_reg(l)?, parse_reg(l)?, parse_reg(l)?)),
+		Token::Instruction(InstructionToken::Ceq)      => Ok(AstInstruction::Ceq     (parse_reg(l)?, parse_reg(l)?, parse_reg(l)?)),
+		Token::Instruction(InstructionToken::Flows)    => Ok(AstInstruction::Flows   (parse_reg(l)?, parse_reg_or_word(l)?, parse_reg_or_word(l)?)),
 		_ => Err(CompilationError::new("parsing instruction", "unexpected token, expected instruction", l.span())),
 	}
 }
 
+/// Parses a store instruction, which is either "store r ρ" or "store r offset ρ".
+/// Assumes the first token of the instruction has already been parsed.
+fn parse_store(l: &mut Lexer<'_, Token>) -> Result<AstInstruction, CompilationError> {
+	let r = parse_reg(l)?;
+	let p1 = parse_reg_or_word(l)?;
+
+	Ok(match parse_optional_reg_or_word(l)? {
+		Some(p2) => AstInstruction::Store(r, p1, p2),
+		None => AstInstruction::Store(r, AstRegisterOrWord::Word(AstWord::Integer(0)), p1),
+	})
+}
+
+/// Parses an optional offset at the end of an instruction, which defaults to 0.
+fn parse_offset(l: &mut Lexer<'_, Token>) -> Result<AstRegisterOrWord, CompilationError> {
+	Ok(parse_optional_reg_or_word(l)?.unwrap_or(AstRegisterOrWord::Word(AstWord::Integer(0))))
+}
+
+/// Parses either a register or a data word, if the row doesn't end here.
+fn parse_optional_reg_or_word(l: &mut Lexer<'_, Token>) -> Result<Option<AstRegisterOrWord>, CompilationError> {
+	// Peek at the next token without consuming it
+	match l.clone().next() {
+		None | Some(Ok(Token::Comma)) | Some(Ok(Token::LineBreak)) => Ok(None),
+		_ => parse_reg_or_word(l).map(Some),
+	}
+}
+
 /// Parses either a register or a data word.
 #[rustfmt::skip]
 fn parse_reg_or_word(l: &mut Lexer<'_, Token>) -> Result<AstRegisterOrWord, CompilationError> {
@@ -158,7 +195,10 @@ fn parse_reg_or_word(l: &mut Lexer<'_, Token>) -> Result<AstRegisterOrWord, Comp
 			
 			Token::LeftBracket        => Ok(AstRegisterOrWord::Word(AstWord::Expression(parse_expression(l)?))),
 			
-			_ => Err(CompilationError::new("parsing register or word", "unexpected token, expected value", l.span())),
+			_ => match permission_from_token(&token) {
+				Some(p)               => Ok(AstRegisterOrWord::Word(AstWord::Permission(p))),
+				None => Err(CompilationError::new("parsing register or word", "unexpected token, expected value", l.span())),
+			},
 		}
 	} else {
 		Err(CompilationError::new("parsing register or word", "unexpected syntax error here", l.span()))
@@ -179,20 +219,16 @@ fn parse_reg(l: &mut Lexer<'_, Token>) -> Result<Register, CompilationError> {
 	}
 }
 
-/// Parses a permission
+/// Converts a permission token to its permission, or None if the token isn't a permission.
 #[rustfmt::skip]
-fn parse_permission(l: &mut Lexer<'_, Token>) -> Result<Permission, CompilationError> {
-	if let Some(Ok(token)) = l.next() {
-		match token {
-			Token::PermissionO   => Ok(Permission::O),
-			Token::PermissionE   => Ok(Permission::E),
-			Token::PermissionRO  => Ok(Permission::RO),
-			Token::PermissionRX  => Ok(Permission::RX),
-			Token::PermissionRW  => Ok(Permission::RW),
-			Token::PermissionRWX => Ok(Permission::RWX),
-			_ => Err(CompilationError::new("parsing permission", "unexpected token, expected permission", l.span())),
-		}
-	} else {
-		Err(CompilationError::new("parsing permission", "unexpected syntax error here", l.span()))
+fn permission_from_token(token: &Token) -> Option<Permission> {
+	match token {
+		Token::PermissionO   => Some(Permission::O),
+		Token::PermissionE   => Some(Permission::E),
+		Token::PermissionRO  => Some(Permission::RO),
+		Token::PermissionRX  => Some(Permission::RX),
+		Token::PermissionRW  => Some(Permission::RW),
+		Token::PermissionRWX => Some(Permission::RWX),
+		_ => None,
 	}
 }
@@ -22,10 +22,9 @@ pub fn process_strings_to_chars(mut ast: Ast) -> Result<Ast, CompilationError> {
 		.rows
 		.iter()
 		.flat_map(|l| match l {
-			(AstRow::String(s), _span) => s
+			(AstRow::String(s), span) => s
 				.chars()
-				.enumerate()
-				.map(|(i, c)| (AstRow::Word(AstWord::Char(c)), (i..i + 1)))
+				.map(|c| (AstRow::Word(AstWord::Char(c)), span.clone()))
 				.collect::<Vec<_>>(),
 			_ => vec![l.clone()],
 		})
@@ -130,8 +129,11 @@ fn evaluate_instruction(
 	Ok(match inst {
 		// Single expression instructions
 		AstInstruction::Mov(r, p) => AstInstruction::Mov(r, evaluate_register_or_word(p, env)?),
-		AstInstruction::Store(r, p) => AstInstruction::Store(r, evaluate_register_or_word(p, env)?),
+		AstInstruction::Load(r1, r2, p) => AstInstruction::Load(r1, r2, evaluate_register_or_word(p, env)?),
 		AstInstruction::Lea(r, p) => AstInstruction::Lea(r, evaluate_register_or_word(p, env)?),
+		AstInstruction::Restrict(r, p) => AstInstruction::Restrict(r, evaluate_register_or_word(p, env)?),
+		AstInstruction::Seta(r, p) => AstInstruction::Seta(r, evaluate_register_or_word(p, env)?),
+		AstInstruction::Setbounds(r, p) => AstInstruction::Setbounds(r, evaluate_register_or_word(p, env)?),
 
 		// Double expression instructions
 		AstInstruction::Subseg(r, p1, p2) => AstInstruction::Subseg(
@@ -140,6 +142,12 @@ fn evaluate_instruction(
 			evaluate_register_or_word(p2, env)?,
 		),
 
+		AstInstruction::Store(r, p1, p2) => AstInstruction::Store(
+			r,
+			evaluate_register_or_word(p1, env)?,
+			evaluate_register_or_word(p2, env)?,
+		),
+
 		AstInstruction::Add(r, p1, p2) => AstInstruction::Add(
 			r,
 			evaluate_register_or_word(p1, env)?,
@@ -158,6 +166,12 @@ fn evaluate_instruction(
 			evaluate_register_or_word(p2, env)?,
 		),
 
+		AstInstruction::Flows(r, p1, p2) => AstInstruction::Flows(
+			r,
+			evaluate_register_or_word(p1, env)?,
+			evaluate_register_or_word(p2, env)?,
+		),
+
 		// Anything else
 		_ => inst,
 	})
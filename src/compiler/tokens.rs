@@ -133,6 +133,7 @@ pub enum Token {
 	#[token("store",    |_| InstructionToken::Store,    ignore(case))]
 	#[token("jmp",      |_| InstructionToken::Jmp,      ignore(case))]
 	#[token("jnz",      |_| InstructionToken::Jnz,      ignore(case))]
+	#[token("jal",      |_| InstructionToken::Jal,      ignore(case))]
 	#[token("restrict", |_| InstructionToken::Restrict, ignore(case))]
 	#[token("subseg",   |_| InstructionToken::Subseg,   ignore(case))]
 	#[token("lea",      |_| InstructionToken::Lea,      ignore(case))]
@@ -144,6 +145,13 @@ pub enum Token {
 	#[token("gete",     |_| InstructionToken::Gete,     ignore(case))]
 	#[token("geta",     |_| InstructionToken::Geta,     ignore(case))]
 	#[token("isptr",    |_| InstructionToken::Isptr,    ignore(case))]
+	#[token("getl",     |_| InstructionToken::Getl,     ignore(case))]
+	#[token("geto",     |_| InstructionToken::Geto,     ignore(case))]
+	#[token("seta",     |_| InstructionToken::Seta,     ignore(case))]
+	#[token("setbounds", |_| InstructionToken::Setbounds, ignore(case))]
+	#[token("subset",   |_| InstructionToken::Subset,   ignore(case))]
+	#[token("ceq",      |_| InstructionToken::Ceq,      ignore(case))]
+	#[token("flows",    |_| InstructionToken::Flows,    ignore(case))]
 	Instruction(InstructionToken),
 }
 
@@ -157,6 +165,7 @@ pub enum InstructionToken {
 	Store,
 	Jmp,
 	Jnz,
+	Jal,
 	Restrict,
 	Subseg,
 	Lea,
@@ -168,6 +177,13 @@ pub enum InstructionToken {
 	Gete,
 	Geta,
 	Isptr,
+	Getl,
+	Geto,
+	Seta,
+	Setbounds,
+	Subset,
+	Ceq,
+	Flows,
 }
 
 /// The callback to convert a decimal integer string to int.
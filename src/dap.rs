@@ -0,0 +1,588 @@
+use std::{
+	collections::BTreeMap,
+	fs::{self, File},
+	io::{self, BufRead, Write},
+	path::Path,
+};
+
+use serde_json::{json, Value};
+
+use crate::{
+	debugger::{self, Debugger},
+	emulator::{
+		machine::{Machine, State},
+		machine_config::{MachineConfig, ProgramConfig},
+		program::{Address, Capability, RegInt, Register, Row, Word},
+	},
+	try_read_machine_config,
+};
+
+/*
+--------------------------------------------------------------------------------
+||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||
+--------------------------------------------------------------------------------
+*/
+
+/// The machine is the only thread.
+const THREAD_ID: i64 = 1;
+
+/// The variable references of the scopes, and the bases of the references of the capabilities they contain.
+/// A capability in register n (PC being 256) has reference REGISTER_CAPABILITIES + n,
+/// and one in memory at address a has reference MEMORY_CAPABILITIES + a.
+const REGISTERS: i64 = 1;
+const MEMORY: i64 = 2;
+const REGISTER_CAPABILITIES: i64 = 1_000;
+const MEMORY_CAPABILITIES: i64 = 1_000_000;
+
+/// The largest message accepted from the client, requests being small JSON objects.
+const MAX_MESSAGE: usize = 1 << 20;
+
+/// A Debug Adapter Protocol server, letting editors like VS Code debug a machine.
+///
+/// The launch request takes the path of the program to debug (an assembly file, compiled if its extension is .asm,
+/// or a RON file like for emulate), and optionally compile and stopOnEntry flags.
+/// Breakpoints are set on source lines, using the source locations of the rows.
+/// Registers and memory are shown as variables, with capabilities expanded into their perm, base, end and address.
+/// Expressions evaluated in the debug console are debugger commands, e.g. `cap r1` or `provenance pc`.
+///
+/// Like the debugger, the machine runs synchronously: continuing can't be paused.
+#[derive(Default)]
+pub struct DapServer {
+	debugger: Option<Debugger>,
+	seq: i64,
+
+	/// The breakpoint lines requested for every source path
+	breakpoints: BTreeMap<String, Vec<usize>>,
+
+	stop_on_entry: bool,
+}
+
+impl DapServer {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn machine(&self) -> Option<&Machine> {
+		self.debugger.as_ref().map(Debugger::machine)
+	}
+
+	/// Serves a single client, until it disconnects or until the input ends.
+	pub fn serve(&mut self, mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+		while let Some(request) = read_message(&mut input)? {
+			let messages = self.handle(&request);
+
+			for message in &messages {
+				write_message(&mut output, message)?;
+			}
+
+			if request["command"] == "disconnect" {
+				break;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Handles a single request and returns the messages to send back: its response, followed by events.
+	pub fn handle(&mut self, request: &Value) -> Vec<Value> {
+		let command = request["command"].as_str().unwrap_or_default();
+		let arguments = &request["arguments"];
+
+		let mut events = Vec::new();
+
+		let result = match command {
+			"initialize" => Ok(json!({
+				"supportsConfigurationDoneRequest": true,
+				"supportsStepBack": true,
+				"supportsEvaluateForHovers": true,
+			})),
+			"launch" => self.launch(arguments, &mut events),
+			"setBreakpoints" => self.set_breakpoints(arguments),
+			"setExceptionBreakpoints" => Ok(json!({ "breakpoints": [] })),
+			"configurationDone" => {
+				if self.stop_on_entry {
+					events.push(self.stopped("entry", None));
+				} else {
+					self.run(|debugger| debugger.run_to_breakpoint(|_| true), &mut events);
+				}
+				Ok(Value::Null)
+			}
+			"threads" => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "machine" }] })),
+			"stackTrace" => self.stack_trace(),
+			"scopes" => self.scopes(),
+			"variables" => self.variables(arguments),
+			"continue" => {
+				self.run(|debugger| debugger.run_to_breakpoint(|_| true), &mut events);
+				Ok(json!({ "allThreadsContinued": true }))
+			}
+			"next" | "stepIn" | "stepOut" => {
+				self.run(
+					|debugger| {
+						debugger.machine_mut().step();
+						false
+					},
+					&mut events,
+				);
+				Ok(Value::Null)
+			}
+			"stepBack" => self.run_back(
+				|debugger| {
+					debugger.machine_mut().step_back();
+					false
+				},
+				&mut events,
+			),
+			"reverseContinue" => self.run_back(Debugger::run_back_to_breakpoint, &mut events),
+			"pause" => Ok(Value::Null),
+			"evaluate" => self.evaluate(arguments),
+			"disconnect" | "terminate" => Ok(Value::Null),
+			_ => Err(format!("unsupported request \"{}\"", command)),
+		};
+
+		let mut response = json!({
+			"type": "response",
+			"request_seq": request["seq"],
+			"command": command,
+			"success": result.is_ok(),
+		});
+
+		match result {
+			Ok(Value::Null) => {}
+			Ok(body) => response["body"] = body,
+			Err(message) => response["message"] = message.into(),
+		}
+
+		let mut messages = vec![response];
+		messages.append(&mut events);
+
+		for message in &mut messages {
+			self.seq += 1;
+			message["seq"] = self.seq.into();
+		}
+
+		messages
+	}
+
+	fn debugger(&self) -> Result<&Debugger, String> {
+		self.debugger.as_ref().ok_or_else(|| "no program launched".to_owned())
+	}
+
+	fn debugger_mut(&mut self) -> Result<&mut Debugger, String> {
+		self.debugger.as_mut().ok_or_else(|| "no program launched".to_owned())
+	}
+
+	fn launch(&mut self, arguments: &Value, events: &mut Vec<Value>) -> Result<Value, String> {
+		let program = arguments["program"].as_str().ok_or("missing program to launch")?;
+		let compile = arguments["compile"].as_bool().unwrap_or_else(|| {
+			Path::new(program)
+				.extension()
+				.is_some_and(|extension| extension == "asm")
+		});
+
+		self.debugger = Some(Debugger::new(load_machine(program, compile)?));
+		self.stop_on_entry = arguments["stopOnEntry"].as_bool().unwrap_or(false);
+
+		// Ready for the breakpoints now that the source locations are known
+		events.push(event("initialized", Value::Null));
+		Ok(Value::Null)
+	}
+
+	fn set_breakpoints(&mut self, arguments: &Value) -> Result<Value, String> {
+		let path = arguments["source"]["path"]
+			.as_str()
+			.ok_or("missing source path")?
+			.to_owned();
+		let lines = arguments["breakpoints"]
+			.as_array()
+			.into_iter()
+			.flatten()
+			.filter_map(|breakpoint| breakpoint["line"].as_u64())
+			.map(|line| line as usize)
+			.collect::<Vec<_>>();
+
+		self.breakpoints.insert(path.clone(), lines.clone());
+
+		// Borrow the fields separately, to go through the breakpoints while updating the debugger
+		let debugger = self.debugger.as_mut().ok_or("no program launched")?;
+
+		// Resolve the breakpoints of every source again, as the breakpoints of the debugger aren't per source
+		let mut addresses = Vec::new();
+		for (source, lines) in &self.breakpoints {
+			addresses.extend(
+				lines
+					.iter()
+					.filter_map(|line| line_address(debugger.machine(), source, *line)),
+			);
+		}
+		*debugger.breakpoints_mut() = addresses.into_iter().collect();
+
+		let breakpoints = lines
+			.iter()
+			.map(|line| match line_address(debugger.machine(), &path, *line) {
+				Some(_) => json!({ "verified": true, "line": line }),
+				None => json!({ "verified": false, "line": line, "message": "No instruction on this line" }),
+			})
+			.collect::<Vec<_>>();
+
+		Ok(json!({ "breakpoints": breakpoints }))
+	}
+
+	/// Runs the machine forward, and adds the events describing where it stopped.
+	/// The run returns whether it stopped at a breakpoint.
+	fn run(&mut self, run: impl FnOnce(&mut Debugger) -> bool, events: &mut Vec<Value>) {
+		let Some(debugger) = &mut self.debugger else {
+			return;
+		};
+
+		if !debugger.machine().is_running() {
+			// Continuing a machine that can't run anymore ends the session
+			let exit_code = (debugger.machine().exec_state != State::Halted) as i64;
+			events.push(event("exited", json!({ "exitCode": exit_code })));
+			events.push(event("terminated", Value::Null));
+			return;
+		}
+
+		let hit = run(debugger);
+		let machine = debugger.machine();
+
+		if !machine.is_running() {
+			events.push(event(
+				"output",
+				json!({ "category": "console", "output": format!("{}\n", status(machine)) }),
+			));
+		}
+
+		match machine.exec_state {
+			State::Halted => {
+				events.push(event("exited", json!({ "exitCode": 0 })));
+				events.push(event("terminated", Value::Null));
+			}
+
+			// Stop at the fault, so that it can be looked at
+			State::Failed | State::Stopped => {
+				let fault = machine
+					.trace()
+					.steps()
+					.last()
+					.and_then(|step| step.fault.clone().or(step.stop_reason.clone()));
+				events.push(self.stopped("exception", fault));
+			}
+
+			_ => events.push(self.stopped(if hit { "breakpoint" } else { "step" }, None)),
+		}
+	}
+
+	fn run_back(&mut self, run: impl FnOnce(&mut Debugger) -> bool, events: &mut Vec<Value>) -> Result<Value, String> {
+		let hit = run(self.debugger_mut()?);
+		events.push(self.stopped(if hit { "breakpoint" } else { "step" }, None));
+		Ok(Value::Null)
+	}
+
+	fn stopped(&self, reason: &str, text: Option<String>) -> Value {
+		let mut body = json!({ "reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true });
+
+		if let Some(text) = text {
+			body["description"] = "Machine stopped".into();
+			body["text"] = text.into();
+		}
+
+		event("stopped", body)
+	}
+
+	/// The single frame of the machine, at PC.
+	fn stack_trace(&self) -> Result<Value, String> {
+		let machine = self.debugger()?.machine();
+
+		let Some(pc) = machine.get_register_capability(Register::PC) else {
+			let frame = json!({ "id": 0, "name": "invalid PC", "line": 0, "column": 0 });
+			return Ok(json!({ "stackFrames": [frame], "totalFrames": 1 }));
+		};
+
+		let mut frame = json!({
+			"id": 0,
+			"name": machine.symbol_table().format_address(pc.address),
+			"line": 0,
+			"column": 0,
+			"instructionPointerReference": pc.address.to_string(),
+		});
+
+		if let Some(location) = machine.source_location(pc.address) {
+			frame["line"] = location.line.into();
+			frame["column"] = location.column.into();
+
+			if let Some(file) = &location.file {
+				let path = fs::canonicalize(file).map_or_else(|_| file.clone(), |path| path.display().to_string());
+				let name = Path::new(file)
+					.file_name()
+					.map_or_else(|| file.clone(), |name| name.to_string_lossy().into());
+				frame["source"] = json!({ "name": name, "path": path });
+			}
+		}
+
+		Ok(json!({ "stackFrames": [frame], "totalFrames": 1 }))
+	}
+
+	fn scopes(&self) -> Result<Value, String> {
+		let machine = self.debugger()?.machine();
+
+		Ok(json!({
+			"scopes": [
+				{ "name": "Registers", "variablesReference": REGISTERS, "expensive": false },
+				{
+					"name": "Memory",
+					"variablesReference": MEMORY,
+					"indexedVariables": machine.memory.mem_size(),
+					"expensive": true,
+				},
+			]
+		}))
+	}
+
+	fn variables(&self, arguments: &Value) -> Result<Value, String> {
+		let machine = self.debugger()?.machine();
+		let reference = arguments["variablesReference"]
+			.as_i64()
+			.ok_or("missing variables reference")?;
+
+		let variables = match reference {
+			REGISTERS => registers()
+				.map(|register| {
+					variable(
+						machine,
+						register.to_string(),
+						&machine.read_register(register),
+						REGISTER_CAPABILITIES + register_index(register),
+					)
+				})
+				.collect(),
+
+			MEMORY => {
+				let size = machine.memory.mem_size();
+				let start = (arguments["start"].as_u64().unwrap_or(0) as usize).min(size);
+				let count = arguments["count"].as_u64().map_or(size - start, |count| count as usize);
+
+				(start..start.saturating_add(count).min(size))
+					.map(Address)
+					.map(|address| {
+						let name = match machine.symbol_table().symbolize(address) {
+							Some(symbol) => format!("{} <{}>", address, symbol),
+							None => address.to_string(),
+						};
+
+						match &machine.memory[address] {
+							Row::Word(word) => variable(machine, name, word, MEMORY_CAPABILITIES + address.0 as i64),
+							row @ Row::Instruction(_) => json!({
+								"name": name,
+								"value": machine.symbol_table().format_row(row),
+								"type": "instruction",
+								"variablesReference": 0,
+							}),
+						}
+					})
+					.collect()
+			}
+
+			_ => {
+				let word = if reference >= MEMORY_CAPABILITIES {
+					let address = Address((reference - MEMORY_CAPABILITIES) as usize);
+
+					match address.0 < machine.memory.mem_size() {
+						true => match &machine.memory[address] {
+							Row::Word(word) => word.clone(),
+							Row::Instruction(_) => Word::default(),
+						},
+						false => Word::default(),
+					}
+				} else {
+					registers()
+						.find(|register| REGISTER_CAPABILITIES + register_index(*register) == reference)
+						.map(|register| machine.read_register(register))
+						.unwrap_or_default()
+				};
+
+				match word {
+					Word::Capability(capability) => capability_fields(machine, capability.inner()),
+					_ => Vec::new(),
+				}
+			}
+		};
+
+		Ok(json!({ "variables": variables }))
+	}
+
+	/// Evaluates a register (e.g. for hovers), or runs a debugger command.
+	fn evaluate(&mut self, arguments: &Value) -> Result<Value, String> {
+		let expression = arguments["expression"].as_str().ok_or("missing expression")?.trim();
+		let debugger = self.debugger_mut()?;
+
+		if let Some(register) = debugger::parse_register(expression) {
+			let machine = debugger.machine();
+			let word = machine.read_register(register);
+			let variable = variable(
+				machine,
+				String::new(),
+				&word,
+				REGISTER_CAPABILITIES + register_index(register),
+			);
+
+			return Ok(json!({ "result": variable["value"], "variablesReference": variable["variablesReference"] }));
+		}
+
+		if arguments["context"] != "repl" {
+			return Err(format!("cannot evaluate \"{}\"", expression));
+		}
+
+		let result = debugger.execute(expression).unwrap_or_default();
+		Ok(json!({ "result": result, "variablesReference": 0 }))
+	}
+}
+
+/// Loads the machine to debug, without panicking on invalid inputs.
+fn load_machine(program: &str, compile: bool) -> Result<Machine, String> {
+	let config = if compile {
+		ProgramConfig::from_path_as_source(program)
+			.try_compiled()
+			.map(MachineConfig::from_program)?
+	} else {
+		let file = File::open(program).map_err(|e| format!("Couldn't read {}: {}", program, e))?;
+		try_read_machine_config(file).map_err(|e| format!("Couldn't load {}: {}", program, e))?
+	};
+
+	Machine::try_initialize_from_config(config).map_err(|e| format!("Couldn't load {}: {}", program, e))
+}
+
+/// The address of the first instruction compiled from the line of the source, if any.
+fn line_address(machine: &Machine, source: &str, line: usize) -> Option<Address> {
+	let source = fs::canonicalize(source).ok();
+
+	machine
+		.memory
+		.source_locations()
+		.filter(|(address, location)| {
+			location.line == line
+				&& machine.memory.is_code(*address)
+				&& location
+					.file
+					.as_ref()
+					.is_some_and(|file| fs::canonicalize(file).ok() == source && source.is_some())
+		})
+		.map(|(address, _)| address)
+		.min()
+}
+
+/// PC, then R0 to R255.
+fn registers() -> impl Iterator<Item = Register> {
+	[Register::PC].into_iter().chain((0..=RegInt::MAX).map(Register::R))
+}
+
+fn register_index(register: Register) -> i64 {
+	match register {
+		Register::PC => RegInt::MAX as i64 + 1,
+		Register::R(n) => n as i64,
+	}
+}
+
+/// A variable holding the word, with the given reference to expand it if it's a capability.
+fn variable(machine: &Machine, name: String, word: &Word, reference: i64) -> Value {
+	let (kind, reference) = match word {
+		Word::Integer(_) => ("integer", 0),
+		Word::Char(_) => ("char", 0),
+		Word::Permission(_) => ("permission", 0),
+		Word::Capability(_) => ("capability", reference),
+	};
+
+	json!({
+		"name": name,
+		"value": machine.symbol_table().format_word(word),
+		"type": kind,
+		"variablesReference": reference,
+	})
+}
+
+/// The perm, base, end and address of the capability, as variables.
+fn capability_fields(machine: &Machine, capability: &Capability) -> Vec<Value> {
+	let symbols = machine.symbol_table();
+
+	let address = |address: Address| match symbols.symbolize(address) {
+		Some(symbol) => format!("{} <{}>", address, symbol),
+		None => address.to_string(),
+	};
+
+	[
+		("perm", capability.perm.to_string()),
+		("base", address(capability.base)),
+		("end", address(capability.end)),
+		("address", address(capability.address)),
+	]
+	.into_iter()
+	.map(|(name, value)| json!({ "name": name, "value": value, "variablesReference": 0 }))
+	.collect()
+}
+
+/// The status of the machine, like printed after emulation.
+fn status(machine: &Machine) -> String {
+	let state = machine.exec_state;
+
+	match machine.get_register_capability(Register::PC) {
+		Some(pc) => match machine.source_location(pc.address) {
+			Some(location) => format!("Machine status: {} at address {} ({})", state, pc.address, location),
+			None => format!("Machine status: {} at address {}", state, pc.address),
+		},
+		None => format!("Machine status: {} at invalid address", state),
+	}
+}
+
+fn event(name: &str, body: Value) -> Value {
+	let mut event = json!({ "type": "event", "event": name });
+
+	if !body.is_null() {
+		event["body"] = body;
+	}
+
+	event
+}
+
+/// Reads the next message, framed by a Content-Length header. Returns None when the input ends.
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+	let mut length = None;
+
+	loop {
+		let mut line = String::new();
+		if input.read_line(&mut line)? == 0 {
+			return Ok(None);
+		}
+
+		let line = line.trim();
+		if line.is_empty() {
+			if length.is_some() {
+				break;
+			}
+			continue;
+		}
+
+		if let Some(value) = line.strip_prefix("Content-Length:") {
+			length = value.trim().parse::<usize>().ok();
+		}
+	}
+
+	let length = length.unwrap_or_default();
+	if length > MAX_MESSAGE {
+		return Err(io::Error::new(
+			io::ErrorKind::InvalidData,
+			format!("Message of {} bytes, more than the {} accepted", length, MAX_MESSAGE),
+		));
+	}
+
+	let mut content = vec![0; length];
+	input.read_exact(&mut content)?;
+
+	serde_json::from_slice(&content)
+		.map(Some)
+		.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+	let content = message.to_string();
+	write!(output, "Content-Length: {}\r\n\r\n{}", content.len(), content)?;
+	output.flush()
+}
@@ -0,0 +1,606 @@
+use std::{
+	collections::BTreeSet,
+	io::{BufRead, Write},
+};
+
+use crate::{
+	emulator::{
+		machine::Machine,
+		permission::Permission,
+		program::{Address, Capability, RegInt, Register, Row, Word, WordInt},
+		signed::Signed,
+	},
+	util::pretty_hashmap,
+};
+
+/*
+--------------------------------------------------------------------------------
+||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||
+--------------------------------------------------------------------------------
+*/
+
+const HELP: &str = "\
+Commands:
+  step [n]              (s)  execute n instructions (default 1)
+  continue              (c)  run until a breakpoint or until the machine stops
+  until                 (u)  run until the machine state changes (interrupt, halt, fail)
+  back [n]              (rs) undo the last n steps (default 1)
+  goto <n>                   go backwards or forwards to right after step n
+  lastwrite <addr|reg>  (lw) go back to right before the step that last wrote to an address or register
+  break <addr|label>    (b)  set a breakpoint
+  delete <addr|label>   (d)  remove a breakpoint
+  info                  (i)  list breakpoints and labels
+  where                 (w)  show the current state and instruction
+  regs                  (r)  print all registers
+  mem <addr> [end]      (m)  print the memory rows from addr up to end (exclusive, default addr + 1)
+  cap <reg|addr>             print a capability in a register or in memory, decoded
+  provenance <reg|addr> (pv) print how a capability in a register or in memory was derived, step by step
+  set <reg> <value>          write a value to a register; value is an integer, 'c', a permission,
+                             or cap <perm> <base> <end> <address>
+  help                  (h)  show this message
+  quit                  (q)  exit the debugger
+Addresses can be integers (decimal or 0x-prefixed) or labels.";
+
+/// An interactive debugger, driving a machine one instruction at a time.
+pub struct Debugger {
+	machine: Machine,
+	breakpoints: BTreeSet<Address>,
+}
+
+impl Debugger {
+	/// Creates a debugger for the given machine and boots it, ready to execute its first instruction.
+	/// The history of the machine is recorded, so that it can be stepped backwards,
+	/// as well as the provenance of its capabilities.
+	pub fn new(mut machine: Machine) -> Self {
+		machine.boot();
+		machine.record_history();
+		machine.record_provenance();
+
+		Self {
+			machine,
+			breakpoints: BTreeSet::new(),
+		}
+	}
+
+	pub fn machine(&self) -> &Machine {
+		&self.machine
+	}
+
+	pub fn machine_mut(&mut self) -> &mut Machine {
+		&mut self.machine
+	}
+
+	pub fn breakpoints(&self) -> &BTreeSet<Address> {
+		&self.breakpoints
+	}
+
+	pub fn breakpoints_mut(&mut self) -> &mut BTreeSet<Address> {
+		&mut self.breakpoints
+	}
+
+	/// Runs the command loop, reading commands from the input until "quit" or until the input ends.
+	pub fn run(&mut self, mut input: impl BufRead, mut output: impl Write) {
+		writeln!(output, "{}", self.location()).expect("Could not write to output writer.");
+
+		loop {
+			write!(output, "(cerisemu) ").expect("Could not write to output writer.");
+			output.flush().expect("Could not write to output writer.");
+
+			let mut line = String::new();
+			if input.read_line(&mut line).expect("Couldn't read debugger command.") == 0 {
+				break;
+			}
+
+			let Some(out) = self.execute(&line) else {
+				break;
+			};
+
+			if !out.is_empty() {
+				writeln!(output, "{}", out).expect("Could not write to output writer.");
+			}
+		}
+	}
+
+	/// Executes a single debugger command and returns what should be shown to the user,
+	/// or None if the debugger should quit.
+	pub fn execute(&mut self, command: &str) -> Option<String> {
+		let words = command.split_whitespace().collect::<Vec<_>>();
+
+		let Some((command, args)) = words.split_first() else {
+			return Some(String::new());
+		};
+
+		let result = match command.to_lowercase().as_str() {
+			"q" | "quit" => return None,
+			"h" | "help" => Ok(HELP.to_owned()),
+			"s" | "step" => self.step(args),
+			"c" | "continue" => self.run_while(|_| true),
+			"u" | "until" => {
+				let state = self.machine.exec_state;
+				self.run_while(|machine| machine.exec_state == state)
+			}
+			"rs" | "back" => self.back(args),
+			"goto" => self.goto(args),
+			"lw" | "lastwrite" => self.last_write(args),
+			"b" | "break" => self.add_breakpoint(args),
+			"d" | "delete" => self.delete_breakpoint(args),
+			"i" | "info" => Ok(self.info()),
+			"w" | "where" => Ok(self.location()),
+			"r" | "regs" => Ok(pretty_hashmap(&self.machine.symbolic_registers())
+				.trim_start()
+				.to_owned()),
+			"m" | "mem" => self.memory(args),
+			"cap" => self.capability(args),
+			"pv" | "provenance" => self.provenance(args),
+			"set" => self.set(args),
+			_ => Err(format!(
+				"unknown command \"{}\", type \"help\" for a list of commands",
+				command
+			)),
+		};
+
+		Some(result.unwrap_or_else(|e| format!("Error: {}", e)))
+	}
+
+	fn step(&mut self, args: &[&str]) -> Result<String, String> {
+		let n = match args {
+			[] => 1,
+			[n] => parse_int(n)?,
+			_ => return Err("usage: step [n]".to_owned()),
+		};
+
+		self.check_running()?;
+
+		self.machine.run_for(n);
+
+		Ok(self.location())
+	}
+
+	/// Steps the machine as long as the condition holds, the machine is running, and no breakpoint is hit.
+	/// Always executes at least one step, so that continuing from a breakpoint works.
+	fn run_while(&mut self, condition: impl Fn(&Machine) -> bool) -> Result<String, String> {
+		self.check_running()?;
+
+		if self.run_to_breakpoint(condition) {
+			Ok(format!("Breakpoint hit\n{}", self.location()))
+		} else {
+			Ok(self.location())
+		}
+	}
+
+	/// Like the continue command, steps the machine as long as the condition holds, the machine is running,
+	/// and no breakpoint is hit. Returns whether a breakpoint was hit.
+	pub fn run_to_breakpoint(&mut self, condition: impl Fn(&Machine) -> bool) -> bool {
+		let breakpoints = &self.breakpoints;
+		let at_breakpoint = |machine: &Machine| {
+			machine
+				.get_register_capability(Register::PC)
+				.is_some_and(|pc| breakpoints.contains(&pc.address))
+		};
+
+		let mut hit = false;
+		self.machine.run_until(|machine| {
+			hit = at_breakpoint(machine);
+			hit || !condition(machine)
+		});
+
+		hit
+	}
+
+	/// Steps the machine backwards until a breakpoint is reached, or until the start of the history.
+	/// Returns whether a breakpoint was reached.
+	pub fn run_back_to_breakpoint(&mut self) -> bool {
+		while self.machine.step_back() {
+			let at_breakpoint = self
+				.machine
+				.get_register_capability(Register::PC)
+				.is_some_and(|pc| self.breakpoints.contains(&pc.address));
+
+			if at_breakpoint {
+				return true;
+			}
+		}
+
+		false
+	}
+
+	fn back(&mut self, args: &[&str]) -> Result<String, String> {
+		let n = match args {
+			[] => 1,
+			[n] => parse_int(n)?,
+			_ => return Err("usage: back [n]".to_owned()),
+		};
+
+		for _ in 0..n {
+			if !self.machine.step_back() {
+				return Err(format!("reached the start of the history\n{}", self.location()));
+			}
+		}
+
+		Ok(self.location())
+	}
+
+	fn goto(&mut self, args: &[&str]) -> Result<String, String> {
+		let [n] = args else {
+			return Err("usage: goto <n>".to_owned());
+		};
+
+		let step = parse_int(n)?;
+
+		if self.machine.goto_step(step) {
+			Ok(self.location())
+		} else if self
+			.machine
+			.history()
+			.is_some_and(|history| step < history.first_step())
+		{
+			Err(format!("step {} isn't recorded anymore\n{}", n, self.location()))
+		} else {
+			Err(format!("the machine stopped before step {}\n{}", n, self.location()))
+		}
+	}
+
+	fn last_write(&mut self, args: &[&str]) -> Result<String, String> {
+		let [arg] = args else {
+			return Err("usage: lastwrite <addr|reg>".to_owned());
+		};
+
+		let history = self.machine.history().ok_or("no history recorded")?;
+
+		let step = match parse_register(arg) {
+			Some(register) => history.last_register_write(register),
+			None => history.last_write(self.resolve_address(arg)?),
+		}
+		.ok_or(format!("{} wasn't written to during the recorded history", arg))?;
+
+		self.machine.goto_step(step);
+
+		Ok(format!(
+			"{} was last written by step {}\n{}",
+			arg,
+			step,
+			self.location()
+		))
+	}
+
+	fn add_breakpoint(&mut self, args: &[&str]) -> Result<String, String> {
+		let [arg] = args else {
+			return Err("usage: break <addr|label>".to_owned());
+		};
+
+		let addresses = self.resolve_addresses(arg)?;
+		self.breakpoints.extend(addresses.iter().copied());
+
+		Ok(format!("Breakpoint set at {}", format_addresses(&addresses)))
+	}
+
+	fn delete_breakpoint(&mut self, args: &[&str]) -> Result<String, String> {
+		let [arg] = args else {
+			return Err("usage: delete <addr|label>".to_owned());
+		};
+
+		let addresses = self.resolve_addresses(arg)?;
+		for address in &addresses {
+			self.breakpoints.remove(address);
+		}
+
+		Ok(format!("Breakpoint removed at {}", format_addresses(&addresses)))
+	}
+
+	fn info(&self) -> String {
+		let breakpoints = self
+			.breakpoints
+			.iter()
+			.map(|&address| format!("  {}{}", address, self.label_suffix(address)))
+			.collect::<Vec<_>>();
+
+		let mut symbols = self.machine.symbols().to_vec();
+		symbols.sort_by_key(|(_, address)| *address);
+		let labels = symbols
+			.iter()
+			.map(|(label, address)| format!("  {}: {}", address, label))
+			.collect::<Vec<_>>();
+
+		format!(
+			"Breakpoints:\n{}\nLabels:\n{}",
+			if breakpoints.is_empty() {
+				"  (None)".to_owned()
+			} else {
+				breakpoints.join("\n")
+			},
+			if labels.is_empty() {
+				"  (None)".to_owned()
+			} else {
+				labels.join("\n")
+			},
+		)
+	}
+
+	fn memory(&self, args: &[&str]) -> Result<String, String> {
+		let (start, end) = match args {
+			[start] => {
+				let start = self.resolve_address(start)?;
+				(start, start + 1)
+			}
+			[start, end] => (self.resolve_address(start)?, self.resolve_address(end)?),
+			_ => return Err("usage: mem <addr> [end]".to_owned()),
+		};
+
+		if start >= end || end.0 > self.machine.memory.mem_size() {
+			return Err(format!("invalid memory range {}..{}", start, end));
+		}
+
+		let rows = (start.0..end.0)
+			.map(Address)
+			.map(|address| {
+				format!(
+					"{}{} | {}",
+					address,
+					self.label_suffix(address),
+					self.machine.memory[address]
+				)
+			})
+			.collect::<Vec<_>>();
+
+		Ok(rows.join("\n"))
+	}
+
+	fn capability(&self, args: &[&str]) -> Result<String, String> {
+		let [arg] = args else {
+			return Err("usage: cap <reg|addr>".to_owned());
+		};
+
+		let (name, signed) = self.read_capability(arg)?;
+
+		Ok(self.describe_capability(&name, signed))
+	}
+
+	fn provenance(&self, args: &[&str]) -> Result<String, String> {
+		let [arg] = args else {
+			return Err("usage: provenance <reg|addr>".to_owned());
+		};
+
+		let (name, signed) = self.read_capability(arg)?;
+
+		let chain = self
+			.machine
+			.format_provenance(signed.inner())
+			.ok_or("no provenance recorded")?;
+
+		Ok(format!("{} = {}", name, chain))
+	}
+
+	/// Reads the capability in a register or in memory, along with a name for it.
+	fn read_capability(&self, arg: &str) -> Result<(String, Signed<Capability>), String> {
+		let (name, word) = match parse_register(arg) {
+			Some(register) => (format!("{}", register), self.machine.read_register(register)),
+			None => {
+				let address = self.resolve_address(arg)?;
+				if address.0 >= self.machine.memory.mem_size() {
+					return Err(format!("address {} is out of memory", address));
+				}
+
+				match &self.machine.memory[address] {
+					Row::Word(word) => (format!("{}", address), word.clone()),
+					Row::Instruction(i) => return Err(format!("{} holds an instruction ({})", address, i)),
+				}
+			}
+		};
+
+		let Word::Capability(signed) = word else {
+			return Err(format!("{} is not a capability ({})", name, word));
+		};
+
+		Ok((name, signed))
+	}
+
+	fn describe_capability(&self, name: &str, signed: Signed<Capability>) -> String {
+		let Capability {
+			perm,
+			base,
+			end,
+			address,
+		} = *signed.inner();
+
+		let signature = if self.machine.verify_capability(signed.clone()).is_some() {
+			"valid"
+		} else {
+			"INVALID"
+		};
+
+		format!(
+			"{} = {}\n  permission: {}\n  bounds:     [{}, {}) (length {})\n  address:    {}{} (offset {})\n  signature:  {}",
+			name,
+			signed,
+			perm,
+			base,
+			end,
+			end.0 as WordInt - base.0 as WordInt,
+			address,
+			self.label_suffix(address),
+			address.0 as WordInt - base.0 as WordInt,
+			signature,
+		)
+	}
+
+	fn set(&mut self, args: &[&str]) -> Result<String, String> {
+		let Some((register, value)) = args.split_first() else {
+			return Err("usage: set <reg> <value>".to_owned());
+		};
+
+		let register = parse_register(register).ok_or(format!("invalid register \"{}\"", register))?;
+
+		let value = match value {
+			[value] => parse_word(value)?,
+			["cap", perm, base, end, address] => {
+				let capability = Capability {
+					perm: parse_permission(perm).ok_or(format!("invalid permission \"{}\"", perm))?,
+					base: self.resolve_address(base)?,
+					end: self.resolve_address(end)?,
+					address: self.resolve_address(address)?,
+				};
+				Word::Capability(self.machine.sign_capability(capability))
+			}
+			_ => return Err("usage: set <reg> <value>".to_owned()),
+		};
+
+		self.machine.write_register(register, value.clone());
+
+		Ok(format!("{} = {}", register, value))
+	}
+
+	/// Describes the current state of the machine and the instruction it is about to execute.
+	pub fn location(&self) -> String {
+		let state = self.machine.exec_state;
+
+		let Some(address) = self.pc_address() else {
+			return format!(
+				"{}{} at invalid address (PC = {})",
+				self.step_prefix(),
+				state,
+				self.machine.read_register(Register::PC)
+			);
+		};
+
+		let row = if address.0 < self.machine.memory.mem_size() {
+			format!("{}", self.machine.memory[address])
+		} else {
+			"(out of memory)".to_owned()
+		};
+
+		format!(
+			"{}{} at {}{} | {}",
+			self.step_prefix(),
+			state,
+			address,
+			self.label_suffix(address),
+			row
+		)
+	}
+
+	fn step_prefix(&self) -> String {
+		self.machine
+			.history()
+			.map(|history| format!("[step {}] ", history.position()))
+			.unwrap_or_default()
+	}
+
+	fn check_running(&self) -> Result<(), String> {
+		if self.machine.is_running() {
+			Ok(())
+		} else {
+			Err(format!("the machine is not running ({})", self.machine.exec_state))
+		}
+	}
+
+	fn pc_address(&self) -> Option<Address> {
+		self.machine
+			.get_register_capability(Register::PC)
+			.map(|capability| capability.address)
+	}
+
+	fn label_suffix(&self, address: Address) -> String {
+		self.machine
+			.symbols()
+			.iter()
+			.filter(|(_, a)| *a == address)
+			.map(|(label, _)| format!(" <{}>", label))
+			.collect()
+	}
+
+	/// Resolves an address argument, which is either an integer or a label.
+	/// A label defined by several programs resolves to all of its addresses.
+	fn resolve_addresses(&self, arg: &str) -> Result<Vec<Address>, String> {
+		if let Ok(address) = parse_int(arg) {
+			return Ok(vec![Address(address)]);
+		}
+
+		let addresses = self
+			.machine
+			.symbols()
+			.iter()
+			.filter(|(label, _)| label == arg)
+			.map(|(_, address)| *address)
+			.collect::<Vec<_>>();
+
+		if addresses.is_empty() {
+			Err(format!("\"{}\" is neither an address nor a known label", arg))
+		} else {
+			Ok(addresses)
+		}
+	}
+
+	pub(crate) fn resolve_address(&self, arg: &str) -> Result<Address, String> {
+		match self.resolve_addresses(arg)?.as_slice() {
+			[address] => Ok(*address),
+			addresses => Err(format!(
+				"label \"{}\" is ambiguous ({})",
+				arg,
+				format_addresses(addresses)
+			)),
+		}
+	}
+}
+
+fn format_addresses(addresses: &[Address]) -> String {
+	addresses
+		.iter()
+		.map(|address| format!("{}", address))
+		.collect::<Vec<_>>()
+		.join(", ")
+}
+
+fn parse_int<T: TryFrom<WordInt>>(s: &str) -> Result<T, String> {
+	let (negative, digits) = match s.strip_prefix('-') {
+		Some(digits) => (true, digits),
+		None => (false, s),
+	};
+
+	let value = match digits.strip_prefix("0x").or(digits.strip_prefix("0X")) {
+		Some(hex) => WordInt::from_str_radix(hex, 16),
+		None => digits.parse::<WordInt>(),
+	}
+	.map_err(|_| format!("invalid integer \"{}\"", s))?;
+
+	let value = if negative { -value } else { value };
+
+	T::try_from(value).map_err(|_| format!("integer out of range \"{}\"", s))
+}
+
+pub(crate) fn parse_register(s: &str) -> Option<Register> {
+	let s = s.to_lowercase();
+
+	if s == "pc" {
+		return Some(Register::PC);
+	}
+
+	s.strip_prefix('r')?.parse::<RegInt>().ok().map(Register::R)
+}
+
+fn parse_permission(s: &str) -> Option<Permission> {
+	match s.to_uppercase().as_str() {
+		"O" => Some(Permission::O),
+		"E" => Some(Permission::E),
+		"RO" => Some(Permission::RO),
+		"RX" => Some(Permission::RX),
+		"RW" => Some(Permission::RW),
+		"RWX" => Some(Permission::RWX),
+		_ => None,
+	}
+}
+
+fn parse_word(s: &str) -> Result<Word, String> {
+	if let Some(c) = s.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')) {
+		let mut chars = c.chars();
+		return match (chars.next(), chars.next()) {
+			(Some(c), None) => Ok(Word::Char(c)),
+			_ => Err(format!("invalid character {}", s)),
+		};
+	}
+
+	if let Some(perm) = parse_permission(s) {
+		return Ok(Word::Permission(perm));
+	}
+
+	parse_int::<WordInt>(s).map(Word::Integer)
+}
@@ -1,13 +1,29 @@
 use self::{machine::Machine, machine_config::MachineConfig};
 
+pub mod backtrace;
+pub mod coverage;
+pub mod diff;
+pub mod dump;
+pub mod encoding;
 pub mod exec;
+pub mod history;
 pub mod instruction;
+pub mod leak;
 pub mod machine;
 pub mod machine_config;
 pub mod memory;
+pub mod observer;
 pub mod permission;
+pub mod profile;
 pub mod program;
+pub mod provenance;
+pub mod rules;
+pub mod semantics;
 pub mod signed;
+pub mod step_log;
+pub mod stop_condition;
+pub mod symbols;
+pub mod trace;
 
 /*
 --------------------------------------------------------------------------------
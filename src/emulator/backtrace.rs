@@ -0,0 +1,126 @@
+use std::cmp::max;
+
+use super::{
+	machine::{Interrupt, State},
+	program::Register,
+	symbols::SymbolTable,
+	trace::{Effect, Trace},
+};
+
+/*
+--------------------------------------------------------------------------------
+||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||
+--------------------------------------------------------------------------------
+*/
+
+/// A human-readable rendering of a trace, printed in columns.
+/// Every step is one entry, with additional entries for interrupts and for the machine stopping.
+/// Addresses in capabilities are rendered symbolically using the given symbol table.
+#[derive(Clone, Copy, Debug)]
+pub struct Backtrace<'a> {
+	trace: &'a Trace,
+	symbols: &'a SymbolTable,
+}
+
+impl<'a> Backtrace<'a> {
+	pub fn new(trace: &'a Trace, symbols: &'a SymbolTable) -> Self {
+		Self { trace, symbols }
+	}
+
+	pub fn entries(&self) -> Vec<Vec<String>> {
+		let mut entries = Vec::new();
+
+		for step in self.trace.steps() {
+			let mut entry = vec![
+				format!("State: {}", step.state),
+				format!("PC: {}", self.symbols.format_word(&step.pc)),
+			];
+
+			match (&step.instruction, &step.source) {
+				(Some(instruction), Some(source)) => entry.push(format!("Instruction: {} @ {}", instruction, source)),
+				(Some(instruction), None) => entry.push(format!("Instruction: {}", instruction)),
+				_ => {}
+			}
+
+			for effect in &step.effects {
+				match effect {
+					Effect::Register { register, value } if *register != Register::PC => {
+						entry.push(format!("{} = {}", register, self.symbols.format_word(value)))
+					}
+					Effect::Jump { target, taken: true } => {
+						entry.push(format!("Jumping to {}", self.symbols.format_word(target)))
+					}
+					Effect::Jump { taken: false, .. } => entry.push("NOT jumping".to_string()),
+					_ => {}
+				}
+			}
+
+			if let Some(fault) = &step.fault {
+				entry.push(format!("Error: {}", fault));
+			}
+
+			if let Some(result) = step.result {
+				entry.push(format!("New State: {}", result));
+			}
+
+			entries.push(entry);
+
+			if let Some(interrupt) = step.interrupt {
+				entries.push(vec![
+					format!("Interrupt: {}", interrupt),
+					format!("New State: {}", State::Interrupted(interrupt)),
+				]);
+			}
+
+			let stop_message = match step.new_state {
+				State::Halted => format!("{} Interrupt not recoverable", Interrupt::Halt),
+				State::Failed => format!("{} Interrupt not recoverable", Interrupt::Fail),
+				State::Stopped => format!(
+					"Stop condition met: {}",
+					step.stop_reason.as_deref().unwrap_or_default()
+				),
+				_ => continue,
+			};
+
+			entries.push(vec![stop_message]);
+			entries.push(vec![format!("State: {}", step.new_state), "Shutting down".to_string()]);
+		}
+
+		entries
+	}
+
+	pub fn print(&self) {
+		println!("Machine backtrace:");
+
+		let entries = self.entries();
+
+		if entries.is_empty() {
+			println!("(Empty)");
+			return;
+		}
+
+		let collumn_sizes = entries.iter().fold(Vec::<usize>::new(), |mut acc, trace| {
+			while acc.len() < trace.len() {
+				acc.push(0)
+			}
+			for (i, a) in acc.iter_mut().enumerate() {
+				if i < trace.len() - 1 {
+					*a = max(*a, trace.get(i).map(String::len).unwrap_or(0))
+				}
+			}
+			acc
+		});
+
+		for trace in &entries {
+			println!(
+				"> {}",
+				trace
+					.iter()
+					.enumerate()
+					.map(|(i, s)| format!("{:<width$}", s, width = collumn_sizes[i]))
+					.collect::<Vec<String>>()
+					.join(" | ")
+			);
+		}
+	}
+}
@@ -0,0 +1,248 @@
+use std::{
+	collections::BTreeMap,
+	fmt::Write as _,
+	fs,
+	io::{self, Write},
+};
+
+use super::{
+	encoding,
+	instruction::Instruction,
+	machine::Machine,
+	memory::Memory,
+	observer::ExecObserver,
+	program::{Address, LabelIdentifier, Row, Word},
+};
+
+/*
+--------------------------------------------------------------------------------
+||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||
+--------------------------------------------------------------------------------
+*/
+
+/// The file name used for programs that weren't compiled from a file, like in SourceLocation.
+pub const NO_FILE: &str = "<source>";
+
+/// How many times a jnz jumped and didn't jump.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Branch {
+	pub taken: usize,
+	pub not_taken: usize,
+}
+
+/// The coverage of a single source line.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LineCoverage {
+	/// The instructions of the line, in memory order
+	pub rows: Vec<Address>,
+
+	/// How many times the line was executed, i.e. the most any of its instructions was executed
+	pub hits: usize,
+
+	/// The branches of the jnz instructions of the line, None if the jnz was never executed
+	pub branches: Vec<Option<Branch>>,
+
+	/// The labels defined on the first row of the line
+	pub labels: Vec<LabelIdentifier>,
+}
+
+/// An observer recording which rows were executed and which directions every jnz took.
+/// The coverage is mapped back to the source lines using the source locations of the rows in memory,
+/// see source_lines(), write_lcov() and annotated_listing().
+///
+/// Like the source locations, the coverage is about the rows as they were loaded:
+/// rows overwritten and executed later on are still attributed to the line they were loaded from.
+#[derive(Clone, Debug, Default)]
+pub struct Coverage {
+	rows: BTreeMap<Address, usize>,
+	branches: BTreeMap<Address, Branch>,
+
+	/// The address of the jnz being executed, if the current instruction is one
+	jnz: Option<Address>,
+}
+
+impl Coverage {
+	/// How many times the row at every executed address was executed.
+	pub fn rows(&self) -> &BTreeMap<Address, usize> {
+		&self.rows
+	}
+
+	/// The directions taken by the jnz at every executed address.
+	pub fn branches(&self) -> &BTreeMap<Address, Branch> {
+		&self.branches
+	}
+
+	/// The coverage of every source line containing instructions, by file and line.
+	pub fn source_lines(&self, memory: &Memory) -> BTreeMap<String, BTreeMap<usize, LineCoverage>> {
+		let mut files = BTreeMap::<String, BTreeMap<usize, LineCoverage>>::new();
+
+		for (address, location) in memory.source_locations() {
+			if !memory.is_code(address) {
+				continue;
+			}
+
+			let file = location.file.clone().unwrap_or_else(|| NO_FILE.to_owned());
+			let line = files.entry(file).or_default().entry(location.line).or_default();
+
+			if line.rows.is_empty() {
+				line.labels = memory.symbols().labels_at(address).cloned().collect();
+			}
+
+			line.rows.push(address);
+			line.hits = line.hits.max(self.rows.get(&address).copied().unwrap_or(0));
+
+			// The row may be encoded, see Machine::load_program()
+			let is_jnz = self.branches.contains_key(&address)
+				|| match &memory[address] {
+					Row::Instruction(instruction) => matches!(instruction, Instruction::Jnz(..)),
+					Row::Word(Word::Integer(z)) => matches!(encoding::decode(*z), Ok(Instruction::Jnz(..))),
+					_ => false,
+				};
+
+			if is_jnz {
+				line.branches.push(self.branches.get(&address).copied());
+			}
+		}
+
+		files
+	}
+
+	/// Writes the coverage of the source lines in the lcov tracefile format, one record per source file.
+	/// Labels are reported as functions, and every jnz as a block of two branches (taken, not taken).
+	pub fn write_lcov(&self, memory: &Memory, mut output: impl Write) -> io::Result<()> {
+		for (file, lines) in self.source_lines(memory) {
+			writeln!(output, "TN:")?;
+			writeln!(output, "SF:{}", file)?;
+
+			let functions = lines
+				.iter()
+				.flat_map(|(number, line)| line.labels.iter().map(move |label| (*number, label, line.hits)))
+				.collect::<Vec<_>>();
+
+			for (number, label, _) in &functions {
+				writeln!(output, "FN:{},{}", number, label)?;
+			}
+			for (_, label, hits) in &functions {
+				writeln!(output, "FNDA:{},{}", hits, label)?;
+			}
+			writeln!(output, "FNF:{}", functions.len())?;
+			writeln!(
+				output,
+				"FNH:{}",
+				functions.iter().filter(|(_, _, hits)| *hits > 0).count()
+			)?;
+
+			let mut branches_found = 0;
+			let mut branches_hit = 0;
+
+			for (number, line) in &lines {
+				for (block, branch) in line.branches.iter().enumerate() {
+					let counts = match branch {
+						Some(branch) => [branch.taken.to_string(), branch.not_taken.to_string()],
+						None => ["-".to_owned(), "-".to_owned()],
+					};
+
+					for (i, count) in counts.iter().enumerate() {
+						writeln!(output, "BRDA:{},{},{},{}", number, block, i, count)?;
+					}
+
+					branches_found += 2;
+					branches_hit += branch.map_or(0, |branch| {
+						(branch.taken > 0) as usize + (branch.not_taken > 0) as usize
+					});
+				}
+			}
+			writeln!(output, "BRF:{}", branches_found)?;
+			writeln!(output, "BRH:{}", branches_hit)?;
+
+			for (number, line) in &lines {
+				writeln!(output, "DA:{},{}", number, line.hits)?;
+			}
+			writeln!(output, "LF:{}", lines.len())?;
+			writeln!(output, "LH:{}", lines.values().filter(|line| line.hits > 0).count())?;
+
+			writeln!(output, "end_of_record")?;
+		}
+
+		output.flush()
+	}
+
+	/// Renders every source file with the number of times each line was executed in front of it,
+	/// "#####" for lines containing instructions that were never executed, and "-" for lines without instructions.
+	/// Lines with a jnz are followed by how many times it jumped and didn't.
+	///
+	/// Files that can't be read (like programs not compiled from a file) are rendered from their instructions instead.
+	pub fn annotated_listing(&self, memory: &Memory) -> String {
+		let mut out = String::new();
+
+		for (file, lines) in self.source_lines(memory) {
+			let hit = lines.values().filter(|line| line.hits > 0).count();
+			let _ = writeln!(out, "{} ({}/{} lines executed)", file, hit, lines.len());
+
+			let source = fs::read_to_string(&file).ok();
+
+			let rendered = match &source {
+				Some(source) => source
+					.lines()
+					.enumerate()
+					.map(|(i, text)| (lines.get(&(i + 1)), text.to_owned()))
+					.collect::<Vec<_>>(),
+
+				None => lines
+					.values()
+					.map(|line| {
+						let text = line
+							.rows
+							.iter()
+							.map(|address| memory.symbols().format_row(&memory[*address]))
+							.collect::<Vec<_>>()
+							.join(", ");
+						(Some(line), text)
+					})
+					.collect::<Vec<_>>(),
+			};
+
+			for (line, text) in rendered {
+				let count = match line {
+					Some(line) if line.hits > 0 => line.hits.to_string(),
+					Some(_) => "#####".to_owned(),
+					None => "-".to_owned(),
+				};
+
+				let _ = writeln!(out, "{:>9} | {}", count, text);
+
+				for branch in line.iter().flat_map(|line| &line.branches) {
+					let branch = match branch {
+						Some(branch) => format!("jumped {}, not jumped {}", branch.taken, branch.not_taken),
+						None => "never executed".to_owned(),
+					};
+
+					let _ = writeln!(out, "{:>9} | ^ jnz {}", "", branch);
+				}
+			}
+
+			out.push('\n');
+		}
+
+		out
+	}
+}
+
+impl ExecObserver for Coverage {
+	fn before_instruction(&mut self, _: &Machine, address: Address, instruction: &Instruction) {
+		*self.rows.entry(address).or_default() += 1;
+		self.jnz = matches!(instruction, Instruction::Jnz(..)).then_some(address);
+	}
+
+	fn jump(&mut self, _: &Machine, _: &Word, taken: bool) {
+		if let Some(address) = self.jnz {
+			let branch = self.branches.entry(address).or_default();
+
+			if taken {
+				branch.taken += 1;
+			} else {
+				branch.not_taken += 1;
+			}
+		}
+	}
+}
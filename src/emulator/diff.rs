@@ -0,0 +1,186 @@
+use std::collections::BTreeSet;
+
+use super::{
+	dump::MachineDump,
+	machine::{Interrupt, State},
+	memory::Memory,
+	program::{Address, Register, Row, Word},
+	semantics::Semantics,
+	symbols::SymbolTable,
+};
+
+/*
+--------------------------------------------------------------------------------
+||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||
+--------------------------------------------------------------------------------
+*/
+
+/// The differences between two machines, e.g. the same program run under two kernels.
+///
+/// Capabilities are compared by value, ignoring their signatures: two machines never share their keys,
+/// and dumped machines have no signatures at all.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MachineDiff {
+	pub exec_state: Option<(State, State)>,
+	pub stop_reason: Option<(Option<String>, Option<String>)>,
+	pub encoded_instructions: Option<(bool, bool)>,
+	pub semantics: Option<(Semantics, Semantics)>,
+
+	/// The differing registers, a register missing from a machine being zero
+	pub registers: Vec<(Register, Word, Word)>,
+
+	/// The differing interrupt table entries, None if a machine has no entry for the interrupt
+	pub interrupt_table: Vec<(Interrupt, Option<Address>, Option<Address>)>,
+
+	pub memory_size: Option<(usize, usize)>,
+
+	/// The differing memory rows, rows beyond the end of the smaller memory being zero
+	pub memory: Vec<(Address, Row, Row)>,
+}
+
+impl MachineDiff {
+	pub fn new(a: &MachineDump, b: &MachineDump) -> Self {
+		fn differs<T: PartialEq + Clone>(a: &T, b: &T) -> Option<(T, T)> {
+			(a != b).then(|| (a.clone(), b.clone()))
+		}
+
+		let registers = a
+			.registers
+			.keys()
+			.chain(b.registers.keys())
+			.collect::<BTreeSet<_>>()
+			.into_iter()
+			.filter_map(|register| {
+				let word_a = a.registers.get(register).cloned().unwrap_or_default();
+				let word_b = b.registers.get(register).cloned().unwrap_or_default();
+				(!same_word(&word_a, &word_b)).then_some((*register, word_a, word_b))
+			})
+			.collect();
+
+		let interrupt_table = a
+			.interrupt_table
+			.keys()
+			.chain(b.interrupt_table.keys())
+			.collect::<BTreeSet<_>>()
+			.into_iter()
+			.filter_map(|interrupt| {
+				let address_a = a.interrupt_table.get(interrupt).copied();
+				let address_b = b.interrupt_table.get(interrupt).copied();
+				(address_a != address_b).then_some((*interrupt, address_a, address_b))
+			})
+			.collect();
+
+		let size_a = a.memory.mem_size();
+		let size_b = b.memory.mem_size();
+
+		let row = |memory: &Memory, address: Address| match address.0 < memory.mem_size() {
+			true => memory[address].clone(),
+			false => Row::default(),
+		};
+
+		let memory = (0..size_a.max(size_b))
+			.map(Address)
+			.filter_map(|address| {
+				let row_a = row(&a.memory, address);
+				let row_b = row(&b.memory, address);
+				(!same_row(&row_a, &row_b)).then_some((address, row_a, row_b))
+			})
+			.collect();
+
+		Self {
+			exec_state: differs(&a.exec_state, &b.exec_state),
+			stop_reason: differs(&a.stop_reason, &b.stop_reason),
+			encoded_instructions: differs(&a.encoded_instructions, &b.encoded_instructions),
+			semantics: differs(&a.semantics, &b.semantics),
+			registers,
+			interrupt_table,
+			memory_size: differs(&size_a, &size_b),
+			memory,
+		}
+	}
+
+	pub fn is_empty(&self) -> bool {
+		*self == Self::default()
+	}
+
+	/// Renders the differences one per line, as "- " followed by the first machine's value
+	/// and "+ " followed by the second machine's, with addresses rendered using the given symbols.
+	pub fn format(&self, symbols: &SymbolTable) -> String {
+		if self.is_empty() {
+			return "The machines are identical.".to_owned();
+		}
+
+		let mut out = Vec::new();
+
+		let mut push = |what: String, a: String, b: String| {
+			out.push(what);
+			out.push(format!("  - {}", a));
+			out.push(format!("  + {}", b));
+		};
+
+		if let Some((a, b)) = &self.exec_state {
+			push("Exec state:".to_owned(), a.to_string(), b.to_string());
+		}
+
+		if let Some((a, b)) = &self.stop_reason {
+			let reason = |reason: &Option<String>| reason.clone().unwrap_or_else(|| "(None)".to_owned());
+			push("Stop reason:".to_owned(), reason(a), reason(b));
+		}
+
+		if let Some((a, b)) = &self.encoded_instructions {
+			push("Encoded instructions:".to_owned(), a.to_string(), b.to_string());
+		}
+
+		if let Some((a, b)) = &self.semantics {
+			push("Semantics:".to_owned(), format!("{:?}", a), format!("{:?}", b));
+		}
+
+		for (register, a, b) in &self.registers {
+			push(
+				format!("Register {}:", register),
+				symbols.format_word(a),
+				symbols.format_word(b),
+			);
+		}
+
+		for (interrupt, a, b) in &self.interrupt_table {
+			let address = |address: &Option<Address>| address.map_or_else(|| "(None)".to_owned(), |a| a.to_string());
+			push(format!("Interrupt {}:", interrupt), address(a), address(b));
+		}
+
+		if let Some((a, b)) = &self.memory_size {
+			push("Memory size:".to_owned(), a.to_string(), b.to_string());
+		}
+
+		for (address, a, b) in &self.memory {
+			let location = match symbols.symbolize(*address) {
+				Some(symbol) => format!("{} ({})", address, symbol),
+				None => address.to_string(),
+			};
+
+			push(
+				format!("Memory {}:", location),
+				symbols.format_row(a),
+				symbols.format_row(b),
+			);
+		}
+
+		out.join("\n")
+	}
+}
+
+/// Compares the words, ignoring the signatures of capabilities.
+pub fn same_word(a: &Word, b: &Word) -> bool {
+	match (a, b) {
+		(Word::Capability(a), Word::Capability(b)) => a.inner() == b.inner(),
+		_ => a == b,
+	}
+}
+
+/// Compares the rows, ignoring the signatures of capabilities.
+pub fn same_row(a: &Row, b: &Row) -> bool {
+	match (a, b) {
+		(Row::Word(a), Row::Word(b)) => same_word(a, b),
+		_ => a == b,
+	}
+}
@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use super::{
+	machine::{Interrupt, Machine, State},
+	memory::Memory,
+	program::{Address, Register, Word},
+	semantics::Semantics,
+};
+
+/*
+--------------------------------------------------------------------------------
+||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||
+--------------------------------------------------------------------------------
+*/
+
+/// A machine as dumped by `cerisemu emulate --dump`, to be inspected after the fact.
+///
+/// The keys of the machine aren't dumped, so the capabilities of a dump have no signature
+/// and the machine can't be resumed from it.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename = "Machine")]
+pub struct MachineDump {
+	pub exec_state: State,
+
+	#[serde(default)]
+	pub stop_reason: Option<String>,
+
+	pub registers: HashMap<Register, Word>,
+	pub interrupt_table: HashMap<Interrupt, Address>,
+	pub memory: Memory,
+
+	#[serde(default)]
+	pub encoded_instructions: bool,
+
+	#[serde(default)]
+	pub semantics: Semantics,
+}
+
+impl From<&Machine> for MachineDump {
+	fn from(machine: &Machine) -> Self {
+		Self {
+			exec_state: machine.exec_state,
+			stop_reason: machine.stop_reason().map(str::to_owned),
+			registers: machine.registers().clone(),
+			interrupt_table: machine.interrupt_table().clone(),
+			memory: machine.memory.clone(),
+			encoded_instructions: machine.encoded_instructions(),
+			semantics: machine.semantics(),
+		}
+	}
+}
@@ -0,0 +1,332 @@
+use super::{
+	instruction::{Instruction, RegisterOrWord},
+	permission::Permission,
+	program::{Program, Register, Row, Word, WordInt},
+};
+
+/*
+--------------------------------------------------------------------------------
+||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||
+--------------------------------------------------------------------------------
+*/
+
+// Binary layout of an encoded instruction, from the least significant bit upwards:
+//
+//   [ opcode: 8 bits ][ operand 1 ][ operand 2 ][ operand 3 ] 0...
+//
+// The operands are packed one after the other in the order they appear in the assembly syntax.
+// Their width depends on their type:
+//   - register:          9 bits; 0 = PC, n + 1 = Rn
+//   - register or word: 23 bits; 2 bits of tag followed by 21 bits of payload
+//        tag 0: register, payload is a register (as above)
+//        tag 1: integer, payload is a 21-bit two's complement integer
+//        tag 2: char, payload is the unicode scalar value
+//        tag 3: permission, payload is a permission, see encode_permission()
+//
+// The widest instructions (e.g. add r ρ1 ρ2 or store r ρ1 ρ2) take 8 + 9 + 23 + 23 = 63 bits,
+// so every encoded instruction is a non-negative WordInt. All unused bits must be zero.
+//
+// The opcode 0 is fail, meaning that empty (zeroed) memory decodes to fail.
+//
+// The layout is versioned by ENCODING_VERSION, which encoded programs record (see Program::encoding_version),
+// so that programs encoded with another layout are rejected rather than decoded into other instructions.
+// New instructions take unused opcodes and keep the version, but any change to the operands of an existing
+// instruction bumps it:
+//   1: the first layout
+//   2: load r1 r2 ρ and store r ρ1 ρ2 take an offset as second operand, they used to be load r1 r2 and store r ρ
+//   3: restrict r ρ takes a register or word, it used to take a 3-bit permission
+
+const OPCODE_BITS: u32 = 8;
+const REGISTER_BITS: u32 = 9;
+const TAG_BITS: u32 = 2;
+const PAYLOAD_BITS: u32 = 21;
+
+const TAG_REGISTER: u64 = 0;
+const TAG_INTEGER: u64 = 1;
+const TAG_CHAR: u64 = 2;
+const TAG_PERMISSION: u64 = 3;
+
+/// The version of the layout above.
+pub const ENCODING_VERSION: u32 = 3;
+
+/// The smallest integer immediate that can be encoded.
+pub const MIN_IMMEDIATE: WordInt = -(1 << (PAYLOAD_BITS - 1));
+/// The largest integer immediate that can be encoded.
+pub const MAX_IMMEDIATE: WordInt = (1 << (PAYLOAD_BITS - 1)) - 1;
+
+#[rustfmt::skip]
+mod opcode {
+	pub const FAIL:     u64 = 0x00;
+	pub const HALT:     u64 = 0x01;
+	pub const MOV:      u64 = 0x02;
+	pub const LOAD:     u64 = 0x03;
+	pub const STORE:    u64 = 0x04;
+	pub const JMP:      u64 = 0x05;
+	pub const JNZ:      u64 = 0x06;
+	pub const RESTRICT: u64 = 0x07;
+	pub const SUBSEG:   u64 = 0x08;
+	pub const LEA:      u64 = 0x09;
+	pub const ADD:      u64 = 0x0A;
+	pub const SUB:      u64 = 0x0B;
+	pub const LT:       u64 = 0x0C;
+	pub const GETP:     u64 = 0x0D;
+	pub const GETB:     u64 = 0x0E;
+	pub const GETE:     u64 = 0x0F;
+	pub const GETA:     u64 = 0x10;
+	pub const ISPTR:    u64 = 0x11;
+	pub const JAL:      u64 = 0x12;
+	pub const GETL:     u64 = 0x13;
+	pub const GETO:     u64 = 0x14;
+	pub const SETA:     u64 = 0x15;
+	pub const SETBOUNDS: u64 = 0x16;
+	pub const SUBSET:   u64 = 0x17;
+	pub const CEQ:      u64 = 0x18;
+	pub const FLOWS:    u64 = 0x19;
+}
+
+/// Encodes an instruction into a single integer word.
+/// Fails if one of the immediates cannot be represented (capabilities, or integers that are too large).
+#[rustfmt::skip]
+pub fn encode(instruction: &Instruction) -> Result<WordInt, String> {
+	let mut e = Encoder::default();
+
+	match instruction {
+		Instruction::Fail              => e.opcode(opcode::FAIL),
+		Instruction::Halt              => e.opcode(opcode::HALT),
+		Instruction::Mov(r, p)         => e.opcode(opcode::MOV).register(r).reg_or_word(p)?,
+		Instruction::Load(r1, r2, p)   => e.opcode(opcode::LOAD).register(r1).register(r2).reg_or_word(p)?,
+		Instruction::Store(r, p1, p2)  => e.opcode(opcode::STORE).register(r).reg_or_word(p1)?.reg_or_word(p2)?,
+		Instruction::Jmp(r)            => e.opcode(opcode::JMP).register(r),
+		Instruction::Jnz(r1, r2)       => e.opcode(opcode::JNZ).register(r1).register(r2),
+		Instruction::Jal(r1, r2)       => e.opcode(opcode::JAL).register(r1).register(r2),
+		Instruction::Restrict(r, p)    => e.opcode(opcode::RESTRICT).register(r).reg_or_word(p)?,
+		Instruction::Subseg(r, p1, p2) => e.opcode(opcode::SUBSEG).register(r).reg_or_word(p1)?.reg_or_word(p2)?,
+		Instruction::Lea(r, p)         => e.opcode(opcode::LEA).register(r).reg_or_word(p)?,
+		Instruction::Add(r, p1, p2)    => e.opcode(opcode::ADD).register(r).reg_or_word(p1)?.reg_or_word(p2)?,
+		Instruction::Sub(r, p1, p2)    => e.opcode(opcode::SUB).register(r).reg_or_word(p1)?.reg_or_word(p2)?,
+		Instruction::Lt(r, p1, p2)     => e.opcode(opcode::LT).register(r).reg_or_word(p1)?.reg_or_word(p2)?,
+		Instruction::Getp(r1, r2)      => e.opcode(opcode::GETP).register(r1).register(r2),
+		Instruction::Getb(r1, r2)      => e.opcode(opcode::GETB).register(r1).register(r2),
+		Instruction::Gete(r1, r2)      => e.opcode(opcode::GETE).register(r1).register(r2),
+		Instruction::Geta(r1, r2)      => e.opcode(opcode::GETA).register(r1).register(r2),
+		Instruction::Isptr(r1, r2)     => e.opcode(opcode::ISPTR).register(r1).register(r2),
+		Instruction::Getl(r1, r2)      => e.opcode(opcode::GETL).register(r1).register(r2),
+		Instruction::Geto(r1, r2)      => e.opcode(opcode::GETO).register(r1).register(r2),
+		Instruction::Seta(r, p)        => e.opcode(opcode::SETA).register(r).reg_or_word(p)?,
+		Instruction::Setbounds(r, p)   => e.opcode(opcode::SETBOUNDS).register(r).reg_or_word(p)?,
+		Instruction::Subset(r1, r2, r3) => e.opcode(opcode::SUBSET).register(r1).register(r2).register(r3),
+		Instruction::Ceq(r1, r2, r3)    => e.opcode(opcode::CEQ).register(r1).register(r2).register(r3),
+		Instruction::Flows(r, p1, p2)  => e.opcode(opcode::FLOWS).register(r).reg_or_word(p1)?.reg_or_word(p2)?,
+	};
+
+	Ok(e.bits as WordInt)
+}
+
+/// decode(𝑧) from Cerise.
+/// Decodes an integer word back into an instruction.
+/// Fails if the integer isn't a valid encoding, so that decode(encode(𝑖)) = 𝑖 and encode(decode(𝑧)) = 𝑧 always hold.
+#[rustfmt::skip]
+pub fn decode(z: WordInt) -> Result<Instruction, String> {
+	if z < 0 {
+		return Err(format!("{} is negative", z));
+	}
+
+	let mut d = Decoder { bits: z as u64 };
+
+	let instruction = match d.pop(OPCODE_BITS) {
+		opcode::FAIL     => Instruction::Fail,
+		opcode::HALT     => Instruction::Halt,
+		opcode::MOV      => Instruction::Mov     (d.register()?, d.reg_or_word()?),
+		opcode::LOAD     => Instruction::Load    (d.register()?, d.register()?, d.reg_or_word()?),
+		opcode::STORE    => Instruction::Store   (d.register()?, d.reg_or_word()?, d.reg_or_word()?),
+		opcode::JMP      => Instruction::Jmp     (d.register()?),
+		opcode::JNZ      => Instruction::Jnz     (d.register()?, d.register()?),
+		opcode::JAL      => Instruction::Jal     (d.register()?, d.register()?),
+		opcode::RESTRICT => Instruction::Restrict(d.register()?, d.reg_or_word()?),
+		opcode::SUBSEG   => Instruction::Subseg  (d.register()?, d.reg_or_word()?, d.reg_or_word()?),
+		opcode::LEA      => Instruction::Lea     (d.register()?, d.reg_or_word()?),
+		opcode::ADD      => Instruction::Add     (d.register()?, d.reg_or_word()?, d.reg_or_word()?),
+		opcode::SUB      => Instruction::Sub     (d.register()?, d.reg_or_word()?, d.reg_or_word()?),
+		opcode::LT       => Instruction::Lt      (d.register()?, d.reg_or_word()?, d.reg_or_word()?),
+		opcode::GETP     => Instruction::Getp    (d.register()?, d.register()?),
+		opcode::GETB     => Instruction::Getb    (d.register()?, d.register()?),
+		opcode::GETE     => Instruction::Gete    (d.register()?, d.register()?),
+		opcode::GETA     => Instruction::Geta    (d.register()?, d.register()?),
+		opcode::ISPTR    => Instruction::Isptr   (d.register()?, d.register()?),
+		opcode::GETL     => Instruction::Getl    (d.register()?, d.register()?),
+		opcode::GETO     => Instruction::Geto    (d.register()?, d.register()?),
+		opcode::SETA     => Instruction::Seta    (d.register()?, d.reg_or_word()?),
+		opcode::SETBOUNDS => Instruction::Setbounds(d.register()?, d.reg_or_word()?),
+		opcode::SUBSET   => Instruction::Subset  (d.register()?, d.register()?, d.register()?),
+		opcode::CEQ      => Instruction::Ceq     (d.register()?, d.register()?, d.register()?),
+		opcode::FLOWS    => Instruction::Flows   (d.register()?, d.reg_or_word()?, d.reg_or_word()?),
+		op => return Err(format!("unknown opcode {:#x}", op)),
+	};
+
+	if d.bits != 0 {
+		return Err(format!("{:#x} has unused bits set", z));
+	}
+
+	Ok(instruction)
+}
+
+#[rustfmt::skip]
+pub fn encode_permission(permission: Permission) -> u64 {
+	match permission {
+		Permission::O   => 0,
+		Permission::E   => 1,
+		Permission::RO  => 2,
+		Permission::RX  => 3,
+		Permission::RW  => 4,
+		Permission::RWX => 5,
+	}
+}
+
+#[rustfmt::skip]
+pub fn decode_permission(code: u64) -> Option<Permission> {
+	match code {
+		0 => Some(Permission::O),
+		1 => Some(Permission::E),
+		2 => Some(Permission::RO),
+		3 => Some(Permission::RX),
+		4 => Some(Permission::RW),
+		5 => Some(Permission::RWX),
+		_ => None,
+	}
+}
+
+impl Program {
+	/// Returns the same program with every instruction row replaced by its encoded integer word.
+	pub fn encoded(self) -> Result<Program, String> {
+		let rows = self
+			.rows
+			.into_iter()
+			.map(|row| match row {
+				Row::Instruction(instruction) => encode(&instruction)
+					.map(|z| Row::Word(Word::Integer(z)))
+					.map_err(|e| format!("couldn't encode \"{}\": {}", instruction, e)),
+				row => Ok(row),
+			})
+			.collect::<Result<Vec<_>, String>>()?;
+
+		Ok(Program {
+			rows,
+			encoding_version: Some(ENCODING_VERSION),
+			..self
+		})
+	}
+}
+
+/*
+--------------------------------------------------------------------------------
+||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||
+--------------------------------------------------------------------------------
+*/
+
+#[derive(Default)]
+struct Encoder {
+	bits: u64,
+	offset: u32,
+}
+
+impl Encoder {
+	fn push(&mut self, value: u64, width: u32) -> &mut Self {
+		debug_assert!(value < (1 << width), "Encoded field doesn't fit its width");
+		debug_assert!(
+			self.offset + width < u64::BITS,
+			"Encoded instruction doesn't fit a word"
+		);
+
+		self.bits |= value << self.offset;
+		self.offset += width;
+		self
+	}
+
+	fn opcode(&mut self, opcode: u64) -> &mut Self {
+		self.push(opcode, OPCODE_BITS)
+	}
+
+	fn register(&mut self, register: &Register) -> &mut Self {
+		self.push(encode_register(register), REGISTER_BITS)
+	}
+
+	fn reg_or_word(&mut self, reg_or_word: &RegisterOrWord) -> Result<&mut Self, String> {
+		let (tag, payload) = match reg_or_word {
+			RegisterOrWord::Register(r) => (TAG_REGISTER, encode_register(r)),
+
+			RegisterOrWord::Word(Word::Integer(i)) => {
+				if !(MIN_IMMEDIATE..=MAX_IMMEDIATE).contains(i) {
+					return Err(format!(
+						"immediate {} out of range [{}, {}]",
+						i, MIN_IMMEDIATE, MAX_IMMEDIATE
+					));
+				}
+				(TAG_INTEGER, (*i as u64) & ((1 << PAYLOAD_BITS) - 1))
+			}
+
+			RegisterOrWord::Word(Word::Char(c)) => (TAG_CHAR, *c as u64),
+			RegisterOrWord::Word(Word::Permission(p)) => (TAG_PERMISSION, encode_permission(*p)),
+			RegisterOrWord::Word(Word::Capability(c)) => {
+				return Err(format!("capability immediate {} cannot be encoded", c))
+			}
+		};
+
+		Ok(self.push(tag, TAG_BITS).push(payload, PAYLOAD_BITS))
+	}
+}
+
+struct Decoder {
+	bits: u64,
+}
+
+impl Decoder {
+	fn pop(&mut self, width: u32) -> u64 {
+		let value = self.bits & ((1 << width) - 1);
+		self.bits >>= width;
+		value
+	}
+
+	fn register(&mut self) -> Result<Register, String> {
+		decode_register(self.pop(REGISTER_BITS))
+	}
+
+	fn reg_or_word(&mut self) -> Result<RegisterOrWord, String> {
+		let tag = self.pop(TAG_BITS);
+		let payload = self.pop(PAYLOAD_BITS);
+
+		Ok(match tag {
+			TAG_REGISTER => RegisterOrWord::Register(decode_register(payload)?),
+
+			TAG_INTEGER => {
+				// Sign-extend the payload
+				let shift = u64::BITS - PAYLOAD_BITS;
+				RegisterOrWord::Word(Word::Integer(((payload << shift) as WordInt) >> shift))
+			}
+
+			TAG_CHAR => RegisterOrWord::Word(Word::Char(
+				char::from_u32(payload as u32).ok_or(format!("invalid char code {:#x}", payload))?,
+			)),
+
+			TAG_PERMISSION => RegisterOrWord::Word(Word::Permission(
+				decode_permission(payload).ok_or(format!("invalid permission code {}", payload))?,
+			)),
+
+			_ => unreachable!(),
+		})
+	}
+}
+
+fn encode_register(register: &Register) -> u64 {
+	match register {
+		Register::PC => 0,
+		Register::R(n) => *n as u64 + 1,
+	}
+}
+
+fn decode_register(code: u64) -> Result<Register, String> {
+	match code {
+		0 => Ok(Register::PC),
+		n if n <= 256 => Ok(Register::R((n - 1) as u8)),
+		n => Err(format!("invalid register code {}", n)),
+	}
+}
@@ -1,10 +1,12 @@
 use crate::util::Lattice;
 
 use super::{
+	encoding,
 	instruction::{Instruction, RegisterOrWord},
-	machine::{Interrupt, Machine, State},
+	machine::{Interrupt, Machine, State, StepOutcome},
 	permission::Permission,
 	program::{AddrInt, Address, Capability, Register, Row, Word, WordInt},
+	rules::{self, Rule, Violation},
 };
 
 /*
@@ -15,85 +17,156 @@ use super::{
 
 impl Machine {
 	/// Executes an entire emulation loop.
-	/// The PC register is first initialized with a (RWX, 0, MAX_ADDRESS, 0) capability.
-	/// This loop is stopped when the machine reaches a HALTED or FAILED state.
+	/// The machine is first booted, then stepped until it reaches a HALTED or FAILED state.
 	pub fn exec_machine(&mut self) {
-		self.exec_state = State::Running;
+		self.boot();
+		self.run_until(|_| false);
+	}
 
+	/// Prepares the machine for execution.
+	/// The PC register is initialized with a (RWX, 0, MAX_ADDRESS, 0) capability.
+	pub fn boot(&mut self) {
 		// Create the master capability that the OS will own. Copy it to PC.
-		let master_capa = self.sign_capability(Capability {
+		self.boot_with_pc(self.master_capability());
+	}
+
+	/// The capability PC starts with when booting, (RWX, 0, MAX_ADDRESS, 0).
+	pub fn master_capability(&self) -> Capability {
+		Capability {
 			perm: Permission::top(),
 			base: Address(0x0),
 			end: Address(self.memory.mem_size()),
 			address: Address(0x0),
-		});
+		}
+	}
+
+	/// Prepares the machine for execution, starting with the given capability in PC instead of the master capability.
+	pub fn boot_with_pc(&mut self, pc: Capability) {
+		self.exec_state = State::Running;
 
-		self.write_register(Register::PC, Word::Capability(master_capa));
+		let pc = self.sign_capability(pc);
+		self.write_register(Register::PC, Word::Capability(pc));
+	}
 
+	/// Steps the machine until the predicate holds after a step, or until the machine stops.
+	/// Returns the outcome of the last step.
+	pub fn run_until(&mut self, mut predicate: impl FnMut(&Machine) -> bool) -> StepOutcome {
 		loop {
-			let new_state = self.exec_single();
+			let outcome = self.step();
 
-			match new_state {
-				// Machine is running normally; continue
-				State::Running => {
-					self.exec_state = new_state;
-					self.append_backtrace(format!("New State: {}", new_state));
-					continue;
-				}
+			if matches!(outcome, StepOutcome::Stopped(_)) || predicate(self) {
+				return outcome;
+			}
+		}
+	}
 
-				// Machine failed or halted while trying to recover from an interrupt; stop the machine for good
-				State::Halted | State::Failed if matches!(self.exec_state, State::Interrupted(_)) => {
-					// Return the machine to state it triggered the interrupt with
-					let (new_state, interrupt) = match self.exec_state {
-						State::Interrupted(Interrupt::Halt) => (State::Halted, Interrupt::Halt),
-						State::Interrupted(Interrupt::Fail) => (State::Failed, Interrupt::Fail),
-						_ => unreachable!(),
-					};
+	/// Steps the machine at most n times, or until the machine stops.
+	/// Returns the outcome of the last step (or Executed if n is 0 and the machine is running).
+	pub fn run_for(&mut self, n: usize) -> StepOutcome {
+		let mut outcome = if self.is_running() {
+			StepOutcome::Executed
+		} else {
+			StepOutcome::Stopped(self.exec_state)
+		};
 
-					self.exec_state = new_state;
-					self.new_backtrace(format!("{} Interrupt not recoverable", interrupt));
+		for _ in 0..n {
+			outcome = self.step();
 
-					break;
-				}
+			if let StepOutcome::Stopped(_) = outcome {
+				break;
+			}
+		}
+
+		outcome
+	}
+
+	/// Whether the machine has been booted and hasn't stopped yet.
+	pub fn is_running(&self) -> bool {
+		matches!(self.exec_state, State::Running | State::Interrupted(_))
+	}
+
+	/// Executes a single iteration of the emulation loop: a single instruction, recovering with an interrupt if needed.
+	/// Does nothing if the machine isn't running (it hasn't been booted, or it stopped already).
+	pub fn step(&mut self) -> StepOutcome {
+		if !self.is_running() {
+			return StepOutcome::Stopped(self.exec_state);
+		}
+
+		self.begin_history_step();
 
-				// Machine halted or failed; attempt to recover with an interrupt and continue
-				State::Halted | State::Failed => {
-					let interrupt = match new_state {
-						State::Halted => Interrupt::Halt,
-						State::Failed => Interrupt::Fail,
-						_ => unreachable!(),
-					};
+		let new_state = self.exec_single();
 
-					let Row::Word(destination) = self.get_interrupt_memory(interrupt) else {
-						// If recovery is impossible because the destination row isn't a Word,
-						// then terminate with the appropriate state
-						self.exec_state = new_state;
-						self.new_backtrace(format!("{} Interrupt not recoverable", interrupt));
-						break;
-					};
+		let outcome = match new_state {
+			// Machine is running normally; continue
+			State::Running => {
+				self.exec_state = new_state;
+				StepOutcome::Executed
+			}
 
-					self.append_backtrace(format!("New State: {}", new_state));
+			// Machine failed or halted while trying to recover from an interrupt; stop the machine for good
+			State::Halted | State::Failed if matches!(self.exec_state, State::Interrupted(_)) => {
+				// Return the machine to state it triggered the interrupt with
+				let new_state = match self.exec_state {
+					State::Interrupted(Interrupt::Halt) => State::Halted,
+					State::Interrupted(Interrupt::Fail) => State::Failed,
+					_ => unreachable!(),
+				};
 
+				self.exec_state = new_state;
+				StepOutcome::Stopped(new_state)
+			}
+
+			// Machine halted or failed; attempt to recover with an interrupt and continue
+			State::Halted | State::Failed => {
+				let interrupt = match new_state {
+					State::Halted => Interrupt::Halt,
+					State::Failed => Interrupt::Fail,
+					_ => unreachable!(),
+				};
+
+				if let Row::Word(destination) = self.get_interrupt_memory(interrupt) {
 					// Mark the state as interrupted so we can terminate if the machine fails to recover
 					self.exec_state = State::Interrupted(interrupt);
-
-					self.new_backtrace(format!("Interrupt: {}", interrupt));
-					self.append_backtrace(format!("New State: {}", self.exec_state));
+					self.notify(|o, m| o.interrupt(m, interrupt, &destination));
 
 					// If recovering is possible, attempt to continue execution at the interrupt destination
-					self.write_register(Register::PC, self.update_pc_perm(destination));
-					continue;
+					let new_pc = self.update_pc_perm(destination);
+					self.write_register(Register::PC, new_pc);
+					StepOutcome::Interrupted(interrupt)
+				} else {
+					// If recovery is impossible because the destination row isn't a Word,
+					// then terminate with the appropriate state
+					self.exec_state = new_state;
+					StepOutcome::Stopped(new_state)
 				}
+			}
+
+			State::Interrupted(_) | State::Stopped => {
+				unreachable!("ExecSingle should not return an Interrupted or Stopped State.")
+			}
+		};
 
-				State::Interrupted(_) => unreachable!("ExecSingle should not return an Interrupted State."),
+		// Stop the machine early if a stop condition was met during this step, even if it stopped for good already
+		let outcome = match outcome {
+			StepOutcome::Executed | StepOutcome::Interrupted(_) if self.check_stop_conditions(true) => {
+				StepOutcome::Stopped(State::Stopped)
 			}
+			StepOutcome::Stopped(State::Halted | State::Failed) if self.check_stop_conditions(false) => {
+				StepOutcome::Stopped(State::Stopped)
+			}
+			_ => outcome,
+		};
+
+		if let StepOutcome::Stopped(state) = outcome {
+			self.notify(|o, m| o.stopped(m, state));
 		}
 
-		self.new_backtrace(format!("State: {}", self.exec_state));
-		self.append_backtrace("Shutting down".to_string());
+		self.notify(|o, m| o.after_step(m, outcome));
+
+		outcome
 	}
 
-	/// ExecSingle from Cerise.
+	/// ExecSingle from Cerise, see rules::EXEC_SINGLE.
 	/// First performs necessary checks, then executes a single instruction.
 	///
 	/// Cerise specs:
@@ -102,10 +175,10 @@ impl Machine {
 	///      then [decode(𝑧)](𝜑)
 	///      else (Failed, 𝜑)
 	///
-	/// Since we don't need decoding of instructions in our emulator, we replace decode() with exec_instruction() instead.
+	/// Instructions are usually stored directly as instruction rows, in which case no decoding is needed.
+	/// If the machine runs with encoded instructions, integer words are decoded with encoding::decode() first.
 	fn exec_single(&mut self) -> State {
-		self.new_backtrace(format!("State: {}", self.exec_state));
-		self.append_backtrace(format!("PC: {}", self.read_register(Register::PC)));
+		self.notify(|o, m| o.before_step(m));
 
 		let Some(Capability {
 			perm,
@@ -114,33 +187,51 @@ impl Machine {
 			address,
 		}) = self.get_register_capability(Register::PC)
 		else {
-			self.append_backtrace("Error: Invalid PC, not a capability".to_string());
-			return State::Failed;
+			let pc = self.read_register(Register::PC);
+			return self.fetch_violation(rules::PC_CAPABILITY, &[("φ.reg(pc)", pc.to_string())]);
 		};
 
-		if !(base <= address && address < end && perm >= Permission::RX) {
-			self.append_backtrace(format!(
-				"Error: Invalid PC, address ({}) out of bounds or invalid permission ({})",
-				address, perm
-			));
-			return State::Failed;
+		if !(base <= address && address < end) {
+			return self.fetch_violation(
+				rules::PC_IN_BOUNDS,
+				&[
+					("b", base.to_string()),
+					("a", address.to_string()),
+					("e", end.to_string()),
+				],
+			);
 		}
 
-		let Row::Instruction(instruction) = self.memory[address].clone() else {
-			self.append_backtrace(format!(
-				"Error: Invalid instruction where PC is pointing to ({})",
-				self.memory[address]
-			));
-			return State::Failed;
+		// Permissions are only partially ordered, so this is not the same as perm < RX
+		#[allow(clippy::neg_cmp_op_on_partial_ord)]
+		if !(perm >= Permission::RX) {
+			return self.fetch_violation(rules::PC_EXECUTABLE, &[("p", perm.to_string())]);
+		}
+
+		let instruction = match self.memory[address].clone() {
+			Row::Instruction(instruction) => instruction,
+
+			// In encoded mode, integer words are decoded into instructions
+			Row::Word(Word::Integer(z)) if self.encoded_instructions() => match encoding::decode(z) {
+				Ok(instruction) => instruction,
+				Err(e) => return self.fetch_violation(rules::INSTRUCTION, &[("decode(φ.mem(a))", e)]),
+			},
+
+			row => return self.fetch_violation(rules::INSTRUCTION, &[("φ.mem(a)", row.to_string())]),
 		};
 
-		self.append_backtrace(format!("Instruction: {}", instruction));
-		self.exec_instruction(instruction)
+		self.notify(|o, m| o.before_instruction(m, address, &instruction));
+		let state = self.exec_instruction(&instruction);
+		self.notify(|o, m| o.after_instruction(m, address, &instruction, state));
+
+		state
 	}
 
 	/// Executes a single instruction, applies any side effects of that instruction to the machine itself, and returns the resulting machine state.
-	fn exec_instruction(&mut self, instruction: Instruction) -> State {
-		match instruction {
+	/// The conditions of every instruction are the premises of its rule in rules.rs, which failures point to,
+	/// see rules::instruction_rule(); the comments on the arms below keep both in sync with the code.
+	fn exec_instruction(&mut self, instruction: &Instruction) -> State {
+		match instruction.clone() {
 			// Effect:
 			// 	(Failed, 𝜑)
 			Instruction::Fail => State::Failed,
@@ -162,15 +253,17 @@ impl Machine {
 			}
 
 			// Instruction:
-			// 	load 𝑟1 𝑟2
-			// Conditions:
+			// 	load 𝑟1 𝑟2 𝜌
+			// Conditions (EXTENDED FROM CERISE):
 			// 	𝜑.reg(𝑟2) = (𝑝, 𝑏, 𝑒, 𝑎)
+			// 	𝑧 = getWord(𝜑, 𝜌)
+			// 	𝑧 ∈ Z
 			// 	𝑝 ∈ {ro, rx, rw, rwx}
-			// 	𝑏 ≤ 𝑎 < 𝑒
-			// 	𝑤 = 𝜑.mem(𝑎)
+			// 	𝑏 ≤ 𝑎 + 𝑧 < 𝑒
+			// 	𝑤 = 𝜑.mem(𝑎 + 𝑧)
 			// Effect:
 			// 	updPC(𝜑[reg.𝑟1 ↦ 𝑤])
-			Instruction::Load(r1, r2) => {
+			Instruction::Load(r1, r2, p) => {
 				let Some(Capability {
 					perm,
 					base,
@@ -178,24 +271,35 @@ impl Machine {
 					address,
 				}) = self.get_register_capability(r2)
 				else {
-					self.append_backtrace(format!(
-						"Error: Invalid register r2 ({}), not a capability",
-						self.read_register(r2)
-					));
-					return State::Failed;
+					let w = self.read_register(r2);
+					return self.violation(instruction, rules::R2_CAPABILITY, &[("φ.reg(r2)", w.to_string())]);
 				};
 
-				if !(base <= address && address < end && perm >= Permission::RO) {
-					self.append_backtrace(format!("Error: Invalid address ({}) or permission ({})", address, perm));
-					return State::Failed;
+				let word = self.get_word(p);
+				let Word::Integer(z) = word else {
+					return self.violation(instruction, rules::Z_INTEGER, &[("z", word.to_string())]);
 				};
 
-				let Row::Word(w) = self.memory[address].clone() else {
-					self.append_backtrace(format!(
-						"Error: Invalid address ({}), not a word ({})",
-						address, self.memory[address]
-					));
-					return State::Failed;
+				#[allow(clippy::neg_cmp_op_on_partial_ord)]
+				if !(perm >= Permission::RO) {
+					return self.violation(instruction, rules::READABLE, &[("p", perm.to_string())]);
+				}
+
+				let Some(address) = offset_address(address, z).filter(|a| base <= *a && *a < end) else {
+					return self.violation(
+						instruction,
+						rules::OFFSET_IN_BOUNDS,
+						&[
+							("b", base.to_string()),
+							("a + z", offset_term(address, z)),
+							("e", end.to_string()),
+						],
+					);
+				};
+
+				let Row::Word(w) = self.read_memory(address) else {
+					let row = self.memory[address].to_string();
+					return self.violation(instruction, rules::LOADED_WORD, &[("φ.mem(a + z)", row)]);
 				};
 
 				self.write_register(r1, w);
@@ -203,15 +307,17 @@ impl Machine {
 			}
 
 			// Instruction:
-			// 	store 𝑟 𝜌
-			// Conditions:
+			// 	store 𝑟 𝜌1 𝜌2
+			// Conditions (EXTENDED FROM CERISE):
 			// 	𝜑.reg(𝑟) = (𝑝, 𝑏, 𝑒, 𝑎)
+			// 	𝑧 = getWord(𝜑, 𝜌1)
+			// 	𝑧 ∈ Z
 			// 	𝑝 ∈ {rw, rwx}
-			// 	𝑏 ≤ 𝑎 < 𝑒
-			// 	𝑤 = getWord(𝜑, 𝜌)
+			// 	𝑏 ≤ 𝑎 + 𝑧 < 𝑒
+			// 	𝑤 = getWord(𝜑, 𝜌2)
 			// Effect:
-			// 	updPC(𝜑[mem.𝑎 ↦ 𝑤])
-			Instruction::Store(r, p) => {
+			// 	updPC(𝜑[mem.(𝑎 + 𝑧) ↦ 𝑤])
+			Instruction::Store(r, p1, p2) => {
 				let Some(Capability {
 					perm,
 					base,
@@ -219,21 +325,35 @@ impl Machine {
 					address,
 				}) = self.get_register_capability(r)
 				else {
-					self.append_backtrace(format!(
-						"Error: Invalid register r ({}), not a capability",
-						self.read_register(r)
-					));
-					return State::Failed;
+					let w = self.read_register(r);
+					return self.violation(instruction, rules::R_CAPABILITY, &[("φ.reg(r)", w.to_string())]);
 				};
 
-				if !(base <= address && address < end && perm >= Permission::RW) {
-					self.append_backtrace(format!("Error: Invalid address ({}) or permission ({})", address, perm));
-					return State::Failed;
+				let word = self.get_word(p1);
+				let Word::Integer(z) = word else {
+					return self.violation(instruction, rules::Z_INTEGER, &[("z", word.to_string())]);
+				};
+
+				#[allow(clippy::neg_cmp_op_on_partial_ord)]
+				if !(perm >= Permission::RW) {
+					return self.violation(instruction, rules::WRITABLE, &[("p", perm.to_string())]);
 				}
 
-				let w = self.get_word(p);
+				let Some(address) = offset_address(address, z).filter(|a| base <= *a && *a < end) else {
+					return self.violation(
+						instruction,
+						rules::OFFSET_IN_BOUNDS,
+						&[
+							("b", base.to_string()),
+							("a + z", offset_term(address, z)),
+							("e", end.to_string()),
+						],
+					);
+				};
+
+				let w = self.get_word(p2);
 
-				self.memory[address] = Row::Word(w);
+				self.write_memory(address, Row::Word(w));
 				self.upd_pc()
 			}
 
@@ -249,7 +369,7 @@ impl Machine {
 
 				self.write_register(Register::PC, new_pc);
 
-				self.append_backtrace(format!("Jumping to {}", value));
+				self.notify(|o, m| o.jump(m, &value, true));
 
 				State::Running
 			}
@@ -264,55 +384,96 @@ impl Machine {
 			// 	else updPC(𝜑)
 			Instruction::Jnz(r1, r2) => {
 				let value = self.read_register(r1);
-				let new_pc = self.update_pc_perm(value.clone());
 
+				// updatePcPerm derives a new capability, so it only runs when the jump is taken
 				if self.read_register(r2) != Word::Integer(0) {
+					let new_pc = self.update_pc_perm(value.clone());
 					self.write_register(Register::PC, new_pc);
 
-					self.append_backtrace(format!("Jumping to {}", value));
+					self.notify(|o, m| o.jump(m, &value, true));
 
 					State::Running
 				} else {
-					self.append_backtrace("NOT jumping".to_string());
+					self.notify(|o, m| o.jump(m, &value, false));
 					self.upd_pc()
 				}
 			}
 
+			// Instruction:
+			// 	jal 𝑟1 𝑟2
+			// Conditions:
+			// 	𝜑.reg(pc) = (𝑝, 𝑏, 𝑒, 𝑎)
+			// 	𝑤 = (e, 𝑏, 𝑒, 𝑎 + 1)
+			// 	newPc = updatePcPerm(𝜑.reg(𝑟1))
+			// Effect:
+			// 	(Running, 𝜑[reg.𝑟2 ↦ 𝑤][reg.pc ↦ newPc])
+			Instruction::Jal(r1, r2) => {
+				let Some(parent) = self.get_register_capability(Register::PC) else {
+					let pc = self.read_register(Register::PC);
+					return self.violation(instruction, rules::PC_CAPABILITY, &[("φ.reg(pc)", pc.to_string())]);
+				};
+
+				let Capability { address, .. } = parent;
+
+				// The target is read before writing the link, in case both registers are the same
+				let value = self.read_register(r1);
+				let new_pc = self.update_pc_perm(value.clone());
+
+				let w = self.derive_capability(
+					parent,
+					Capability {
+						perm: Permission::E,
+						address: address + 1,
+						..parent
+					},
+				);
+
+				self.write_register(r2, Word::Capability(w));
+				self.write_register(Register::PC, new_pc);
+
+				self.notify(|o, m| o.jump(m, &value, true));
+
+				State::Running
+			}
+
 			// Instruction:
 			// 	restrict 𝑟 𝜌
-			// Conditions (MODIFIED FROM CERISE):
+			// Conditions (MODIFIED FROM CERISE under Semantics::Modified):
 			// 	𝜑.reg(𝑟) = (𝑝, 𝑏, 𝑒, 𝑎)
-			// 	𝜌 ≼ 𝑝
-			// 	𝑤 = (𝜌, 𝑏, 𝑒, 𝑎)
+			// 	𝑝' = decodePerm(getWord(𝜑, 𝜌))
+			// 	𝑝' ≼ 𝑝
+			// 	𝑤 = (𝑝', 𝑏, 𝑒, 𝑎)
 			// Effect:
 			// 	updPC(𝜑[reg.𝑟 ↦ 𝑤])
 			Instruction::Restrict(r, p) => {
-				let Some(Capability {
-					perm,
-					base,
-					end,
-					address,
-				}) = self.get_register_capability(r)
-				else {
-					self.append_backtrace(format!(
-						"Error: Invalid register r ({}), not a capability",
-						self.read_register(r)
-					));
-					return State::Failed;
+				let Some(parent) = self.get_register_capability(r) else {
+					let w = self.read_register(r);
+					return self.violation(instruction, rules::R_CAPABILITY, &[("φ.reg(r)", w.to_string())]);
+				};
+
+				let Capability { perm, .. } = parent;
+
+				let word = self.get_word(p);
+				let Some(new_perm) = self.semantics().decode_perm(&word) else {
+					return self.violation(instruction, rules::NEW_PERM, &[("getWord(φ, ρ)", word.to_string())]);
 				};
 
 				#[allow(clippy::neg_cmp_op_on_partial_ord)]
-				if !(p <= perm) {
-					self.append_backtrace(format!("Error: Invalid permission ({})", perm));
-					return State::Failed;
+				if !(new_perm <= perm) {
+					return self.violation(
+						instruction,
+						rules::PERM_FLOWS,
+						&[("p'", new_perm.to_string()), ("p", perm.to_string())],
+					);
 				}
 
-				let w = self.sign_capability(Capability {
-					perm: p,
-					base,
-					end,
-					address,
-				});
+				let w = self.derive_capability(
+					parent,
+					Capability {
+						perm: new_perm,
+						..parent
+					},
+				);
 
 				self.write_register(r, Word::Capability(w));
 				self.upd_pc()
@@ -320,59 +481,64 @@ impl Machine {
 
 			// Instruction:
 			// 	subseg 𝑟 𝜌1 𝜌2
-			// Conditions:
+			// Conditions (MODIFIED FROM CERISE under Semantics::Modified):
 			// 	𝜑.reg(𝑟) = (𝑝, 𝑏, 𝑒, 𝑎)
 			// 	𝑧1 = getWord(𝜑, 𝜌1)
 			// 	𝑧2 = getWord(𝜑, 𝜌2)
 			// 	𝑧1 ∈ Z
 			// 	𝑧2 ∈ Z
-			// 	𝑏 ≤ 𝑧1 < AddrMax
-			// 	0 ≤ 𝑧2 ≤ 𝑒
+			// 	isWithin(𝑧1, 𝑧2, 𝑏, 𝑒)
 			// 	𝑝 ≠ e
 			// 	𝑤 = (𝑝, 𝑧1, 𝑧2, 𝑎)
 			// Effect:
 			// 	updPC(𝜑[reg.𝑟 ↦ 𝑤])
 			Instruction::Subseg(r, p1, p2) => {
-				let Some(Capability {
-					perm,
-					base,
-					end,
-					address,
-				}) = self.get_register_capability(r)
-				else {
-					self.append_backtrace(format!(
-						"Error: Invalid register r ({}), not a capability",
-						self.read_register(r)
-					));
-					return State::Failed;
+				let Some(parent) = self.get_register_capability(r) else {
+					let w = self.read_register(r);
+					return self.violation(instruction, rules::R_CAPABILITY, &[("φ.reg(r)", w.to_string())]);
 				};
 
-				let z1 = self.get_word(p1);
-				let z2 = self.get_word(p2);
+				let Capability { perm, base, end, .. } = parent;
 
-				let (Word::Integer(z1), Word::Integer(z2)) = (z1.clone(), z2.clone()) else {
-					self.append_backtrace(format!("Error: Invalid p1 ({}) or p2 ({}), not integers", z1, z2));
-					return State::Failed;
+				let word = self.get_word(p1);
+				let Word::Integer(z1) = word else {
+					return self.violation(instruction, rules::Z1_INTEGER, &[("z1", word.to_string())]);
 				};
 
-				if !(base <= z1 as AddrInt
-					&& (z1 as AddrInt) < self.memory.mem_size()
-					&& 0 <= z2 && (z2 as AddrInt) <= end
-					&& perm != Permission::E)
-				{
-					self.append_backtrace(format!(
-						"Invalid addresses z1 ({}) or z2 ({}), or invalid permission ({})",
-						z1, z2, perm
-					));
-					return State::Failed;
+				let word = self.get_word(p2);
+				let Word::Integer(z2) = word else {
+					return self.violation(instruction, rules::Z2_INTEGER, &[("z2", word.to_string())]);
+				};
+
+				let bounds = self
+					.semantics()
+					.subseg_bounds(base, end, z1, z2, self.memory.mem_size());
+
+				let Some((new_base, new_end)) = bounds else {
+					return self.violation(
+						instruction,
+						rules::WITHIN,
+						&[
+							("z1", z1.to_string()),
+							("z2", z2.to_string()),
+							("b", base.to_string()),
+							("e", end.to_string()),
+						],
+					);
+				};
+
+				if perm == Permission::E {
+					return self.violation(instruction, rules::NOT_ENTER, &[("p", perm.to_string())]);
 				}
 
-				let w = self.sign_capability(Capability {
-					perm,
-					base: Address(z1 as AddrInt),
-					end: Address(z2 as AddrInt),
-					address,
-				});
+				let w = self.derive_capability(
+					parent,
+					Capability {
+						base: new_base,
+						end: new_end,
+						..parent
+					},
+				);
 
 				self.write_register(r, Word::Capability(w));
 				self.upd_pc()
@@ -380,44 +546,43 @@ impl Machine {
 
 			// Instruction:
 			// 	lea 𝑟 𝜌
-			// Conditions:
+			// Conditions (MODIFIED FROM CERISE under Semantics::Modified):
 			// 	𝜑.reg(𝑟) = (𝑝, 𝑏, 𝑒, 𝑎)
 			// 	𝑧 = getWord(𝜑, 𝜌)
 			// 	𝑝 ≠ e
+			// 	𝑧 ∈ Z
+			// 	𝑎 + 𝑧 ∈ [0, AddrMax] (only under Semantics::Cerise)
 			// 	𝑤 = (𝑝, 𝑏, 𝑒, 𝑎 + 𝑧)
 			// Effect:
 			// 	updPC(𝜑[reg.𝑟 ↦ 𝑤])
 			Instruction::Lea(r, p) => {
-				let Some(Capability {
-					perm,
-					base,
-					end,
-					address,
-				}) = self.get_register_capability(r)
-				else {
-					self.append_backtrace(format!(
-						"Error: Invalid register r ({}), not a capability",
-						self.read_register(r)
-					));
-					return State::Failed;
+				let Some(parent) = self.get_register_capability(r) else {
+					let w = self.read_register(r);
+					return self.violation(instruction, rules::R_CAPABILITY, &[("φ.reg(r)", w.to_string())]);
 				};
 
+				let Capability { perm, address, .. } = parent;
+
 				if perm == Permission::E {
-					self.append_backtrace(format!("Error: Invalid permission ({})", perm));
-					return State::Failed;
+					return self.violation(instruction, rules::NOT_ENTER, &[("p", perm.to_string())]);
 				}
 
-				let Word::Integer(z) = self.get_word(p.clone()) else {
-					self.append_backtrace(format!("Error: Invalid p ({}), not an integer", p));
-					return State::Failed;
+				let word = self.get_word(p);
+				let Word::Integer(z) = word else {
+					return self.violation(instruction, rules::Z_INTEGER, &[("z", word.to_string())]);
 				};
 
-				let w = self.sign_capability(Capability {
-					perm,
-					base,
-					end,
-					address: Address((address.0 as WordInt + z) as AddrInt), // This mess is needed because z can be negative
-				});
+				let Some(new_address) = self.semantics().lea_address(address, z, self.memory.mem_size()) else {
+					return self.violation(instruction, rules::LEA_ADDRESS, &[("a + z", offset_term(address, z))]);
+				};
+
+				let w = self.derive_capability(
+					parent,
+					Capability {
+						address: new_address,
+						..parent
+					},
+				);
 
 				self.write_register(r, Word::Capability(w));
 				self.upd_pc()
@@ -434,17 +599,11 @@ impl Machine {
 			// Effect:
 			// 	updPC(𝜑[reg.𝑟 ↦ 𝑧])
 			Instruction::Add(r, p1, p2) => {
-				let z1 = self.get_word(p1);
-				let z2 = self.get_word(p2);
-
-				let (Word::Integer(z1), Word::Integer(z2)) = (z1.clone(), z2.clone()) else {
-					self.append_backtrace(format!("Error: Invalid p1 ({}) or p2 ({}), not integers", z1, z2));
+				let Some((z1, z2)) = self.integer_operands(instruction, p1, p2) else {
 					return State::Failed;
 				};
 
-				let z = z1 + z2;
-
-				self.write_register(r, Word::Integer(z));
+				self.write_register(r, Word::Integer(z1 + z2));
 				self.upd_pc()
 			}
 
@@ -459,17 +618,11 @@ impl Machine {
 			// Effect:
 			// 	updPC(𝜑[reg.𝑟 ↦ 𝑧])
 			Instruction::Sub(r, p1, p2) => {
-				let z1 = self.get_word(p1);
-				let z2 = self.get_word(p2);
-
-				let (Word::Integer(z1), Word::Integer(z2)) = (z1.clone(), z2.clone()) else {
-					self.append_backtrace(format!("Error: Invalid p1 ({}) or p2 ({}), not integers", z1, z2));
+				let Some((z1, z2)) = self.integer_operands(instruction, p1, p2) else {
 					return State::Failed;
 				};
 
-				let z = z1 - z2;
-
-				self.write_register(r, Word::Integer(z));
+				self.write_register(r, Word::Integer(z1 - z2));
 				self.upd_pc()
 			}
 
@@ -484,11 +637,7 @@ impl Machine {
 			// Effect:
 			// 	updPC(𝜑[reg.𝑟 ↦ 𝑧])
 			Instruction::Lt(r, p1, p2) => {
-				let z1 = self.get_word(p1);
-				let z2 = self.get_word(p2);
-
-				let (Word::Integer(z1), Word::Integer(z2)) = (z1.clone(), z2.clone()) else {
-					self.append_backtrace(format!("Error: Invalid p1 ({}) or p2 ({}), not integers", z1, z2));
+				let Some((z1, z2)) = self.integer_operands(instruction, p1, p2) else {
 					return State::Failed;
 				};
 
@@ -500,20 +649,19 @@ impl Machine {
 
 			// Instruction:
 			// 	getp 𝑟1 𝑟2
-			// Conditions (MODIFIED FROM CERISE):
+			// Conditions (MODIFIED FROM CERISE under Semantics::Modified):
 			// 	𝜑.reg(𝑟2) = (𝑝, _, _, _)
 			// Effect:
-			// 	updPC(𝜑[reg.𝑟1 ↦ 𝑝])
+			// 	updPC(𝜑[reg.𝑟1 ↦ encodePerm(𝑝)])
 			Instruction::Getp(r1, r2) => {
 				let Some(Capability { perm, .. }) = self.get_register_capability(r2) else {
-					self.append_backtrace(format!(
-						"Error: Invalid register r2 ({}), not a capability",
-						self.read_register(r2)
-					));
-					return State::Failed;
+					let w = self.read_register(r2);
+					return self.violation(instruction, rules::R2_CAPABILITY, &[("φ.reg(r2)", w.to_string())]);
 				};
 
-				self.write_register(r1, Word::Permission(perm));
+				let w = self.semantics().encode_perm(perm);
+
+				self.write_register(r1, w);
 				self.upd_pc()
 			}
 
@@ -525,11 +673,8 @@ impl Machine {
 			// 	updPC(𝜑[reg.𝑟1 ↦ 𝑏])
 			Instruction::Getb(r1, r2) => {
 				let Some(Capability { base, .. }) = self.get_register_capability(r2) else {
-					self.append_backtrace(format!(
-						"Error: Invalid register r2 ({}), not a capability",
-						self.read_register(r2)
-					));
-					return State::Failed;
+					let w = self.read_register(r2);
+					return self.violation(instruction, rules::R2_CAPABILITY, &[("φ.reg(r2)", w.to_string())]);
 				};
 
 				self.write_register(r1, Word::Integer(base.0 as WordInt));
@@ -544,11 +689,8 @@ impl Machine {
 			// 	updPC(𝜑[reg.𝑟1 ↦ 𝑒])
 			Instruction::Gete(r1, r2) => {
 				let Some(Capability { end, .. }) = self.get_register_capability(r2) else {
-					self.append_backtrace(format!(
-						"Error: Invalid register r2 ({}), not a capability",
-						self.read_register(r2)
-					));
-					return State::Failed;
+					let w = self.read_register(r2);
+					return self.violation(instruction, rules::R2_CAPABILITY, &[("φ.reg(r2)", w.to_string())]);
 				};
 
 				self.write_register(r1, Word::Integer(end.0 as WordInt));
@@ -563,11 +705,8 @@ impl Machine {
 			// 	updPC(𝜑[reg.𝑟1 ↦ 𝑎])
 			Instruction::Geta(r1, r2) => {
 				let Some(Capability { address, .. }) = self.get_register_capability(r2) else {
-					self.append_backtrace(format!(
-						"Error: Invalid register r2 ({}), not a capability",
-						self.read_register(r2)
-					));
-					return State::Failed;
+					let w = self.read_register(r2);
+					return self.violation(instruction, rules::R2_CAPABILITY, &[("φ.reg(r2)", w.to_string())]);
 				};
 
 				self.write_register(r1, Word::Integer(address.0 as WordInt));
@@ -590,9 +729,289 @@ impl Machine {
 				self.write_register(r1, Word::Integer(z));
 				self.upd_pc()
 			}
+
+			// Instruction:
+			// 	getl 𝑟1 𝑟2
+			// Conditions:
+			// 	𝜑.reg(𝑟2) = (_, 𝑏, 𝑒, _)
+			// Effect:
+			// 	updPC(𝜑[reg.𝑟1 ↦ 𝑒 - 𝑏])
+			Instruction::Getl(r1, r2) => {
+				let Some(Capability { base, end, .. }) = self.get_register_capability(r2) else {
+					let w = self.read_register(r2);
+					return self.violation(instruction, rules::R2_CAPABILITY, &[("φ.reg(r2)", w.to_string())]);
+				};
+
+				self.write_register(r1, Word::Integer(end.0 as WordInt - base.0 as WordInt));
+				self.upd_pc()
+			}
+
+			// Instruction:
+			// 	geto 𝑟1 𝑟2
+			// Conditions:
+			// 	𝜑.reg(𝑟2) = (_, 𝑏, _, 𝑎)
+			// Effect:
+			// 	updPC(𝜑[reg.𝑟1 ↦ 𝑎 - 𝑏])
+			Instruction::Geto(r1, r2) => {
+				let Some(Capability { base, address, .. }) = self.get_register_capability(r2) else {
+					let w = self.read_register(r2);
+					return self.violation(instruction, rules::R2_CAPABILITY, &[("φ.reg(r2)", w.to_string())]);
+				};
+
+				self.write_register(r1, Word::Integer(address.0 as WordInt - base.0 as WordInt));
+				self.upd_pc()
+			}
+
+			// Instruction:
+			// 	seta 𝑟 𝜌
+			// Conditions:
+			// 	𝜑.reg(𝑟) = (𝑝, 𝑏, 𝑒, _)
+			// 	𝑧 = getWord(𝜑, 𝜌)
+			// 	𝑧 ∈ Z
+			// 	0 ≤ 𝑧
+			// 	𝑝 ≠ e
+			// 	𝑤 = (𝑝, 𝑏, 𝑒, 𝑧)
+			// Effect:
+			// 	updPC(𝜑[reg.𝑟 ↦ 𝑤])
+			//
+			// Like CHERI's CSetAddr, 𝑧 isn't bounded: it may lie outside [𝑏, 𝑒) or even outside the memory,
+			// since every access through the capability checks its address against its bounds.
+			Instruction::Seta(r, p) => {
+				let Some(parent) = self.get_register_capability(r) else {
+					let w = self.read_register(r);
+					return self.violation(instruction, rules::R_CAPABILITY, &[("φ.reg(r)", w.to_string())]);
+				};
+
+				let Capability { perm, .. } = parent;
+
+				let word = self.get_word(p);
+				let Word::Integer(z) = word else {
+					return self.violation(instruction, rules::Z_INTEGER, &[("z", word.to_string())]);
+				};
+
+				if z < 0 {
+					return self.violation(instruction, rules::Z_POSITIVE, &[("z", z.to_string())]);
+				}
+
+				if perm == Permission::E {
+					return self.violation(instruction, rules::NOT_ENTER, &[("p", perm.to_string())]);
+				}
+
+				let w = self.derive_capability(
+					parent,
+					Capability {
+						address: Address(z as AddrInt),
+						..parent
+					},
+				);
+
+				self.write_register(r, Word::Capability(w));
+				self.upd_pc()
+			}
+
+			// Instruction:
+			// 	setbounds 𝑟 𝜌
+			// Conditions:
+			// 	𝜑.reg(𝑟) = (𝑝, 𝑏, 𝑒, 𝑎)
+			// 	𝑧 = getWord(𝜑, 𝜌)
+			// 	𝑧 ∈ Z
+			// 	0 ≤ 𝑧
+			// 	𝑏 ≤ 𝑎
+			// 	𝑎 + 𝑧 ≤ 𝑒
+			// 	𝑝 ≠ e
+			// 	𝑤 = (𝑝, 𝑎, 𝑎 + 𝑧, 𝑎)
+			// Effect:
+			// 	updPC(𝜑[reg.𝑟 ↦ 𝑤])
+			Instruction::Setbounds(r, p) => {
+				let Some(parent) = self.get_register_capability(r) else {
+					let w = self.read_register(r);
+					return self.violation(instruction, rules::R_CAPABILITY, &[("φ.reg(r)", w.to_string())]);
+				};
+
+				let Capability {
+					perm,
+					base,
+					end,
+					address,
+				} = parent;
+
+				let word = self.get_word(p);
+				let Word::Integer(z) = word else {
+					return self.violation(instruction, rules::Z_INTEGER, &[("z", word.to_string())]);
+				};
+
+				if z < 0 {
+					return self.violation(instruction, rules::Z_POSITIVE, &[("z", z.to_string())]);
+				}
+
+				if base > address {
+					return self.violation(
+						instruction,
+						rules::BASE_BEFORE_ADDRESS,
+						&[("b", base.to_string()), ("a", address.to_string())],
+					);
+				}
+
+				let Some(new_end) = offset_address(address, z).filter(|e| *e <= end) else {
+					return self.violation(
+						instruction,
+						rules::LENGTH_IN_BOUNDS,
+						&[("a + z", offset_term(address, z)), ("e", end.to_string())],
+					);
+				};
+
+				if perm == Permission::E {
+					return self.violation(instruction, rules::NOT_ENTER, &[("p", perm.to_string())]);
+				}
+
+				let w = self.derive_capability(
+					parent,
+					Capability {
+						base: address,
+						end: new_end,
+						..parent
+					},
+				);
+
+				self.write_register(r, Word::Capability(w));
+				self.upd_pc()
+			}
+
+			// Instruction:
+			// 	subset 𝑟1 𝑟2 𝑟3
+			// Conditions:
+			// 	𝜑.reg(𝑟2) = (𝑝2, 𝑏2, 𝑒2, _)
+			// 	𝜑.reg(𝑟3) = (𝑝3, 𝑏3, 𝑒3, _)
+			// 	if 𝑝3 ≼ 𝑝2  ∧  𝑏2 ≤ 𝑏3  ∧  𝑒3 ≤ 𝑒2 then 𝑧 = 1 else 𝑧 = 0
+			// Effect:
+			// 	updPC(𝜑[reg.𝑟1 ↦ 𝑧])
+			Instruction::Subset(r1, r2, r3) => {
+				let Some((c2, c3)) = self.capability_operands(instruction, r2, r3) else {
+					return State::Failed;
+				};
+
+				let z = if c3.perm <= c2.perm && c2.base <= c3.base && c3.end <= c2.end {
+					1
+				} else {
+					0
+				};
+
+				self.write_register(r1, Word::Integer(z));
+				self.upd_pc()
+			}
+
+			// Instruction:
+			// 	ceq 𝑟1 𝑟2 𝑟3
+			// Conditions:
+			// 	𝜑.reg(𝑟2) = (𝑝2, 𝑏2, 𝑒2, 𝑎2)
+			// 	𝜑.reg(𝑟3) = (𝑝3, 𝑏3, 𝑒3, 𝑎3)
+			// 	if (𝑝2, 𝑏2, 𝑒2, 𝑎2) = (𝑝3, 𝑏3, 𝑒3, 𝑎3) then 𝑧 = 1 else 𝑧 = 0
+			// Effect:
+			// 	updPC(𝜑[reg.𝑟1 ↦ 𝑧])
+			Instruction::Ceq(r1, r2, r3) => {
+				let Some((c2, c3)) = self.capability_operands(instruction, r2, r3) else {
+					return State::Failed;
+				};
+
+				let z = if c2 == c3 { 1 } else { 0 };
+
+				self.write_register(r1, Word::Integer(z));
+				self.upd_pc()
+			}
+
+			// Instruction:
+			// 	flows 𝑟 𝜌1 𝜌2
+			// Conditions (MODIFIED FROM CERISE under Semantics::Modified):
+			// 	𝑝1 = decodePerm(getWord(𝜑, 𝜌1))
+			// 	𝑝2 = decodePerm(getWord(𝜑, 𝜌2))
+			// 	if 𝑝1 ≼ 𝑝2 then 𝑧 = 1 else 𝑧 = 0
+			// Effect:
+			// 	updPC(𝜑[reg.𝑟 ↦ 𝑧])
+			Instruction::Flows(r, p1, p2) => {
+				let p1 = self.get_word(p1);
+				let Some(p1) = self.semantics().decode_perm(&p1) else {
+					return self.violation(instruction, rules::PERM_1, &[("getWord(φ, ρ1)", p1.to_string())]);
+				};
+
+				let p2 = self.get_word(p2);
+				let Some(p2) = self.semantics().decode_perm(&p2) else {
+					return self.violation(instruction, rules::PERM_2, &[("getWord(φ, ρ2)", p2.to_string())]);
+				};
+
+				let z = if p1 <= p2 { 1 } else { 0 };
+
+				self.write_register(r, Word::Integer(z));
+				self.upd_pc()
+			}
 		}
 	}
 
+	/// Fails because the premise of the rule doesn't hold for the subject being executed,
+	/// along with the values of the terms it was checked with.
+	fn fail_rule(&mut self, subject: String, rule: &Rule, premise: &str, values: &[(&str, String)]) -> State {
+		self.fault(Violation::new(subject, rule, premise, values));
+		State::Failed
+	}
+
+	/// Fails because the premise of the rule of the instruction doesn't hold.
+	fn violation(&mut self, instruction: &Instruction, premise: &str, values: &[(&str, String)]) -> State {
+		self.fail_rule(
+			instruction.to_string(),
+			rules::instruction_rule(instruction),
+			premise,
+			values,
+		)
+	}
+
+	/// Fails because the premise of ExecSingle doesn't hold, before the instruction is known.
+	fn fetch_violation(&mut self, premise: &str, values: &[(&str, String)]) -> State {
+		self.fail_rule(rules::EXEC_SINGLE.name.to_owned(), &rules::EXEC_SINGLE, premise, values)
+	}
+
+	/// The integers z1 and z2 of arithmetic instructions, failing if either of them isn't an integer.
+	fn integer_operands(
+		&mut self,
+		instruction: &Instruction,
+		p1: RegisterOrWord,
+		p2: RegisterOrWord,
+	) -> Option<(WordInt, WordInt)> {
+		let word = self.get_word(p1);
+		let Word::Integer(z1) = word else {
+			self.violation(instruction, rules::Z1_INTEGER, &[("z1", word.to_string())]);
+			return None;
+		};
+
+		let word = self.get_word(p2);
+		let Word::Integer(z2) = word else {
+			self.violation(instruction, rules::Z2_INTEGER, &[("z2", word.to_string())]);
+			return None;
+		};
+
+		Some((z1, z2))
+	}
+
+	/// The capabilities in r2 and r3 of the instructions comparing capabilities, failing if either of them isn't one.
+	fn capability_operands(
+		&mut self,
+		instruction: &Instruction,
+		r2: Register,
+		r3: Register,
+	) -> Option<(Capability, Capability)> {
+		let Some(c2) = self.get_register_capability(r2) else {
+			let w = self.read_register(r2);
+			self.violation(instruction, rules::R2_CAPABILITY_2, &[("φ.reg(r2)", w.to_string())]);
+			return None;
+		};
+
+		let Some(c3) = self.get_register_capability(r3) else {
+			let w = self.read_register(r3);
+			self.violation(instruction, rules::R3_CAPABILITY_3, &[("φ.reg(r3)", w.to_string())]);
+			return None;
+		};
+
+		Some((c2, c3))
+	}
+
 	/// getWord(𝜑, 𝜌) from Cerise.
 	///
 	/// Cerise specs:
@@ -606,7 +1025,7 @@ impl Machine {
 		}
 	}
 
-	/// updPC(𝜑) from Cerise.
+	/// updPC(𝜑) from Cerise, see rules::UPD_PC.
 	///
 	/// Cerise specs:
 	///   updPC(𝜑) =
@@ -621,16 +1040,29 @@ impl Machine {
 			address,
 		}) = self.get_register_capability(Register::PC)
 		else {
-			self.append_backtrace("Error: Couldn't update PC, invalid PC".to_string());
-			return State::Failed;
+			let pc = self.read_register(Register::PC);
+			return self.fail_rule(
+				rules::UPD_PC.name.to_owned(),
+				&rules::UPD_PC,
+				rules::PC_CAPABILITY,
+				&[("φ.reg(pc)", pc.to_string())],
+			);
 		};
 
-		let new_capa = self.sign_capability(Capability {
+		let parent = Capability {
 			perm,
 			base,
 			end,
-			address: address + 1,
-		});
+			address,
+		};
+
+		let new_capa = self.derive_capability(
+			parent,
+			Capability {
+				address: address + 1,
+				..parent
+			},
+		);
 
 		self.write_register(Register::PC, Word::Capability(new_capa));
 		State::Running
@@ -643,28 +1075,47 @@ impl Machine {
 	///     if 𝑤 = (e, 𝑏, 𝑒, 𝑎)
 	///     then (rx, 𝑏, 𝑒, 𝑎)
 	///     else 𝑤
-	fn update_pc_perm(&self, word: Word) -> Word {
+	fn update_pc_perm(&mut self, word: Word) -> Word {
 		let Word::Capability(signed_capability) = word.clone() else {
 			return word;
 		};
 
 		let capability = self.verify_capability(signed_capability);
 
-		if let Some(Capability {
-			perm: Permission::E,
-			base,
-			end,
-			address,
-		}) = capability
+		if let Some(
+			parent @ Capability {
+				perm: Permission::E, ..
+			},
+		) = capability
 		{
-			Word::Capability(self.sign_capability(Capability {
-				perm: Permission::RX,
-				base,
-				end,
-				address,
-			}))
+			Word::Capability(self.derive_capability(
+				parent,
+				Capability {
+					perm: Permission::RX,
+					..parent
+				},
+			))
 		} else {
 			word
 		}
 	}
 }
+
+/// Computes 𝑎 + 𝑧 for a signed offset 𝑧, returning None if the result is not a valid address.
+fn offset_address(address: Address, z: WordInt) -> Option<Address> {
+	(address.0 as WordInt)
+		.checked_add(z)
+		.and_then(|a| AddrInt::try_from(a).ok())
+		.map(Address)
+}
+
+/// Renders 𝑎 + 𝑧 in hexadecimal for a signed offset 𝑧, even when it is not a valid address.
+fn offset_term(address: Address, z: WordInt) -> String {
+	let sum = address.0 as i128 + z as i128;
+
+	if sum < 0 {
+		format!("-{:#x}", -sum)
+	} else {
+		format!("{:#x}", sum)
+	}
+}
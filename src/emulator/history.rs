@@ -0,0 +1,134 @@
+use super::{
+	instruction::Instruction,
+	machine::{Interrupt, Machine, State},
+	observer::ExecObserver,
+	program::{Address, Register, Row, Word},
+	step_log::StepLog,
+};
+
+/*
+--------------------------------------------------------------------------------
+||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||
+--------------------------------------------------------------------------------
+*/
+
+/// Everything a single step changed in the machine, enough to undo it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StepDelta {
+	/// The address of the instruction executed during the step, if it could be fetched
+	pub address: Option<Address>,
+
+	/// The state of the machine before the step
+	pub state: State,
+
+	/// The stop reason of the machine before the step
+	pub stop_reason: Option<String>,
+
+	/// The registers written during the step, with their value before the write (None if they had none), in order
+	pub registers: Vec<(Register, Option<Word>)>,
+
+	/// The memory rows written during the step, with their value before the write, in order
+	pub memory: Vec<(Address, Row)>,
+
+	/// The interrupts added to the interrupt table during the step, see Machine::get_interrupt_memory()
+	pub interrupts: Vec<Interrupt>,
+
+	/// The number of steps in the trace before the step, see Trace::recorded_steps()
+	pub(crate) trace_len: usize,
+}
+
+/// The built-in observer recording the deltas of every step, see Machine::record_history().
+///
+/// Only the latest steps are kept like in the trace, so the machine can't step back further than that.
+#[derive(Clone, Debug, Default)]
+pub struct History {
+	steps: StepLog<StepDelta>,
+}
+
+impl History {
+	pub(crate) fn new(limit: Option<usize>) -> Self {
+		Self {
+			steps: StepLog::new(limit),
+		}
+	}
+
+	/// The steps kept, in order, starting at first_step(). Step n brings the machine from position n
+	/// to position n + 1.
+	pub fn steps(&self) -> &[StepDelta] {
+		self.steps.entries()
+	}
+
+	/// The current position, i.e. the number of steps recorded so far, including the ones that aren't kept anymore.
+	pub fn position(&self) -> usize {
+		self.steps.total()
+	}
+
+	/// The first step kept, i.e. the earliest position the machine can step back to.
+	pub fn first_step(&self) -> usize {
+		self.steps.first()
+	}
+
+	pub(crate) fn set_limit(&mut self, limit: Option<usize>) {
+		self.steps.set_limit(limit);
+	}
+
+	/// Returns the step that last wrote to the given address, if it is kept.
+	pub fn last_write(&self, address: Address) -> Option<usize> {
+		self.steps()
+			.iter()
+			.rposition(|step| step.memory.iter().any(|(a, _)| *a == address))
+			.map(|index| self.first_step() + index)
+	}
+
+	/// Returns the step that last wrote to the given register, if it is kept.
+	pub fn last_register_write(&self, register: Register) -> Option<usize> {
+		self.steps()
+			.iter()
+			.rposition(|step| step.registers.iter().any(|(r, _)| *r == register))
+			.map(|index| self.first_step() + index)
+	}
+
+	pub(crate) fn begin_step(&mut self, state: State, stop_reason: Option<String>, trace_len: usize) {
+		self.steps.push(StepDelta {
+			address: None,
+			state,
+			stop_reason,
+			registers: Vec::new(),
+			memory: Vec::new(),
+			interrupts: Vec::new(),
+			trace_len,
+		});
+	}
+
+	pub(crate) fn interrupt_added(&mut self, interrupt: Interrupt) {
+		if let Some(step) = self.steps.last_mut() {
+			step.interrupts.push(interrupt);
+		}
+	}
+
+	pub(crate) fn pop(&mut self) -> Option<StepDelta> {
+		self.steps.pop()
+	}
+}
+
+impl ExecObserver for History {
+	fn before_instruction(&mut self, _: &Machine, address: Address, _: &Instruction) {
+		if let Some(step) = self.steps.last_mut() {
+			step.address = Some(address);
+		}
+	}
+
+	fn register_write(&mut self, machine: &Machine, register: Register, _: &Word, _: &Word) {
+		// The old word is the default one for registers that were never written
+		if let Some(step) = self.steps.last_mut() {
+			step.registers
+				.push((register, machine.registers().get(&register).cloned()));
+		}
+	}
+
+	fn memory_write(&mut self, _: &Machine, address: Address, old: &Row, _: &Row) {
+		if let Some(step) = self.steps.last_mut() {
+			step.memory.push((address, old.clone()));
+		}
+	}
+}
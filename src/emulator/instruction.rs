@@ -1,4 +1,4 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
 use super::{
 	permission::Permission,
@@ -6,6 +6,7 @@ use super::{
 };
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(from = "SerializedInstruction")]
 #[rustfmt::skip]
 pub enum Instruction {
 	/// fail
@@ -14,16 +15,18 @@ pub enum Instruction {
 	Halt,
 	/// mov r ρ
 	Mov     (Register, RegisterOrWord),
-	/// load r1 r2
-	Load    (Register, Register),
-	/// store r ρ
-	Store   (Register, RegisterOrWord),
+	/// load r1 r2 ρ
+	Load    (Register, Register, RegisterOrWord),
+	/// store r ρ1 ρ2
+	Store   (Register, RegisterOrWord, RegisterOrWord),
 	/// jmp r
 	Jmp     (Register),
 	/// jnz r1 r2
 	Jnz     (Register, Register),
+	/// jal r1 r2
+	Jal     (Register, Register),
 	/// restrict r ρ
-	Restrict(Register, Permission),
+	Restrict(Register, RegisterOrWord),
 	/// subseg r ρ1 ρ2
 	Subseg  (Register, RegisterOrWord, RegisterOrWord),
 	/// lea r ρ
@@ -44,6 +47,77 @@ pub enum Instruction {
 	Geta    (Register, Register),
 	/// isptr r1 r2
 	Isptr   (Register, Register),
+	/// getl r1 r2
+	Getl    (Register, Register),
+	/// geto r1 r2
+	Geto    (Register, Register),
+	/// seta r ρ
+	Seta    (Register, RegisterOrWord),
+	/// setbounds r ρ
+	Setbounds(Register, RegisterOrWord),
+	/// subset r1 r2 r3
+	Subset  (Register, Register, Register),
+	/// ceq r1 r2 r3
+	Ceq     (Register, Register, Register),
+	/// flows r ρ1 ρ2
+	Flows   (Register, RegisterOrWord, RegisterOrWord),
+}
+
+impl Instruction {
+	/// The name of the instruction in the assembly syntax, without its operands.
+	#[rustfmt::skip]
+	pub fn mnemonic(&self) -> &'static str {
+		match self {
+			Instruction::Fail            => "fail",
+			Instruction::Halt            => "halt",
+			Instruction::Mov(..)         => "mov",
+			Instruction::Load(..)        => "load",
+			Instruction::Store(..)       => "store",
+			Instruction::Jmp(..)         => "jmp",
+			Instruction::Jnz(..)         => "jnz",
+			Instruction::Jal(..)         => "jal",
+			Instruction::Restrict(..)    => "restrict",
+			Instruction::Subseg(..)      => "subseg",
+			Instruction::Lea(..)         => "lea",
+			Instruction::Add(..)         => "add",
+			Instruction::Sub(..)         => "sub",
+			Instruction::Lt(..)          => "lt",
+			Instruction::Getp(..)        => "getp",
+			Instruction::Getb(..)        => "getb",
+			Instruction::Gete(..)        => "gete",
+			Instruction::Geta(..)        => "geta",
+			Instruction::Isptr(..)       => "isptr",
+			Instruction::Getl(..)        => "getl",
+			Instruction::Geto(..)        => "geto",
+			Instruction::Seta(..)        => "seta",
+			Instruction::Setbounds(..)   => "setbounds",
+			Instruction::Subset(..)      => "subset",
+			Instruction::Ceq(..)         => "ceq",
+			Instruction::Flows(..)       => "flows",
+		}
+	}
+
+	/// The operands of the instruction that may be words, in order.
+	#[rustfmt::skip]
+	pub fn operands_mut(&mut self) -> Vec<&mut RegisterOrWord> {
+		match self {
+			Instruction::Mov(_, p)
+			| Instruction::Load(_, _, p)
+			| Instruction::Restrict(_, p)
+			| Instruction::Lea(_, p)
+			| Instruction::Seta(_, p)
+			| Instruction::Setbounds(_, p)  => vec![p],
+
+			Instruction::Store(_, p1, p2)
+			| Instruction::Subseg(_, p1, p2)
+			| Instruction::Add(_, p1, p2)
+			| Instruction::Sub(_, p1, p2)
+			| Instruction::Lt(_, p1, p2)
+			| Instruction::Flows(_, p1, p2) => vec![p1, p2],
+
+			_                               => vec![],
+		}
+	}
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
@@ -51,3 +125,120 @@ pub enum RegisterOrWord {
 	Register(Register),
 	Word(Word),
 }
+
+/// An instruction as written in RON files. Besides the current forms, it accepts the forms of files compiled
+/// before load and store took an offset (`Load(r1, r2)`, `Store(r, ρ)`) and before restrict took a register
+/// or word operand (`Restrict(r, RO)`).
+#[derive(Deserialize)]
+#[rustfmt::skip]
+enum SerializedInstruction {
+	Fail,
+	Halt,
+	Mov     (Register, RegisterOrWord),
+	Load    (Register, Register, #[serde(default = "zero")] RegisterOrWord),
+	Store   (Register, RegisterOrWord, #[serde(default)] TrailingOperand),
+	Jmp     (Register),
+	Jnz     (Register, Register),
+	Jal     (Register, Register),
+	Restrict(Register, #[serde(deserialize_with = "permission_operand")] RegisterOrWord),
+	Subseg  (Register, RegisterOrWord, RegisterOrWord),
+	Lea     (Register, RegisterOrWord),
+	Add     (Register, RegisterOrWord, RegisterOrWord),
+	Sub     (Register, RegisterOrWord, RegisterOrWord),
+	Lt      (Register, RegisterOrWord, RegisterOrWord),
+	Getp    (Register, Register),
+	Getb    (Register, Register),
+	Gete    (Register, Register),
+	Geta    (Register, Register),
+	Isptr   (Register, Register),
+	Getl    (Register, Register),
+	Geto    (Register, Register),
+	Seta    (Register, RegisterOrWord),
+	Setbounds(Register, RegisterOrWord),
+	Subset  (Register, Register, Register),
+	Ceq     (Register, Register, Register),
+	Flows   (Register, RegisterOrWord, RegisterOrWord),
+}
+
+impl From<SerializedInstruction> for Instruction {
+	#[rustfmt::skip]
+	fn from(instruction: SerializedInstruction) -> Self {
+		use SerializedInstruction as S;
+
+		match instruction {
+			S::Fail                                    => Instruction::Fail,
+			S::Halt                                    => Instruction::Halt,
+			S::Mov(r, p)                               => Instruction::Mov(r, p),
+			S::Load(r1, r2, p)                         => Instruction::Load(r1, r2, p),
+			S::Store(r, p1, TrailingOperand(Some(p2))) => Instruction::Store(r, p1, p2),
+			S::Store(r, p, TrailingOperand(None))      => Instruction::Store(r, zero(), p),
+			S::Jmp(r)                                  => Instruction::Jmp(r),
+			S::Jnz(r1, r2)                             => Instruction::Jnz(r1, r2),
+			S::Jal(r1, r2)                             => Instruction::Jal(r1, r2),
+			S::Restrict(r, p)                          => Instruction::Restrict(r, p),
+			S::Subseg(r, p1, p2)                       => Instruction::Subseg(r, p1, p2),
+			S::Lea(r, p)                               => Instruction::Lea(r, p),
+			S::Add(r, p1, p2)                          => Instruction::Add(r, p1, p2),
+			S::Sub(r, p1, p2)                          => Instruction::Sub(r, p1, p2),
+			S::Lt(r, p1, p2)                           => Instruction::Lt(r, p1, p2),
+			S::Getp(r1, r2)                            => Instruction::Getp(r1, r2),
+			S::Getb(r1, r2)                            => Instruction::Getb(r1, r2),
+			S::Gete(r1, r2)                            => Instruction::Gete(r1, r2),
+			S::Geta(r1, r2)                            => Instruction::Geta(r1, r2),
+			S::Isptr(r1, r2)                           => Instruction::Isptr(r1, r2),
+			S::Getl(r1, r2)                            => Instruction::Getl(r1, r2),
+			S::Geto(r1, r2)                            => Instruction::Geto(r1, r2),
+			S::Seta(r, p)                              => Instruction::Seta(r, p),
+			S::Setbounds(r, p)                         => Instruction::Setbounds(r, p),
+			S::Subset(r1, r2, r3)                      => Instruction::Subset(r1, r2, r3),
+			S::Ceq(r1, r2, r3)                         => Instruction::Ceq(r1, r2, r3),
+			S::Flows(r, p1, p2)                        => Instruction::Flows(r, p1, p2),
+		}
+	}
+}
+
+/// The offset of load and store when it is left out.
+fn zero() -> RegisterOrWord {
+	RegisterOrWord::Word(Word::Integer(0))
+}
+
+/// The last operand of store, missing in the older form `Store(r, ρ)` where the only operand is the stored word.
+#[derive(Default)]
+struct TrailingOperand(Option<RegisterOrWord>);
+
+impl<'de> Deserialize<'de> for TrailingOperand {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		RegisterOrWord::deserialize(deserializer).map(|operand| Self(Some(operand)))
+	}
+}
+
+/// The operand of restrict as written in RON files, which used to be a bare permission (e.g. `Restrict(R(1), RO)`)
+/// before restrict accepted any register or word.
+#[derive(Deserialize)]
+#[allow(clippy::upper_case_acronyms)]
+enum PermissionOperand {
+	Register(Register),
+	Word(Word),
+	O,
+	E,
+	RO,
+	RX,
+	RW,
+	RWX,
+}
+
+/// Deserializes the operand of restrict, accepting bare permissions as well, see PermissionOperand.
+fn permission_operand<'de, D: Deserializer<'de>>(deserializer: D) -> Result<RegisterOrWord, D::Error> {
+	let permission = |perm| RegisterOrWord::Word(Word::Permission(perm));
+
+	Ok(match PermissionOperand::deserialize(deserializer)? {
+		PermissionOperand::Register(r) => RegisterOrWord::Register(r),
+		PermissionOperand::Word(w) => RegisterOrWord::Word(w),
+		PermissionOperand::O => permission(Permission::O),
+		PermissionOperand::E => permission(Permission::E),
+		PermissionOperand::RO => permission(Permission::RO),
+		PermissionOperand::RX => permission(Permission::RX),
+		PermissionOperand::RW => permission(Permission::RW),
+		PermissionOperand::RWX => permission(Permission::RWX),
+	})
+}
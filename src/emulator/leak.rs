@@ -0,0 +1,207 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+	instruction::Instruction,
+	machine::Machine,
+	observer::ExecObserver,
+	permission::Permission,
+	program::{AddrInt, Address, Capability, Register, SourceLocation, Word},
+	symbols::SymbolTable,
+};
+
+/*
+--------------------------------------------------------------------------------
+||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||
+--------------------------------------------------------------------------------
+*/
+
+/// An upper bound on the authority of a capability: at most the permission, within the range.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Grant {
+	pub perm: Permission,
+	pub base: AddrInt,
+	pub end: AddrInt,
+}
+
+impl Grant {
+	/// Whether the capability carries at most the authority of the grant.
+	pub fn covers(&self, capability: &Capability) -> bool {
+		capability.perm <= self.perm && self.base <= capability.base.0 && capability.end.0 <= self.end
+	}
+}
+
+/// The capabilities a code region may receive in registers when it is entered through an E-capability.
+///
+/// Besides the capabilities covered by one of the grants, a region may always receive O-capabilities, which carry
+/// no authority, and E-capabilities, which can only be jumped to (e.g. the link of a jal, or pointers to routines).
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DomainPolicy {
+	/// The first address of the region
+	pub start: AddrInt,
+
+	/// The address right after the region
+	pub end: AddrInt,
+
+	#[serde(default)]
+	pub receives: Vec<Grant>,
+}
+
+impl DomainPolicy {
+	/// Whether the region may receive the capability.
+	pub fn allows(&self, capability: &Capability) -> bool {
+		matches!(capability.perm, Permission::O | Permission::E)
+			|| self.receives.iter().any(|grant| grant.covers(capability))
+	}
+}
+
+/// A capability still held in a register when control passed through an E-capability into another region,
+/// with more authority than the region may receive.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Leak {
+	/// The step of the jump, like in the trace
+	pub step: usize,
+
+	/// The address of the jump instruction
+	pub address: Address,
+	pub instruction: Instruction,
+	pub source: Option<SourceLocation>,
+
+	/// The address the E-capability enters the region at
+	pub target: Address,
+
+	pub register: Register,
+	pub capability: Capability,
+}
+
+/// An observer checking the registers whenever control passes through an E-capability (a jump where updatePcPerm
+/// turns E into RX) into another region, reporting every register holding a capability the entered region may not
+/// receive. Jumps within a region aren't checked, and the addresses outside every region count as a single region,
+/// which may only receive O- and E-capabilities, see DomainPolicy.
+///
+/// Code usually clears its registers before such a jump, so that the callee (or the caller when returning)
+/// doesn't get hold of more than it should. The checker catches the registers someone forgot to clear.
+#[derive(Clone, Debug, Default)]
+pub struct LeakChecker {
+	domains: Vec<DomainPolicy>,
+	leaks: Vec<Leak>,
+
+	/// How many steps were started, the current one included
+	steps: usize,
+
+	/// The instruction being executed, at its address, with its source location
+	current: Option<(Address, Instruction, Option<SourceLocation>)>,
+}
+
+impl LeakChecker {
+	pub fn new(domains: Vec<DomainPolicy>) -> Self {
+		Self {
+			domains,
+			..Default::default()
+		}
+	}
+
+	/// All the leaks found so far, in execution order.
+	pub fn leaks(&self) -> &[Leak] {
+		&self.leaks
+	}
+
+	/// The policy of the region containing the address, the default one if no region contains it.
+	pub fn policy(&self, address: Address) -> DomainPolicy {
+		self.domain(address)
+			.map(|index| self.domains[index].clone())
+			.unwrap_or_default()
+	}
+
+	/// The index of the region containing the address, None if no region contains it.
+	fn domain(&self, address: Address) -> Option<usize> {
+		self.domains
+			.iter()
+			.position(|domain| domain.start <= address.0 && address.0 < domain.end)
+	}
+
+	/// Renders the leaks one per line, with symbolic addresses.
+	pub fn report(&self, symbols: &SymbolTable) -> String {
+		if self.leaks.is_empty() {
+			return "No capability leaked at domain transitions.".to_owned();
+		}
+
+		self.leaks
+			.iter()
+			.map(|leak| {
+				let source = leak
+					.source
+					.as_ref()
+					.map(|source| format!(" @ {}", source))
+					.unwrap_or_default();
+
+				format!(
+					"[step {}] {} at {}{} enters {}, but {} still holds {}",
+					leak.step,
+					leak.instruction,
+					symbols.format_address(leak.address),
+					source,
+					symbols.format_address(leak.target),
+					leak.register,
+					symbols.format_capability(&leak.capability)
+				)
+			})
+			.collect::<Vec<_>>()
+			.join("\n")
+	}
+}
+
+impl ExecObserver for LeakChecker {
+	fn before_step(&mut self, _: &Machine) {
+		self.steps += 1;
+		self.current = None;
+	}
+
+	fn before_instruction(&mut self, machine: &Machine, address: Address, instruction: &Instruction) {
+		self.current = Some((address, instruction.clone(), machine.source_location(address).cloned()));
+	}
+
+	fn jump(&mut self, machine: &Machine, target: &Word, taken: bool) {
+		let Word::Capability(target) = target else {
+			return;
+		};
+
+		let target = target.inner();
+		if !taken || target.perm != Permission::E {
+			return;
+		}
+
+		let Some((address, instruction, source)) = &self.current else {
+			return;
+		};
+
+		// Calls and returns within a region don't pass control to other code
+		if self.domain(*address) == self.domain(target.address) {
+			return;
+		}
+
+		let policy = self.policy(target.address);
+
+		let mut registers = machine.registers().iter().collect::<Vec<_>>();
+		registers.sort_by_key(|(register, _)| **register);
+
+		for (register, word) in registers {
+			let Word::Capability(capability) = word else {
+				continue;
+			};
+
+			if *register == Register::PC || policy.allows(capability.inner()) {
+				continue;
+			}
+
+			self.leaks.push(Leak {
+				step: self.steps - 1,
+				address: *address,
+				instruction: instruction.clone(),
+				source: source.clone(),
+				target: target.address,
+				register: *register,
+				capability: *capability.inner(),
+			});
+		}
+	}
+}
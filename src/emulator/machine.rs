@@ -1,7 +1,7 @@
 use std::{
-	cmp::max,
 	collections::HashMap,
 	fmt::{self, Display, Formatter},
+	sync::{Arc, Mutex},
 };
 
 use serde::{Deserialize, Serialize};
@@ -9,10 +9,23 @@ use serde::{Deserialize, Serialize};
 use crate::util::{indent_string, pretty_hashmap};
 
 use super::{
-	machine_config::MachineConfig,
+	backtrace::Backtrace,
+	encoding::ENCODING_VERSION,
+	history::History,
+	instruction::RegisterOrWord,
+	machine_config::{MachineConfig, ProgramConfig},
 	memory::Memory,
-	program::{Address, Capability, Program, Register, Row, Word},
+	observer::{ExecObserver, Observers},
+	profile::Profile,
+	program::{Address, Capability, LabelIdentifier, Program, Register, Row, SourceLocation, Word},
+	provenance::Provenance,
+	rules::Violation,
+	semantics::Semantics,
 	signed::{self, Signable, Signed, SigningKey, VerifyingKey},
+	step_log::DEFAULT_RECORDED_STEPS,
+	stop_condition::StopCondition,
+	symbols::SymbolTable,
+	trace::Trace,
 };
 
 /*
@@ -24,17 +37,27 @@ use super::{
 #[derive(Serialize, Clone, Debug)]
 pub struct Machine {
 	pub exec_state: State,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	stop_reason: Option<String>,
+
 	registers: HashMap<Register, Word>,
 	interrupt_table: HashMap<Interrupt, Address>,
 	pub memory: Memory,
 
+	#[serde(default)]
+	encoded_instructions: bool,
+
+	#[serde(default)]
+	semantics: Semantics,
+
 	#[serde(skip)]
 	signing_key: SigningKey,
 	#[serde(skip)]
 	verifying_key: VerifyingKey,
 
 	#[serde(skip)]
-	backtrace: Vec<Vec<String>>,
+	observers: Observers,
 }
 
 #[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
@@ -47,6 +70,9 @@ pub enum State {
 	Failed,
 
 	Interrupted(Interrupt),
+
+	/// The machine was stopped early because a stop condition was met, see Machine::stop_reason().
+	Stopped,
 }
 
 #[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -55,16 +81,33 @@ pub enum Interrupt {
 	Fail,
 }
 
+/// What happened during a single step of the machine, see Machine::step().
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum StepOutcome {
+	/// An instruction was executed and the machine keeps running.
+	Executed,
+
+	/// The machine halted or failed, and recovered by jumping to the address given in the interrupt table.
+	Interrupted(Interrupt),
+
+	/// The machine reached a HALTED or FAILED state for good, was stopped by a stop condition,
+	/// or wasn't running in the first place.
+	Stopped(State),
+}
+
 impl Default for Machine {
 	fn default() -> Self {
 		let (signing_key, verifying_key) = signed::create_key_pair();
 
 		Self {
 			exec_state: Default::default(),
+			stop_reason: Default::default(),
 			registers: Default::default(),
 			memory: Default::default(),
 			interrupt_table: Default::default(),
-			backtrace: Default::default(),
+			encoded_instructions: Default::default(),
+			semantics: Default::default(),
+			observers: Default::default(),
 			signing_key,
 			verifying_key,
 		}
@@ -78,20 +121,43 @@ impl Machine {
 
 	pub fn initialize_from_program(program: Program) -> Self {
 		let mut machine = Self::new();
-		machine.load_program(program, Address(0x0));
 		machine
+			.load_program(program, Address(0x0))
+			.expect("Programs always load without encoded instructions");
+		machine
+	}
+
+	/// Initializes the machine from the config, panicking if one of its programs can't be loaded,
+	/// see try_initialize_from_config().
+	pub fn initialize_from_config(mut machine_config: MachineConfig) -> Self {
+		// Compile the programs first, so that compilation errors are printed along with their source
+		machine_config.programs = machine_config
+			.programs
+			.into_iter()
+			.map(|(address, program_config)| (address, ProgramConfig::CompiledProgram(program_config.compiled())))
+			.collect();
+
+		Self::try_initialize_from_config(machine_config).unwrap_or_else(|e| panic!("{}", e))
 	}
 
-	pub fn initialize_from_config(machine_config: MachineConfig) -> Self {
+	/// Initializes the machine from the config.
+	/// Fails if one of its programs can't be compiled or loaded, see ProgramConfig::try_compiled() and load_program().
+	pub fn try_initialize_from_config(machine_config: MachineConfig) -> Result<Self, String> {
 		let mut machine = Self {
 			memory: Memory::new(machine_config.size),
+			encoded_instructions: machine_config.encoded_instructions,
+			semantics: machine_config.semantics,
 			..Default::default()
 		};
 
 		// Load programs from the config
 		for (address_int, program_config) in machine_config.programs {
-			let program = program_config.compiled();
-			machine.load_program(program, Address(address_int))
+			let program = program_config
+				.try_compiled()
+				.map_err(|e| format!("Couldn't load the program at {}: {}", Address(address_int), e))?;
+			machine
+				.load_program(program, Address(address_int))
+				.map_err(|e| format!("Couldn't load the program at {}: {}", Address(address_int), e))?;
 		}
 
 		// Load registers from the config
@@ -112,11 +178,100 @@ impl Machine {
 			machine.set_interrupt_address(interrupt, Address(address_int))
 		}
 
-		machine
+		// Load stop conditions from the config
+		for condition in machine_config.stop_conditions {
+			machine.add_stop_condition(condition);
+		}
+
+		machine.set_recorded_steps(Some(machine_config.recorded_steps.unwrap_or(DEFAULT_RECORDED_STEPS)));
+
+		Ok(machine)
 	}
 
-	pub fn load_program(&mut self, program: Program, address: Address) {
-		self.memory.load_program(program, address)
+	/// Loads the program into memory at the given address.
+	/// If the machine runs with encoded instructions, they are encoded once loaded, which fails if one of them
+	/// can't be encoded (see encoding::encode()), or if the program was encoded with another layout.
+	/// Encoded programs can't be loaded into a machine that doesn't run with encoded instructions.
+	/// The permission literals of the program are loaded as the words they stand for, see Semantics::literal().
+	pub fn load_program(&mut self, mut program: Program, address: Address) -> Result<(), String> {
+		match program.encoding_version {
+			Some(_) if !self.encoded_instructions => {
+				return Err("the program is encoded, but the machine doesn't run with encoded instructions".to_owned())
+			}
+			Some(version) if version != ENCODING_VERSION => {
+				return Err(format!(
+					"encoded with layout version {}, but this emulator decodes version {}",
+					version, ENCODING_VERSION
+				))
+			}
+			_ => {}
+		}
+
+		for row in &mut program.rows {
+			match row {
+				Row::Word(word) => *word = self.semantics.literal(word),
+				Row::Instruction(instruction) => {
+					for operand in instruction.operands_mut() {
+						if let RegisterOrWord::Word(word) = operand {
+							*word = self.semantics.literal(word);
+						}
+					}
+				}
+			}
+		}
+
+		let end = address + program.rows.len();
+
+		self.memory.load_program(program, address);
+
+		if self.encoded_instructions {
+			// The instructions are encoded once loaded, so that the memory knows which rows are code
+			let loaded = Program {
+				rows: self.memory[address..end].to_vec(),
+				..Default::default()
+			};
+
+			let encoded = loaded.encoded()?;
+			self.memory[address..end].clone_from_slice(&encoded.rows);
+		}
+
+		Ok(())
+	}
+
+	/// All the labels of the loaded programs, at their absolute address in memory.
+	/// Different programs may define the same label, in which case it appears multiple times.
+	pub fn symbols(&self) -> &[(LabelIdentifier, Address)] {
+		self.memory.symbols().symbols()
+	}
+
+	/// The symbol table of the loaded programs, to render addresses symbolically.
+	pub fn symbol_table(&self) -> &SymbolTable {
+		self.memory.symbols()
+	}
+
+	/// The registers, with the addresses of capabilities rendered symbolically.
+	pub fn symbolic_registers(&self) -> HashMap<Register, String> {
+		self.registers
+			.iter()
+			.map(|(register, word)| (*register, self.symbol_table().format_word(word)))
+			.collect()
+	}
+
+	/// Where the row at the given address comes from in the source of the program it was loaded from, if any.
+	pub fn source_location(&self, address: Address) -> Option<&SourceLocation> {
+		self.memory.source_location(address)
+	}
+
+	pub fn encoded_instructions(&self) -> bool {
+		self.encoded_instructions
+	}
+
+	pub fn semantics(&self) -> Semantics {
+		self.semantics
+	}
+
+	pub fn registers(&self) -> &HashMap<Register, Word> {
+		&self.registers
 	}
 
 	pub fn read_register(&self, register: Register) -> Word {
@@ -124,11 +279,26 @@ impl Machine {
 	}
 
 	pub fn write_register(&mut self, register: Register, value: Word) {
-		self.registers.insert(register, value.clone());
+		let old = self.read_register(register);
+		self.notify(|o, m| o.register_write(m, register, &old, &value));
 
-		if register != Register::PC {
-			self.append_backtrace(format!("{} = {}", register, value));
-		}
+		self.registers.insert(register, value);
+	}
+
+	/// Reads a row of memory on behalf of an instruction.
+	pub fn read_memory(&mut self, address: Address) -> Row {
+		let row = self.memory[address].clone();
+		self.notify(|o, m| o.memory_read(m, address, &row));
+
+		row
+	}
+
+	/// Writes a row of memory on behalf of an instruction.
+	pub fn write_memory(&mut self, address: Address, row: Row) {
+		let old = self.memory[address].clone();
+		self.notify(|o, m| o.memory_write(m, address, &old, &row));
+
+		self.memory[address] = row;
 	}
 
 	pub fn verify_capability(&self, signed_capability: Signed<Capability>) -> Option<Capability> {
@@ -139,6 +309,13 @@ impl Machine {
 		capability.signed(&self.signing_key)
 	}
 
+	/// Signs a capability that an instruction created from an existing one.
+	pub fn derive_capability(&mut self, parent: Capability, child: Capability) -> Signed<Capability> {
+		self.notify(|o, m| o.capability_derived(m, &parent, &child));
+
+		self.sign_capability(child)
+	}
+
 	pub fn get_register_capability(&self, register: Register) -> Option<Capability> {
 		if let Word::Capability(capability) = self.read_register(register) {
 			self.verify_capability(capability)
@@ -147,68 +324,231 @@ impl Machine {
 		}
 	}
 
+	pub fn interrupt_table(&self) -> &HashMap<Interrupt, Address> {
+		&self.interrupt_table
+	}
+
 	pub fn set_interrupt_address(&mut self, interrupt: Interrupt, address: Address) {
 		self.interrupt_table.insert(interrupt, address);
 	}
 
+	/// The row at the address of the interrupt, adding the interrupt to the table at 0x0 if it isn't there yet.
 	pub fn get_interrupt_memory(&mut self, interrupt: Interrupt) -> Row {
-		self.memory[*self.interrupt_table.entry(interrupt).or_insert(Address(0x0))].clone()
+		let address = *self.interrupt_table.entry(interrupt).or_insert_with(|| {
+			if let Some(history) = &mut self.observers.history {
+				history.interrupt_added(interrupt);
+			}
+
+			Address(0x0)
+		});
+
+		self.memory[address].clone()
 	}
 
-	pub fn new_backtrace(&mut self, message: String) {
-		self.backtrace.push(vec![message]);
+	/// Attaches an observer that gets notified of everything happening during execution.
+	/// The observer is shared, so that its results can be read once the machine is done,
+	/// and Send, so that the machine can still be moved to another thread.
+	pub fn add_observer(&mut self, observer: Arc<Mutex<dyn ExecObserver + Send>>) {
+		self.observers.attached.push(observer);
 	}
 
-	pub fn append_backtrace(&mut self, message: String) {
-		if let Some(last) = self.backtrace.last_mut() {
-			last.push(message);
-		} else {
-			self.new_backtrace(message);
+	/// Notifies all observers of an event.
+	/// The observers are taken out of the machine while notifying them, so they can look at the machine.
+	pub(crate) fn notify(&mut self, event: impl FnMut(&mut dyn ExecObserver, &Machine)) {
+		let mut observers = std::mem::take(&mut self.observers);
+		observers.notify(self, event);
+		self.observers = observers;
+	}
+
+	/// Notifies all observers that the machine fails because a premise of a rule doesn't hold.
+	pub(crate) fn fault(&mut self, violation: Violation) {
+		let message = violation.to_string();
+
+		self.notify(|o, m| o.violation(m, &violation));
+		self.notify(|o, m| o.fault(m, &message));
+	}
+
+	/// Adds a condition that stops the machine early as soon as it is met.
+	pub fn add_stop_condition(&mut self, condition: StopCondition) {
+		self.observers.stop_watcher.conditions.push(condition);
+	}
+
+	/// Checks whether a stop condition was met during the last step, and if so stops the machine.
+	pub(crate) fn check_stop_conditions(&mut self, pc_moved: bool) -> bool {
+		let mut stop_watcher = std::mem::take(&mut self.observers.stop_watcher);
+		let reason = stop_watcher.check(self, pc_moved);
+		self.observers.stop_watcher = stop_watcher;
+
+		let Some(reason) = reason else {
+			return false;
+		};
+
+		self.exec_state = State::Stopped;
+		self.stop_reason = Some(reason);
+
+		true
+	}
+
+	/// Why the machine was stopped early, if it was.
+	pub fn stop_reason(&self) -> Option<&str> {
+		self.stop_reason.as_deref()
+	}
+
+	/// Starts recording the changes made by every step, which allows stepping backwards (see step_back()).
+	/// Changes made to the machine in between steps (e.g. writing a register by hand) count as part of the previous step.
+	/// Only the latest steps are kept, like in the trace (see set_recorded_steps()).
+	pub fn record_history(&mut self) {
+		self.observers.history = Some(History::new(self.observers.trace.limit()));
+	}
+
+	/// Sets how many of the latest steps the trace and the history keep, or keeps them all if None.
+	/// DEFAULT_RECORDED_STEPS by default, so that recording a machine that never stops doesn't run out of memory.
+	pub fn set_recorded_steps(&mut self, limit: Option<usize>) {
+		self.observers.trace.set_limit(limit);
+
+		if let Some(history) = &mut self.observers.history {
+			history.set_limit(limit);
 		}
 	}
 
+	/// The recorded history, if record_history() was called.
+	pub fn history(&self) -> Option<&History> {
+		self.observers.history.as_ref()
+	}
+
+	pub(crate) fn begin_history_step(&mut self) {
+		if let Some(history) = &mut self.observers.history {
+			let trace_len = self.observers.trace.recorded_steps();
+			history.begin_step(self.exec_state, self.stop_reason.clone(), trace_len);
+		}
+	}
+
+	/// Starts recording where every capability created by an instruction comes from (see Provenance).
+	/// Only the capabilities derived after this call have a provenance.
+	pub fn record_provenance(&mut self) {
+		let steps = self.observers.trace.recorded_steps();
+		self.observers.provenance = Some(Provenance::starting_at(steps));
+	}
+
+	/// The recorded provenance, if record_provenance() was called.
+	pub fn provenance(&self) -> Option<&Provenance> {
+		self.observers.provenance.as_ref()
+	}
+
+	/// Renders the derivation chain of the capability, if the provenance is being recorded.
+	pub fn format_provenance(&self, capability: &Capability) -> Option<String> {
+		Some(self.provenance()?.format_chain(self, capability))
+	}
+
+	/// Undoes the last recorded step. Returns false if there is no step to undo.
+	/// Attached observers aren't notified of undone steps.
+	pub fn step_back(&mut self) -> bool {
+		let Some(step) = self.observers.history.as_mut().and_then(History::pop) else {
+			return false;
+		};
+
+		for (register, word) in step.registers.into_iter().rev() {
+			match word {
+				Some(word) => self.registers.insert(register, word),
+				None => self.registers.remove(&register),
+			};
+		}
+
+		for (address, row) in step.memory.into_iter().rev() {
+			self.memory[address] = row;
+		}
+
+		for interrupt in step.interrupts {
+			self.interrupt_table.remove(&interrupt);
+		}
+
+		self.exec_state = step.state;
+		self.stop_reason = step.stop_reason;
+		self.observers.trace.truncate(step.trace_len);
+
+		if let Some(provenance) = &mut self.observers.provenance {
+			provenance.truncate(step.trace_len);
+		}
+
+		true
+	}
+
+	/// Moves the machine to the position right after the given number of recorded steps,
+	/// stepping backwards or forwards as needed.
+	/// Returns false if the machine stopped before reaching that step, if that step isn't kept anymore
+	/// (see History::first_step()), or if no history is being recorded.
+	pub fn goto_step(&mut self, step: usize) -> bool {
+		loop {
+			let Some(current) = self.history().map(History::position) else {
+				return false;
+			};
+
+			if current == step {
+				return true;
+			}
+
+			if current > step {
+				if !self.step_back() {
+					return false;
+				}
+			} else if let StepOutcome::Stopped(_) = self.step() {
+				return self.history().is_some_and(|history| history.position() == step);
+			}
+		}
+	}
+
+	/// The structured trace of everything the machine did so far, one step at a time.
+	pub fn trace(&self) -> &Trace {
+		&self.observers.trace
+	}
+
+	/// A human-readable rendering of the trace.
+	pub fn backtrace(&self) -> Backtrace<'_> {
+		Backtrace::new(&self.observers.trace, self.symbol_table())
+	}
+
+	/// An instruction-level profile of the trace.
+	pub fn profile(&self) -> Profile<'_> {
+		Profile::new(&self.observers.trace, self.symbol_table())
+	}
+
 	pub fn print_status(&self) {
 		let state = self.exec_state;
 		let pc = &self.read_register(Register::PC);
 
 		if let Some(Capability { address, .. }) = self.get_register_capability(Register::PC) {
-			println!("Machine status: {} at address {}", state, address)
+			match self.source_location(address) {
+				Some(location) => println!("Machine status: {} at address {} ({})", state, address, location),
+				None => println!("Machine status: {} at address {}", state, address),
+			}
 		} else {
 			println!("Machine status: {} at invalid address (PC = {})", state, pc)
 		}
-	}
 
-	pub fn print_backtrace(&self) {
-		println!("Machine backtrace:");
-
-		if self.backtrace.is_empty() {
-			println!("(Empty)");
-			return;
+		if let Some(reason) = self.stop_reason() {
+			println!("Stop reason: {}", reason)
 		}
 
-		let collumn_sizes = self.backtrace.iter().fold(Vec::<usize>::new(), |mut acc, trace| {
-			while acc.len() < trace.len() {
-				acc.push(0)
-			}
-			for (i, a) in acc.iter_mut().enumerate() {
-				if i < trace.len() - 1 {
-					*a = max(*a, trace.get(i).map(String::len).unwrap_or(0))
-				}
+		if let Some(violation) = self.failure() {
+			println!("Failure: {}", violation);
+
+			if let Some(rule) = violation.describe_rule(self.semantics()) {
+				println!("{}", rule);
 			}
-			acc
-		});
+		}
+	}
 
-		for trace in &self.backtrace {
-			println!(
-				"> {}",
-				trace
-					.iter()
-					.enumerate()
-					.map(|(i, s)| format!("{:<width$}", s, width = collumn_sizes[i]))
-					.collect::<Vec<String>>()
-					.join(" | ")
-			);
+	/// Why the machine failed, if the premise of a rule didn't hold during the step it failed for good in.
+	pub fn failure(&self) -> Option<&Violation> {
+		if self.exec_state != State::Failed {
+			return None;
 		}
+
+		self.trace().steps().last()?.violation.as_ref()
+	}
+
+	pub fn print_backtrace(&self) {
+		self.backtrace().print();
 	}
 }
 
@@ -218,7 +558,7 @@ impl Display for Machine {
 
 		let exec_state = self.exec_state;
 		let interrupt_table = indent_string(&pretty_hashmap(&self.interrupt_table), indent);
-		let registers = indent_string(&pretty_hashmap(&self.registers), indent);
+		let registers = indent_string(&pretty_hashmap(&self.symbolic_registers()), indent);
 		let memory = indent_string(&format!("{}", self.memory), indent);
 
 		let inner_machine = indent_string(
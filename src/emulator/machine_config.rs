@@ -9,10 +9,13 @@ use serde::{Deserialize, Serialize};
 use crate::compiler;
 
 use super::{
+	leak::DomainPolicy,
 	machine::Interrupt,
 	permission::Permission,
 	program::{AddrInt, Address, Capability, Program, Register, Word, WordChar, WordInt},
+	semantics::Semantics,
 	signed::Signed,
+	stop_condition::StopCondition,
 };
 
 /*
@@ -33,6 +36,29 @@ pub struct MachineConfig {
 
 	#[serde(default)]
 	pub interrupt_table: HashMap<Interrupt, AddrInt>,
+
+	/// If set, instructions are stored in memory as encoded integer words and decoded when fetched,
+	/// which allows code to be read, copied and generated like any other data.
+	#[serde(default)]
+	pub encoded_instructions: bool,
+
+	/// Selects between the rules of this emulator and the exact published Cerise rules (see semantics.rs).
+	#[serde(default)]
+	pub semantics: Semantics,
+
+	/// Conditions that stop the machine early as soon as one of them is met (see stop_condition.rs).
+	#[serde(default)]
+	pub stop_conditions: Vec<StopCondition>,
+
+	/// What the code regions may receive in registers when entered through an E-capability,
+	/// checked when looking for capability leaks (see leak.rs).
+	#[serde(default)]
+	pub domains: Vec<DomainPolicy>,
+
+	/// How many of the latest steps the trace and the history keep, which bounds the backtrace, the profile
+	/// and how far the debugger can step back. DEFAULT_RECORDED_STEPS (see step_log.rs) if not set.
+	#[serde(default)]
+	pub recorded_steps: Option<usize>,
 }
 
 impl MachineConfig {
@@ -84,7 +110,8 @@ impl ProgramConfig {
 	pub fn compiled(self) -> Program {
 		match self {
 			ProgramConfig::SourceFile(path) => {
-				ProgramConfig::Source(fs::read_to_string(path).expect("Couldn't read source file")).compiled()
+				let source = fs::read_to_string(&path).expect("Couldn't read source file");
+				compiler::compile_unwrapped(&source, Some(&path))
 			}
 
 			ProgramConfig::CompiledFile(path) => {
@@ -95,10 +122,45 @@ impl ProgramConfig {
 				ron::de::from_str(&source).expect("Couldn't deserialize program config")
 			}
 
-			ProgramConfig::Source(source) => compiler::compile_unwrapped(&source),
+			ProgramConfig::Source(source) => compiler::compile_unwrapped(&source, None),
 			ProgramConfig::CompiledProgram(program) => program,
 		}
 	}
+
+	/// Like compiled(), but returns an error instead of panicking, and doesn't print compilation errors.
+	pub fn try_compiled(self) -> Result<Program, String> {
+		match self {
+			ProgramConfig::SourceFile(path) => {
+				let source = fs::read_to_string(&path).map_err(|e| format!("Couldn't read {}: {}", path, e))?;
+				try_compile(&source, Some(&path))
+			}
+
+			ProgramConfig::CompiledFile(path) => {
+				let source = fs::read_to_string(&path).map_err(|e| format!("Couldn't read {}: {}", path, e))?;
+				ProgramConfig::CompiledRon(source).try_compiled()
+			}
+
+			ProgramConfig::CompiledRon(source) => {
+				ron::de::from_str(&source).map_err(|e| format!("Couldn't deserialize program config: {}", e))
+			}
+
+			ProgramConfig::Source(source) => try_compile(&source, None),
+			ProgramConfig::CompiledProgram(program) => Ok(program),
+		}
+	}
+}
+
+/// Compiles the source, describing a compilation error by its line.
+fn try_compile(source: &str, file: Option<&str>) -> Result<Program, String> {
+	compiler::compile_with_file(source, file).map_err(|e| {
+		let line = source[..e.span().start.min(source.len())].lines().count().max(1);
+		format!(
+			"Couldn't compile {}:{}: {}",
+			file.unwrap_or("<source>"),
+			line,
+			e.message()
+		)
+	})
 }
 
 /// This type exists just to make it less annoying to write MachineConfigs in ron.
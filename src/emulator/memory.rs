@@ -1,12 +1,16 @@
 use ::std::boxed::Box;
 use std::{
+	collections::{BTreeMap, BTreeSet},
 	fmt::{self, Display, Formatter},
 	ops::{Index, IndexMut, Range},
 };
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-use super::program::{Address, Program, Row};
+use super::{
+	program::{Address, Program, Row, SourceLocation},
+	symbols::SymbolTable,
+};
 
 /*
 --------------------------------------------------------------------------------
@@ -16,9 +20,21 @@ use super::program::{Address, Program, Row};
 
 pub const DEFAULT_SIZE: usize = 256;
 
-#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct Memory {
 	rows: Box<[Row]>,
+
+	/// Where the rows loaded from compiled programs come from in their source
+	#[serde(skip)]
+	sources: BTreeMap<Address, SourceLocation>,
+
+	/// The rows loaded as instructions from programs, as opposed to data
+	#[serde(skip)]
+	code: BTreeSet<Address>,
+
+	/// The labels of the loaded programs
+	#[serde(default, skip_serializing_if = "SymbolTable::is_empty")]
+	symbols: SymbolTable,
 }
 
 impl Memory {
@@ -26,6 +42,9 @@ impl Memory {
 		// We need to first create a vec and then get a slice from it to make sure we create everything directly on the heap
 		Self {
 			rows: vec![Row::default(); size].into_boxed_slice(),
+			sources: BTreeMap::new(),
+			code: BTreeSet::new(),
+			symbols: SymbolTable::default(),
 		}
 	}
 
@@ -36,7 +55,40 @@ impl Memory {
 	}
 
 	pub fn load_program(&mut self, program: Program, address: Address) {
+		self.symbols.add_program(&program, address);
+
 		self.rows.as_mut()[address.0..program.rows.len() + address.0].clone_from_slice(&program.rows);
+
+		for (i, row) in program.rows.iter().enumerate() {
+			if let Row::Instruction(_) = row {
+				self.code.insert(address + i);
+			}
+		}
+
+		for (i, location) in program.debug_info.into_iter().enumerate() {
+			self.sources.insert(address + i, location);
+		}
+	}
+
+	pub fn symbols(&self) -> &SymbolTable {
+		&self.symbols
+	}
+
+	/// Where the row loaded at the given address comes from in its source, if it was loaded from a compiled program.
+	/// The location isn't updated when the row is overwritten later on.
+	pub fn source_location(&self, address: Address) -> Option<&SourceLocation> {
+		self.sources.get(&address)
+	}
+
+	/// The source locations of all the rows loaded from compiled programs.
+	pub fn source_locations(&self) -> impl Iterator<Item = (Address, &SourceLocation)> {
+		self.sources.iter().map(|(address, location)| (*address, location))
+	}
+
+	/// Whether the row at the given address was loaded as an instruction from a program.
+	/// Like source locations, this isn't updated when the row is overwritten later on.
+	pub fn is_code(&self, address: Address) -> bool {
+		self.code.contains(&address)
 	}
 
 	pub fn mem_size(&self) -> usize {
@@ -78,46 +130,85 @@ impl IndexMut<Range<Address>> for Memory {
 	}
 }
 
-impl Display for Memory {
-	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+impl Memory {
+	/// Renders the rows in the range, one per line, with their labels and source locations.
+	/// Runs of rows that aren't visible are collapsed into a single "..." line,
+	/// and only the first and last address of every run of visible rows is shown.
+	pub fn format_rows(&self, range: Range<Address>, visible: impl Fn(Address, &Row) -> bool) -> String {
 		let mut out = Vec::<String>::new();
-		let mut is_zero = true;
-
-		let mem_size = format!("{}", Address(self.mem_size() - 1));
-		let address_padding = mem_size.chars().count();
-
-		for (address, row) in self.rows.iter().enumerate() {
-			let address = Address(address);
-
-			if row == &Row::default() {
-				if !is_zero {
+		let mut is_hidden = true;
+
+		let Some(last_address) = range
+			.end
+			.0
+			.checked_sub(1)
+			.map(Address)
+			.filter(|_| range.start < range.end)
+		else {
+			return "(Empty)".to_owned();
+		};
+
+		let address_padding = format!("{}", last_address).chars().count();
+
+		// Align the source locations of the rows
+		let row_padding = self
+			.sources
+			.range(range.clone())
+			.map(|(address, _)| self.symbols.format_row(&self[*address]).chars().count())
+			.max()
+			.unwrap_or(0);
+
+		for address in (range.start.0..range.end.0).map(Address) {
+			let row = &self[address];
+
+			if !visible(address, row) {
+				if !is_hidden {
 					let last = out.len() - 1;
 					out[last].replace_range(..address_padding, &format!("{:<address_padding$}", address - 1));
 
 					out.push(format!("{:<address_padding$} ...", ""));
-					is_zero = true;
+					is_hidden = true;
 				}
 			} else {
-				let row = match row {
-					Row::Word(w) => w as &dyn Display,
-					Row::Instruction(i) => i as &dyn Display,
+				let labels = self.symbols.labels_at(address);
+				let row = self.symbols.format_row(row);
+
+				let row = match self.source_location(address) {
+					Some(location) => format!("{:<row_padding$} ; {}", row, location),
+					None => row,
 				};
 
-				if is_zero {
-					out.push(format!("{:<address_padding$} | {}", address, row));
-				} else {
-					out.push(format!("{:<address_padding$} | {}", "", row));
+				let lines = labels.map(|label| format!("{}:", label)).chain([row]);
+
+				for line in lines {
+					if is_hidden {
+						out.push(format!("{:<address_padding$} | {}", address, line));
+					} else {
+						out.push(format!("{:<address_padding$} | {}", "", line));
+					}
+					is_hidden = false;
 				}
-				is_zero = false;
 			}
 		}
 
+		if out.is_empty() {
+			return "(Empty)".to_owned();
+		}
+
 		let last = out.len() - 1;
-		out[last].replace_range(
-			..address_padding,
-			&format!("{:<address_padding$}", Address(self.mem_size() - 1)),
-		);
+		out[last].replace_range(..address_padding, &format!("{:<address_padding$}", last_address));
+
+		out.join("\n")
+	}
+}
+
+impl Display for Memory {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		// Rows with labels are always shown, even if they are zero
+		let rows = self.format_rows(Address(0)..Address(self.mem_size()), |address, row| {
+			row != &Row::default() || self.symbols.labels_at(address).next().is_some()
+		});
 
-		f.pad(&("\n".to_owned() + &out.join("\n")))
+		f.pad(&("\n".to_owned() + &rows))
 	}
 }
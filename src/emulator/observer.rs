@@ -0,0 +1,116 @@
+use std::{
+	fmt::{self, Debug, Formatter},
+	sync::{Arc, Mutex},
+};
+
+use super::{
+	history::History,
+	instruction::Instruction,
+	machine::{Interrupt, Machine, State, StepOutcome},
+	program::{Address, Capability, Register, Row, Word},
+	provenance::Provenance,
+	rules::Violation,
+	stop_condition::StopWatcher,
+	trace::Trace,
+};
+
+/*
+--------------------------------------------------------------------------------
+||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||
+--------------------------------------------------------------------------------
+*/
+
+/// Callbacks for the events happening while a machine executes, see Machine::add_observer().
+///
+/// Every callback has an empty default implementation, so observers only implement the events they care about.
+/// The machine is passed along in the state it is in when the event happens
+/// (e.g. register_write() is called before the register is actually written).
+#[allow(unused_variables)]
+pub trait ExecObserver {
+	/// Called at the start of every step, before the instruction is fetched.
+	fn before_step(&mut self, machine: &Machine) {}
+
+	/// Called once an instruction has been fetched (and decoded) from memory, right before it is executed.
+	fn before_instruction(&mut self, machine: &Machine, address: Address, instruction: &Instruction) {}
+
+	/// Called after an instruction was executed, with the state it resulted in.
+	fn after_instruction(&mut self, machine: &Machine, address: Address, instruction: &Instruction, state: State) {}
+
+	/// Called whenever a register is written to, including PC.
+	fn register_write(&mut self, machine: &Machine, register: Register, old: &Word, new: &Word) {}
+
+	/// Called whenever an instruction reads data from memory.
+	/// Instruction fetches are reported through before_instruction() instead.
+	fn memory_read(&mut self, machine: &Machine, address: Address, row: &Row) {}
+
+	/// Called whenever an instruction writes to memory.
+	fn memory_write(&mut self, machine: &Machine, address: Address, old: &Row, new: &Row) {}
+
+	/// Called whenever an instruction creates a new capability from an existing one,
+	/// including when PC is incremented and when jumping to an E-capability turns it into an RX one.
+	fn capability_derived(&mut self, machine: &Machine, parent: &Capability, child: &Capability) {}
+
+	/// Called whenever a jump instruction is executed, with the word that is jumped to, and whether the jump is taken.
+	/// Unlike the other events, a taken jump is reported after its registers were written: the machine is passed
+	/// with PC already at the target (and with the link of a jal), i.e. in the state the jump resulted in.
+	/// A jump that isn't taken is reported before PC is incremented.
+	fn jump(&mut self, machine: &Machine, target: &Word, taken: bool) {}
+
+	/// Called whenever the machine fails because a premise of a rule doesn't hold, right before fault().
+	fn violation(&mut self, machine: &Machine, violation: &Violation) {}
+
+	/// Called whenever the machine fails, with a description of what went wrong.
+	fn fault(&mut self, machine: &Machine, message: &str) {}
+
+	/// Called whenever the machine recovers from halting or failing by jumping to the interrupt table.
+	fn interrupt(&mut self, machine: &Machine, interrupt: Interrupt, destination: &Word) {}
+
+	/// Called when the machine reaches a HALTED or FAILED state for good, or when a stop condition stops it early.
+	fn stopped(&mut self, machine: &Machine, state: State) {}
+
+	/// Called at the end of every step, with its outcome.
+	fn after_step(&mut self, machine: &Machine, outcome: StepOutcome) {}
+}
+
+/// The observers attached to a machine.
+/// The trace and the stop watcher are always attached, the history and the provenance only if they are being recorded,
+/// and the other observers are shared with whoever attached them.
+#[derive(Clone, Default)]
+pub(crate) struct Observers {
+	pub(crate) trace: Trace,
+	pub(crate) stop_watcher: StopWatcher,
+	pub(crate) history: Option<History>,
+	pub(crate) provenance: Option<Provenance>,
+	pub(crate) attached: Vec<Arc<Mutex<dyn ExecObserver + Send>>>,
+}
+
+impl Observers {
+	pub(crate) fn notify(&mut self, machine: &Machine, mut event: impl FnMut(&mut dyn ExecObserver, &Machine)) {
+		event(&mut self.trace, machine);
+		event(&mut self.stop_watcher, machine);
+
+		if let Some(history) = &mut self.history {
+			event(history, machine);
+		}
+
+		if let Some(provenance) = &mut self.provenance {
+			event(provenance, machine);
+		}
+
+		for observer in &self.attached {
+			event(&mut *observer.lock().expect("An observer panicked."), machine);
+		}
+	}
+}
+
+impl Debug for Observers {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Observers")
+			.field("trace", &self.trace.recorded_steps())
+			.field("stop_watcher", &self.stop_watcher)
+			.field("history", &self.history.as_ref().map(History::position))
+			.field("provenance", &self.provenance.is_some())
+			.field("attached", &self.attached.len())
+			.finish()
+	}
+}
@@ -0,0 +1,258 @@
+use std::{
+	collections::BTreeMap,
+	io::{self, Write},
+};
+
+use super::{
+	instruction::Instruction,
+	permission::Permission,
+	program::{Address, Register, Word},
+	symbols::SymbolTable,
+	trace::{Effect, Trace},
+};
+
+/*
+--------------------------------------------------------------------------------
+||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||
+--------------------------------------------------------------------------------
+*/
+
+/// The name used for instructions that aren't covered by any label.
+pub const UNLABELLED: &str = "<unlabelled>";
+
+/// How many entries of every table are printed in the report.
+const REPORT_SIZE: usize = 10;
+
+/// A loop found in the trace, i.e. a backwards jump within a program that was taken at least once.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HotLoop {
+	/// The address jumped back to, the start of the loop
+	pub head: Address,
+
+	/// The address of the instruction jumping back, the end of the loop
+	pub tail: Address,
+
+	/// How many times the backwards jump was taken
+	pub iterations: usize,
+
+	/// How many instructions were executed between the head and the tail (inclusive)
+	pub instructions: usize,
+}
+
+/// A frame of the reconstructed call stack.
+#[derive(Clone, Debug)]
+struct Frame {
+	name: String,
+
+	/// Where the frame returns to, if it was entered with jal
+	return_address: Option<Address>,
+}
+
+/// An instruction-level profile of a trace: how many instructions were executed per address,
+/// per label region and per opcode, the hottest loops, and the call stacks the instructions were executed in.
+///
+/// Call stacks are reconstructed from the jumps to E-capabilities: a jump to the address a jal linked to returns
+/// to the frame of that jal, any other jump to an E-capability (or an interrupt) enters a new frame.
+#[derive(Clone, Debug)]
+pub struct Profile<'a> {
+	symbols: &'a SymbolTable,
+
+	total: usize,
+	addresses: BTreeMap<Address, usize>,
+	instructions: BTreeMap<Address, Instruction>,
+	labels: BTreeMap<String, usize>,
+	opcodes: BTreeMap<&'static str, usize>,
+	back_edges: BTreeMap<(Address, Address), usize>,
+	stacks: BTreeMap<String, usize>,
+}
+
+impl<'a> Profile<'a> {
+	pub fn new(trace: &Trace, symbols: &'a SymbolTable) -> Self {
+		let mut profile = Self {
+			symbols,
+			total: 0,
+			addresses: BTreeMap::new(),
+			instructions: BTreeMap::new(),
+			labels: BTreeMap::new(),
+			opcodes: BTreeMap::new(),
+			back_edges: BTreeMap::new(),
+			stacks: BTreeMap::new(),
+		};
+
+		let mut stack = Vec::<Frame>::new();
+		let mut interrupted = false;
+
+		for step in trace.steps() {
+			let (Some(address), Some(instruction)) = (step.address, &step.instruction) else {
+				continue;
+			};
+
+			// The first instruction and interrupt handlers start a new frame
+			if stack.is_empty() || std::mem::take(&mut interrupted) {
+				stack.push(Frame {
+					name: profile.label(address),
+					return_address: None,
+				});
+			}
+
+			profile.total += 1;
+			*profile.addresses.entry(address).or_default() += 1;
+			*profile.labels.entry(profile.label(address)).or_default() += 1;
+			*profile.opcodes.entry(instruction.mnemonic()).or_default() += 1;
+			profile.instructions.insert(address, instruction.clone());
+
+			let frames = stack.iter().map(|frame| frame.name.as_str()).collect::<Vec<_>>();
+			*profile.stacks.entry(frames.join(";")).or_default() += 1;
+
+			let entered = step.effects.iter().find_map(|effect| match effect {
+				Effect::Jump {
+					target: Word::Capability(target),
+					taken: true,
+				} if target.inner().perm == Permission::E => Some(target.inner().address),
+				_ => None,
+			});
+
+			let new_pc = step.effects.iter().rev().find_map(|effect| match effect {
+				Effect::Register {
+					register: Register::PC,
+					value: Word::Capability(pc),
+				} => Some(pc.inner().address),
+				_ => None,
+			});
+
+			if let Some(target) = entered {
+				if let Some(frame) = stack.iter().rposition(|frame| frame.return_address == Some(target)) {
+					stack.truncate(frame);
+				} else {
+					stack.push(Frame {
+						name: profile.label(target),
+						return_address: matches!(instruction, Instruction::Jal(..)).then_some(address + 1),
+					});
+				}
+			} else if let Some(pc) = new_pc.filter(|_| step.interrupt.is_none()) {
+				// Jumping backwards within the same program, without entering a new frame, is a loop
+				if pc <= address && symbols.program_bounds(pc) == symbols.program_bounds(address) {
+					*profile.back_edges.entry((pc, address)).or_default() += 1;
+				}
+			}
+
+			interrupted = step.interrupt.is_some();
+		}
+
+		profile
+	}
+
+	/// The label region the address is in, i.e. the closest label at or before it.
+	fn label(&self, address: Address) -> String {
+		self.symbols
+			.closest_label(address)
+			.map_or_else(|| UNLABELLED.to_owned(), |(_, label)| label.clone())
+	}
+
+	/// The total number of instructions executed.
+	pub fn total(&self) -> usize {
+		self.total
+	}
+
+	/// The number of times the instruction at every address was executed.
+	pub fn addresses(&self) -> &BTreeMap<Address, usize> {
+		&self.addresses
+	}
+
+	/// The number of instructions executed in every label region, see UNLABELLED for the instructions outside of labels.
+	pub fn labels(&self) -> &BTreeMap<String, usize> {
+		&self.labels
+	}
+
+	/// The number of times every opcode was executed, by mnemonic.
+	pub fn opcodes(&self) -> &BTreeMap<&'static str, usize> {
+		&self.opcodes
+	}
+
+	/// The loops of the trace, hottest (most instructions executed inside of it) first.
+	pub fn hot_loops(&self) -> Vec<HotLoop> {
+		let mut loops = self
+			.back_edges
+			.iter()
+			.map(|(&(head, tail), &iterations)| HotLoop {
+				head,
+				tail,
+				iterations,
+				instructions: self.addresses.range(head..=tail).map(|(_, count)| count).sum(),
+			})
+			.collect::<Vec<_>>();
+
+		loops.sort_by(|a, b| b.instructions.cmp(&a.instructions).then(a.head.cmp(&b.head)));
+		loops
+	}
+
+	/// The number of instructions executed in every call stack, with the frames separated by semicolons.
+	pub fn stacks(&self) -> &BTreeMap<String, usize> {
+		&self.stacks
+	}
+
+	/// Writes the call stacks in the collapsed stack format ("frame;frame;frame count" per line),
+	/// which is understood by flamegraph tools.
+	pub fn write_collapsed_stacks(&self, mut output: impl Write) -> io::Result<()> {
+		for (stack, count) in &self.stacks {
+			writeln!(output, "{} {}", stack, count)?;
+		}
+
+		output.flush()
+	}
+
+	pub fn print(&self) {
+		println!("Machine profile: {} instructions executed", self.total);
+
+		if self.total == 0 {
+			return;
+		}
+
+		let percent = |count: usize| count as f64 * 100.0 / self.total as f64;
+
+		println!("\nHottest labels:");
+		for (label, count) in sorted_by_count(&self.labels) {
+			println!("> {:>8} {:>6.2}% | {}", count, percent(count), label);
+		}
+
+		println!("\nHottest opcodes:");
+		for (opcode, count) in sorted_by_count(&self.opcodes) {
+			println!("> {:>8} {:>6.2}% | {}", count, percent(count), opcode);
+		}
+
+		println!("\nHottest addresses:");
+		for (address, count) in sorted_by_count(&self.addresses) {
+			println!(
+				"> {:>8} {:>6.2}% | {} ({}) | {}",
+				count,
+				percent(count),
+				address,
+				self.symbols.format_address(address),
+				self.instructions[&address]
+			);
+		}
+
+		println!("\nHottest loops:");
+		for hot_loop in self.hot_loops().iter().take(REPORT_SIZE) {
+			println!(
+				"> {:>8} {:>6.2}% | {} .. {} ({} iterations)",
+				hot_loop.instructions,
+				percent(hot_loop.instructions),
+				self.symbols.format_address(hot_loop.head),
+				self.symbols.format_address(hot_loop.tail),
+				hot_loop.iterations
+			);
+		}
+	}
+}
+
+/// The REPORT_SIZE entries with the highest counts, highest first.
+fn sorted_by_count<K: Clone + Ord>(counts: &BTreeMap<K, usize>) -> Vec<(K, usize)> {
+	let mut counts = counts
+		.iter()
+		.map(|(key, count)| (key.clone(), *count))
+		.collect::<Vec<_>>();
+	counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+	counts.truncate(REPORT_SIZE);
+	counts
+}
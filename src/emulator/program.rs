@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 
 use super::{instruction::Instruction, permission::Permission, signed::Signed};
@@ -19,6 +21,35 @@ pub type LabelIdentifier = String;
 #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
 pub struct Program {
 	pub rows: Vec<Row>,
+
+	/// The labels defined in the source, relative to the start of the program.
+	#[serde(default, skip_serializing_if = "HashMap::is_empty")]
+	pub labels: HashMap<LabelIdentifier, Address>,
+
+	/// Where every row comes from in the source, indexed like the rows. Empty if the program wasn't compiled from source.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub debug_info: Vec<SourceLocation>,
+
+	/// The layout the instructions were encoded with as integer words, see encoding::ENCODING_VERSION.
+	/// None if the program wasn't encoded.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub encoding_version: Option<u32>,
+}
+
+/// The position of a row in the assembly source it was compiled from.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SourceLocation {
+	/// The source file, if the program was compiled from a file
+	pub file: Option<String>,
+
+	/// The line of the row, starting at 1
+	pub line: usize,
+
+	/// The column of the row, starting at 1
+	pub column: usize,
+
+	/// The closest label defined at or before the row, if any
+	pub label: Option<LabelIdentifier>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
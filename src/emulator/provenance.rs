@@ -0,0 +1,238 @@
+use super::{
+	instruction::Instruction,
+	machine::Machine,
+	observer::ExecObserver,
+	program::{Address, Capability, Register, Word},
+};
+
+/*
+--------------------------------------------------------------------------------
+||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||
+--------------------------------------------------------------------------------
+*/
+
+/// What derived a capability from its parent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Cause {
+	/// An instruction (e.g. restrict, subseg, lea, or a jump turning an E-capability into an RX one).
+	Instruction { address: Address, instruction: Instruction },
+
+	/// PC being incremented the given number of times in a row, from one instruction to the next.
+	PcIncrements(usize),
+}
+
+/// The derivation of a capability from its parent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Derivation {
+	pub parent: Capability,
+	pub child: Capability,
+
+	/// The step the capability was derived during (the last step for PC increments)
+	pub step: usize,
+
+	pub cause: Cause,
+}
+
+/// A derivation as recorded. Consecutive PC increments are merged into a single record, so that
+/// the provenance doesn't grow by one record per step.
+#[derive(Clone, Debug)]
+struct Record {
+	parent: Capability,
+	child: Capability,
+
+	/// The instruction deriving the capability, None for PC increments
+	instruction: Option<(Address, Instruction)>,
+
+	/// The step of every derivation merged into the record, one per PC increment
+	steps: Vec<usize>,
+}
+
+impl Record {
+	/// The derivation of the capability, if this record derived it.
+	fn derivation(&self, capability: &Capability) -> Option<Derivation> {
+		match &self.instruction {
+			Some((address, instruction)) => (self.child == *capability).then(|| Derivation {
+				parent: self.parent,
+				child: self.child,
+				step: self.steps[0],
+				cause: Cause::Instruction {
+					address: *address,
+					instruction: instruction.clone(),
+				},
+			}),
+
+			None => {
+				let Capability { address, .. } = *capability;
+
+				let increments = (Capability { address, ..self.parent } == *capability
+					&& self.parent.address < address
+					&& address <= self.child.address)
+					.then(|| address.0 - self.parent.address.0)?;
+
+				Some(Derivation {
+					parent: self.parent,
+					child: *capability,
+					step: self.steps[increments - 1],
+					cause: Cause::PcIncrements(increments),
+				})
+			}
+		}
+	}
+}
+
+/// The built-in observer recording where every capability created by an instruction comes from,
+/// see Machine::record_provenance().
+///
+/// Capabilities are identified by their value, so a capability is considered to come from the last derivation
+/// that created the same value before it was used.
+#[derive(Clone, Debug, Default)]
+pub struct Provenance {
+	records: Vec<Record>,
+
+	/// The number of steps so far
+	steps: usize,
+
+	/// The instruction being executed in the current step
+	current: Option<(usize, Address, Instruction)>,
+
+	/// The record of the current run of PC increments, if any
+	increments: Option<usize>,
+}
+
+impl Provenance {
+	/// A provenance starting to record at the given step.
+	pub fn starting_at(step: usize) -> Self {
+		Self {
+			steps: step,
+			..Default::default()
+		}
+	}
+
+	/// The full derivation chain of the capability, from the capability itself back to the first capability
+	/// that wasn't derived by an instruction (usually the boot-time master capability).
+	pub fn chain(&self, capability: &Capability) -> Vec<Derivation> {
+		let mut chain = Vec::new();
+		let mut capability = *capability;
+		let mut end = self.records.len();
+
+		// A parent is always recorded before its child, which also guarantees that the chain ends
+		while let Some((index, derivation)) = self.records[..end]
+			.iter()
+			.enumerate()
+			.rev()
+			.find_map(|(index, record)| record.derivation(&capability).map(|derivation| (index, derivation)))
+		{
+			capability = derivation.parent;
+			end = index;
+			chain.push(derivation);
+		}
+
+		chain
+	}
+
+	/// Renders the derivation chain of the capability, one capability per line from the capability itself
+	/// back to its root, with what derived each capability from the next one.
+	pub fn format_chain(&self, machine: &Machine, capability: &Capability) -> String {
+		let symbols = machine.symbol_table();
+		let mut out = vec![symbols.format_capability(capability)];
+
+		let chain = self.chain(capability);
+
+		for derivation in &chain {
+			let cause = match &derivation.cause {
+				Cause::Instruction { address, instruction } => {
+					let location = match machine.source_location(*address) {
+						Some(location) => format!("{} ({})", symbols.format_address(*address), location),
+						None => symbols.format_address(*address),
+					};
+
+					format!("{} at {}", instruction, location)
+				}
+				Cause::PcIncrements(1) => "PC increment".to_owned(),
+				Cause::PcIncrements(n) => format!("{} PC increments", n),
+			};
+
+			out.push(format!("  <- derived at step {} by {}, from", derivation.step, cause));
+			out.push(symbols.format_capability(&derivation.parent));
+		}
+
+		let root = chain.last().map_or(*capability, |derivation| derivation.parent);
+
+		if root == machine.master_capability() {
+			out.push("  = the boot-time master capability".to_owned());
+		} else {
+			out.push("  = not derived by an instruction (loaded from the config, or written by hand)".to_owned());
+		}
+
+		out.join("\n")
+	}
+
+	/// Forgets the derivations of the given step and the steps after it.
+	pub(crate) fn truncate(&mut self, step: usize) {
+		for record in &mut self.records {
+			record.steps.retain(|s| *s < step);
+
+			if record.instruction.is_none() {
+				record.child.address = record.parent.address + record.steps.len();
+			}
+		}
+
+		self.records.retain(|record| !record.steps.is_empty());
+		self.steps = step;
+		self.increments = self.records.iter().rposition(|record| record.instruction.is_none());
+		self.current = None;
+	}
+}
+
+impl ExecObserver for Provenance {
+	fn before_step(&mut self, _: &Machine) {
+		self.steps += 1;
+		self.current = None;
+	}
+
+	fn before_instruction(&mut self, _: &Machine, address: Address, instruction: &Instruction) {
+		self.current = Some((self.steps - 1, address, instruction.clone()));
+	}
+
+	fn capability_derived(&mut self, machine: &Machine, parent: &Capability, child: &Capability) {
+		let Some((step, address, instruction)) = self.current.clone() else {
+			return;
+		};
+
+		let is_pc = matches!(machine.read_register(Register::PC), Word::Capability(pc) if pc.inner() == parent);
+
+		let is_increment = is_pc
+			&& *child
+				== Capability {
+					address: parent.address + 1,
+					..*parent
+				};
+
+		if !is_increment {
+			self.records.push(Record {
+				parent: *parent,
+				child: *child,
+				instruction: Some((address, instruction)),
+				steps: vec![step],
+			});
+			return;
+		}
+
+		// Extend the current run of PC increments if PC wasn't changed in between
+		match self.increments.map(|index| &mut self.records[index]) {
+			Some(record) if record.child == *parent => {
+				record.child = *child;
+				record.steps.push(step);
+			}
+			_ => {
+				self.increments = Some(self.records.len());
+				self.records.push(Record {
+					parent: *parent,
+					child: *child,
+					instruction: None,
+					steps: vec![step],
+				});
+			}
+		}
+	}
+}
@@ -0,0 +1,229 @@
+use serde::{Deserialize, Serialize};
+
+use super::{instruction::Instruction, semantics::Semantics};
+
+/*
+--------------------------------------------------------------------------------
+||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||
+--------------------------------------------------------------------------------
+*/
+
+/// A rule of the Cerise operational semantics: an instruction (or a helper of the semantics),
+/// the premises it requires, and its effect when they all hold. When a premise doesn't hold, the machine fails.
+///
+/// Rules marked as extended check more than Cerise does, e.g. load and store take an offset.
+/// Rules marked as modified go through helpers that differ from Cerise under Semantics::Modified,
+/// e.g. decodePerm or isWithin, see semantics::Semantics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rule {
+	/// The instruction with its operands, e.g. "load r1 r2 ρ", or the name of the helper
+	pub name: &'static str,
+
+	pub extended: bool,
+	pub modified: bool,
+
+	/// All the premises, whichever the semantics, see Rule::premises()
+	pub premises: &'static [&'static str],
+
+	/// The premises only checked under Semantics::Cerise
+	pub cerise_only: &'static [&'static str],
+
+	pub effect: &'static str,
+}
+
+impl Rule {
+	/// The premises the rule requires under the semantics, in the order they are checked.
+	pub fn premises(&self, semantics: Semantics) -> impl Iterator<Item = &'static str> + '_ {
+		self.premises
+			.iter()
+			.copied()
+			.filter(move |premise| semantics == Semantics::Cerise || !self.cerise_only.contains(premise))
+	}
+
+	/// Renders the rule under the semantics one premise per line, the given failed premise being marked with a cross.
+	pub fn describe(&self, failed: Option<&str>, semantics: Semantics) -> String {
+		let origin = if self.extended {
+			" (extended from Cerise)"
+		} else if self.modified && semantics == Semantics::Modified {
+			" (modified from Cerise)"
+		} else {
+			""
+		};
+
+		let mut lines = vec![format!("Rule {}{}:", self.name, origin)];
+
+		for premise in self.premises(semantics) {
+			let marker = if Some(premise) == failed { "✗" } else { " " };
+			lines.push(format!("  {} {}", marker, premise));
+		}
+
+		lines.push(format!("  ⇒ {}", self.effect));
+
+		lines.join("\n")
+	}
+}
+
+// The premises, named after what they require, shared by the rules that require the same thing
+pub const PC_CAPABILITY: &str = "φ.reg(pc) = (p, b, e, a)";
+pub const R_CAPABILITY: &str = "φ.reg(r) = (p, b, e, a)";
+pub const R2_CAPABILITY: &str = "φ.reg(r2) = (p, b, e, a)";
+pub const R2_CAPABILITY_2: &str = "φ.reg(r2) = (p2, b2, e2, a2)";
+pub const R3_CAPABILITY_3: &str = "φ.reg(r3) = (p3, b3, e3, a3)";
+pub const PC_IN_BOUNDS: &str = "b ≤ a < e";
+pub const PC_EXECUTABLE: &str = "p ∈ {rx, rwx}";
+pub const INSTRUCTION: &str = "decode(φ.mem(a)) is an instruction";
+pub const Z: &str = "z = getWord(φ, ρ)";
+pub const Z_OFFSET: &str = "z = getWord(φ, ρ1)";
+pub const Z1: &str = "z1 = getWord(φ, ρ1)";
+pub const Z2: &str = "z2 = getWord(φ, ρ2)";
+pub const Z_INTEGER: &str = "z ∈ Z";
+pub const Z1_INTEGER: &str = "z1 ∈ Z";
+pub const Z2_INTEGER: &str = "z2 ∈ Z";
+pub const Z_POSITIVE: &str = "0 ≤ z";
+pub const READABLE: &str = "p ∈ {ro, rx, rw, rwx}";
+pub const WRITABLE: &str = "p ∈ {rw, rwx}";
+pub const NOT_ENTER: &str = "p ≠ e";
+pub const OFFSET_IN_BOUNDS: &str = "b ≤ a + z < e";
+pub const LOADED_WORD: &str = "w = φ.mem(a + z) is a word";
+pub const STORED_WORD: &str = "w = getWord(φ, ρ2)";
+pub const NEW_PC: &str = "newPc = updatePcPerm(φ.reg(r))";
+pub const NEW_PC_R1: &str = "newPc = updatePcPerm(φ.reg(r1))";
+pub const NEW_PERM: &str = "p' = decodePerm(getWord(φ, ρ))";
+pub const PERM_FLOWS: &str = "p' ≼ p";
+pub const WITHIN: &str = "isWithin(z1, z2, b, e)";
+pub const LEA_ADDRESS: &str = "a + z ∈ [0, AddrMax]";
+pub const BASE_BEFORE_ADDRESS: &str = "b ≤ a";
+pub const LENGTH_IN_BOUNDS: &str = "a + z ≤ e";
+pub const PERM_1: &str = "p1 = decodePerm(getWord(φ, ρ1))";
+pub const PERM_2: &str = "p2 = decodePerm(getWord(φ, ρ2))";
+
+const fn rule(name: &'static str, premises: &'static [&'static str], effect: &'static str) -> Rule {
+	Rule {
+		name,
+		extended: false,
+		modified: false,
+		premises,
+		cerise_only: &[],
+		effect,
+	}
+}
+
+const fn extended(name: &'static str, premises: &'static [&'static str], effect: &'static str) -> Rule {
+	Rule {
+		extended: true,
+		..rule(name, premises, effect)
+	}
+}
+
+const fn modified(name: &'static str, premises: &'static [&'static str], effect: &'static str) -> Rule {
+	Rule {
+		modified: true,
+		..rule(name, premises, effect)
+	}
+}
+
+impl Rule {
+	/// Marks some of the premises as only checked under Semantics::Cerise.
+	const fn cerise_only(self, cerise_only: &'static [&'static str]) -> Rule {
+		Rule { cerise_only, ..self }
+	}
+}
+
+/// Fetching and decoding the instruction PC points to, before executing it.
+pub const EXEC_SINGLE: Rule = extended(
+	"ExecSingle",
+	&[PC_CAPABILITY, PC_IN_BOUNDS, PC_EXECUTABLE, INSTRUCTION],
+	"[decode(φ.mem(a))](φ)",
+);
+
+/// Moving PC to the next instruction, after most instructions.
+pub const UPD_PC: Rule = rule("updPC(φ)", &[PC_CAPABILITY], "(Running, φ[reg.pc ↦ (p, b, e, a + 1)])");
+
+#[rustfmt::skip]
+const INSTRUCTIONS: &[Rule] = &[
+	rule("fail", &[], "(Failed, φ)"),
+	rule("halt", &[], "(Halted, φ)"),
+	rule("mov r ρ", &["w = getWord(φ, ρ)"], "updPC(φ[reg.r ↦ w])"),
+	extended("load r1 r2 ρ", &[R2_CAPABILITY, Z, Z_INTEGER, READABLE, OFFSET_IN_BOUNDS, LOADED_WORD], "updPC(φ[reg.r1 ↦ w])"),
+	extended("store r ρ1 ρ2", &[R_CAPABILITY, Z_OFFSET, Z_INTEGER, WRITABLE, OFFSET_IN_BOUNDS, STORED_WORD], "updPC(φ[mem.(a + z) ↦ w])"),
+	rule("jmp r", &[NEW_PC], "(Running, φ[reg.pc ↦ newPc])"),
+	rule("jnz r1 r2", &[NEW_PC_R1], "if φ.reg(r2) ≠ 0 then (Running, φ[reg.pc ↦ newPc]) else updPC(φ)"),
+	rule("jal r1 r2", &[PC_CAPABILITY, "w = (e, b, e, a + 1)", NEW_PC_R1], "(Running, φ[reg.r2 ↦ w][reg.pc ↦ newPc])"),
+	modified("restrict r ρ", &[R_CAPABILITY, NEW_PERM, PERM_FLOWS, "w = (p', b, e, a)"], "updPC(φ[reg.r ↦ w])"),
+	modified("subseg r ρ1 ρ2", &[R_CAPABILITY, Z1, Z2, Z1_INTEGER, Z2_INTEGER, WITHIN, NOT_ENTER, "w = (p, z1, z2, a)"], "updPC(φ[reg.r ↦ w])"),
+	modified("lea r ρ", &[R_CAPABILITY, Z, NOT_ENTER, Z_INTEGER, LEA_ADDRESS, "w = (p, b, e, a + z)"], "updPC(φ[reg.r ↦ w])").cerise_only(&[LEA_ADDRESS]),
+	rule("add r ρ1 ρ2", &[Z1, Z2, Z1_INTEGER, Z2_INTEGER, "z = z1 + z2"], "updPC(φ[reg.r ↦ z])"),
+	rule("sub r ρ1 ρ2", &[Z1, Z2, Z1_INTEGER, Z2_INTEGER, "z = z1 - z2"], "updPC(φ[reg.r ↦ z])"),
+	rule("lt r ρ1 ρ2", &[Z1, Z2, Z1_INTEGER, Z2_INTEGER, "if z1 < z2 then z = 1 else z = 0"], "updPC(φ[reg.r ↦ z])"),
+	modified("getp r1 r2", &[R2_CAPABILITY], "updPC(φ[reg.r1 ↦ encodePerm(p)])"),
+	rule("getb r1 r2", &[R2_CAPABILITY], "updPC(φ[reg.r1 ↦ b])"),
+	rule("gete r1 r2", &[R2_CAPABILITY], "updPC(φ[reg.r1 ↦ e])"),
+	rule("geta r1 r2", &[R2_CAPABILITY], "updPC(φ[reg.r1 ↦ a])"),
+	rule("isptr r1 r2", &["if φ.reg(r2) = (_, _, _, _) then z = 1 else z = 0"], "updPC(φ[reg.r1 ↦ z])"),
+	rule("getl r1 r2", &[R2_CAPABILITY], "updPC(φ[reg.r1 ↦ e - b])"),
+	rule("geto r1 r2", &[R2_CAPABILITY], "updPC(φ[reg.r1 ↦ a - b])"),
+	rule("seta r ρ", &[R_CAPABILITY, Z, Z_INTEGER, Z_POSITIVE, NOT_ENTER, "w = (p, b, e, z)"], "updPC(φ[reg.r ↦ w])"),
+	rule("setbounds r ρ", &[R_CAPABILITY, Z, Z_INTEGER, Z_POSITIVE, BASE_BEFORE_ADDRESS, LENGTH_IN_BOUNDS, NOT_ENTER, "w = (p, a, a + z, a)"], "updPC(φ[reg.r ↦ w])"),
+	rule("subset r1 r2 r3", &[R2_CAPABILITY_2, R3_CAPABILITY_3, "if p3 ≼ p2 ∧ b2 ≤ b3 ∧ e3 ≤ e2 then z = 1 else z = 0"], "updPC(φ[reg.r1 ↦ z])"),
+	rule("ceq r1 r2 r3", &[R2_CAPABILITY_2, R3_CAPABILITY_3, "if (p2, b2, e2, a2) = (p3, b3, e3, a3) then z = 1 else z = 0"], "updPC(φ[reg.r1 ↦ z])"),
+	modified("flows r ρ1 ρ2", &[PERM_1, PERM_2, "if p1 ≼ p2 then z = 1 else z = 0"], "updPC(φ[reg.r ↦ z])"),
+];
+
+/// The rule of the instruction.
+pub fn instruction_rule(instruction: &Instruction) -> &'static Rule {
+	let mnemonic = instruction.mnemonic();
+
+	INSTRUCTIONS
+		.iter()
+		.find(|rule| rule.name.split(' ').next() == Some(mnemonic))
+		.expect("Every instruction has a rule.")
+}
+
+/// The rule with the given name, among the rules of the instructions and of the helpers.
+pub fn find_rule(name: &str) -> Option<&'static Rule> {
+	[&EXEC_SINGLE, &UPD_PC]
+		.into_iter()
+		.chain(INSTRUCTIONS)
+		.find(|rule| rule.name == name)
+}
+
+/// A premise of a rule that didn't hold, making the machine fail, along with the values it was checked with.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Violation {
+	/// What was being executed: the instruction, or the name of the helper rule
+	pub subject: String,
+
+	/// The name of the rule the premise belongs to
+	pub rule: String,
+
+	pub premise: String,
+
+	/// The terms of the premise along with their values, e.g. ("a + z", "0x40")
+	pub values: Vec<(String, String)>,
+}
+
+impl Violation {
+	pub fn new(subject: String, rule: &Rule, premise: &str, values: &[(&str, String)]) -> Self {
+		debug_assert!(
+			rule.premises.contains(&premise),
+			"\"{}\" is not a premise of {}",
+			premise,
+			rule.name
+		);
+
+		Self {
+			subject,
+			rule: rule.name.to_owned(),
+			premise: premise.to_owned(),
+			values: values
+				.iter()
+				.map(|(term, value)| (term.to_string(), value.clone()))
+				.collect(),
+		}
+	}
+
+	/// Renders the rule the premise belongs to under the semantics, with the premise marked.
+	pub fn describe_rule(&self, semantics: Semantics) -> Option<String> {
+		find_rule(&self.rule).map(|rule| rule.describe(Some(&self.premise), semantics))
+	}
+}
@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+	encoding,
+	permission::Permission,
+	program::{AddrInt, Address, Word, WordInt},
+};
+
+/*
+--------------------------------------------------------------------------------
+||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||
+--------------------------------------------------------------------------------
+*/
+
+/// The set of rules the machine follows for the instructions where this emulator deviates from Cerise.
+///
+/// Both profiles go through the exact same instruction pipeline in exec.rs;
+/// the rules only differ in the few helpers defined here, which the instructions call into.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Semantics {
+	/// The rules of this emulator:
+	///   - permissions are first-class words, restrict and getp use Word::Permission
+	///   - subseg requires 𝑏 ≤ 𝑧1 < AddrMax and 0 ≤ 𝑧2 ≤ 𝑒
+	///   - lea may move the address anywhere
+	#[default]
+	Modified,
+
+	/// The rules of the published Cerise development:
+	///   - permissions are encoded as integers (see encoding::encode_permission()), restrict and getp use integers
+	///     and the permission literals of programs are loaded as their encoding
+	///   - subseg requires 𝑧1, 𝑧2 ∈ [0, AddrMax] and 𝑏 ≤ 𝑧1 and 𝑧2 ≤ 𝑒
+	///   - lea requires 𝑎 + 𝑧 ∈ [0, AddrMax]
+	Cerise,
+}
+
+impl Semantics {
+	/// encodePerm(𝑝) from Cerise.
+	/// Converts a permission to the word that getp returns.
+	pub fn encode_perm(self, perm: Permission) -> Word {
+		match self {
+			Semantics::Modified => Word::Permission(perm),
+			Semantics::Cerise => Word::Integer(encoding::encode_permission(perm) as WordInt),
+		}
+	}
+
+	/// The word a literal of a program stands for.
+	/// Cerise has no permission words, so permission literals stand for their encoding there.
+	pub fn literal(self, word: &Word) -> Word {
+		match word {
+			Word::Permission(perm) => self.encode_perm(*perm),
+			word => word.clone(),
+		}
+	}
+
+	/// decodePerm(𝑧) from Cerise.
+	/// Converts a word to the permission it stands for, or None if the word cannot be used as a permission.
+	///
+	/// In Cerise, decodePerm is total and any integer that doesn't encode a permission decodes to O.
+	pub fn decode_perm(self, word: &Word) -> Option<Permission> {
+		match (self, word) {
+			(Semantics::Modified, Word::Permission(p)) => Some(*p),
+			(Semantics::Cerise, Word::Integer(z)) => Some(
+				u64::try_from(*z)
+					.ok()
+					.and_then(encoding::decode_permission)
+					.unwrap_or(Permission::O),
+			),
+			_ => None,
+		}
+	}
+
+	/// isWithin(𝑧1, 𝑧2, 𝑏, 𝑒) from Cerise.
+	/// Returns the new bounds of a subseg if they are valid.
+	pub fn subseg_bounds(
+		self,
+		base: Address,
+		end: Address,
+		z1: WordInt,
+		z2: WordInt,
+		addr_max: AddrInt,
+	) -> Option<(Address, Address)> {
+		let (base, end, addr_max) = (base.0 as WordInt, end.0 as WordInt, addr_max as WordInt);
+
+		let valid = match self {
+			Semantics::Modified => base <= z1 && z1 < addr_max && 0 <= z2 && z2 <= end,
+			Semantics::Cerise => {
+				(0..=addr_max).contains(&z1) && (0..=addr_max).contains(&z2) && base <= z1 && z2 <= end
+			}
+		};
+
+		valid.then_some((Address(z1 as AddrInt), Address(z2 as AddrInt)))
+	}
+
+	/// (𝑎 + 𝑧) from Cerise.
+	/// Returns the new address of a lea if it is valid.
+	pub fn lea_address(self, address: Address, z: WordInt, addr_max: AddrInt) -> Option<Address> {
+		let a = address.0 as WordInt + z;
+
+		match self {
+			// This mess is needed because z can be negative
+			Semantics::Modified => Some(Address(a as AddrInt)),
+			Semantics::Cerise => (0..=addr_max as WordInt).contains(&a).then_some(Address(a as AddrInt)),
+		}
+	}
+}
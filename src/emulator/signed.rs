@@ -62,6 +62,11 @@ where
 		Some(self.inner)
 	}
 
+	/// Returns the inner value without checking the signature.
+	pub fn inner(&self) -> &T {
+		&self.inner
+	}
+
 	pub fn re_signed(self, signing_key: &SigningKey) -> Self {
 		Self::new_signed(self.inner, signing_key)
 	}
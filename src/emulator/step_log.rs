@@ -0,0 +1,108 @@
+/*
+--------------------------------------------------------------------------------
+||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||
+--------------------------------------------------------------------------------
+*/
+
+/// How many of the latest steps the trace and the history keep, unless the machine config says otherwise.
+pub const DEFAULT_RECORDED_STEPS: usize = 10_000;
+
+/// The steps recorded by the trace or the history, one entry per step, keeping only the latest ones once a limit
+/// is set, so that recording a machine that never stops doesn't grow without bound.
+///
+/// Entries keep the index of their step: the n-th entry pushed is step n, whether the older ones are kept or not.
+#[derive(Clone, Debug)]
+pub(crate) struct StepLog<T> {
+	entries: Vec<T>,
+
+	/// The index in entries of the first entry kept
+	start: usize,
+
+	/// The number of entries removed from the start of entries
+	removed: usize,
+
+	limit: Option<usize>,
+}
+
+impl<T> Default for StepLog<T> {
+	fn default() -> Self {
+		Self::new(Some(DEFAULT_RECORDED_STEPS))
+	}
+}
+
+impl<T> StepLog<T> {
+	pub(crate) fn new(limit: Option<usize>) -> Self {
+		Self {
+			entries: Vec::new(),
+			start: 0,
+			removed: 0,
+			limit,
+		}
+	}
+
+	pub(crate) fn limit(&self) -> Option<usize> {
+		self.limit
+	}
+
+	/// Sets how many of the latest entries are kept, which applies from the next push on.
+	pub(crate) fn set_limit(&mut self, limit: Option<usize>) {
+		self.limit = limit;
+	}
+
+	/// The entries kept, in order.
+	pub(crate) fn entries(&self) -> &[T] {
+		&self.entries[self.start..]
+	}
+
+	/// The number of entries pushed so far, whether they are kept or not, which is also the index of the next one.
+	pub(crate) fn total(&self) -> usize {
+		self.removed + self.entries.len()
+	}
+
+	/// The index of the first entry kept.
+	pub(crate) fn first(&self) -> usize {
+		self.removed + self.start
+	}
+
+	pub(crate) fn last_mut(&mut self) -> Option<&mut T> {
+		self.entries[self.start..].last_mut()
+	}
+
+	pub(crate) fn push(&mut self, entry: T) {
+		self.entries.push(entry);
+
+		let Some(limit) = self.limit else {
+			return;
+		};
+
+		self.start = self.start.max(self.entries.len().saturating_sub(limit));
+
+		// Entries that aren't kept are removed in batches, so that a push takes constant amortized time
+		if self.start >= limit.max(1) {
+			self.entries.drain(..self.start);
+			self.removed += self.start;
+			self.start = 0;
+		}
+	}
+
+	/// Removes the last entry, if it is kept.
+	pub(crate) fn pop(&mut self) -> Option<T> {
+		if self.entries.len() > self.start {
+			self.entries.pop()
+		} else {
+			None
+		}
+	}
+
+	/// Removes the entries from the given index on.
+	pub(crate) fn truncate(&mut self, total: usize) {
+		if total < self.removed {
+			self.entries.clear();
+			self.start = 0;
+			self.removed = total;
+		} else {
+			self.entries.truncate(total - self.removed);
+			self.start = self.start.min(self.entries.len());
+		}
+	}
+}
@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+	machine::Machine,
+	observer::ExecObserver,
+	permission::Permission,
+	program::{AddrInt, Address, Capability, LabelIdentifier, Register, Row, Word},
+	rules::Violation,
+};
+
+/*
+--------------------------------------------------------------------------------
+||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||
+--------------------------------------------------------------------------------
+*/
+
+/// A condition that stops the machine early, with the State::Stopped state, as soon as it is met.
+/// Conditions are checked after every step, so the step that met the condition is always fully executed,
+/// including the steps the machine fails in for good.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum StopCondition {
+	/// PC points to the given address (the instruction there isn't executed yet).
+	Address(AddrInt),
+
+	/// PC points to the given label, in any of the loaded programs (the instruction there isn't executed yet).
+	Label(LabelIdentifier),
+
+	/// An instruction reads memory between the given start (inclusive) and end (exclusive) addresses.
+	Read(AddrInt, AddrInt),
+
+	/// An instruction writes memory between the given start (inclusive) and end (exclusive) addresses.
+	Write(AddrInt, AddrInt),
+
+	/// A register other than PC is assigned a capability with the given permission, whose signature is valid.
+	Capability(Permission),
+
+	/// The machine fails because a premise of the given rule doesn't hold, the rule being given by its name
+	/// or its instruction (e.g. "load r1 r2 ρ", "load", or "ExecSingle"), or because the given premise doesn't hold
+	/// (e.g. "b ≤ a + z < e"), see rules::Rule.
+	Fault(String),
+}
+
+/// The built-in observer checking the stop conditions of a machine.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct StopWatcher {
+	pub(crate) conditions: Vec<StopCondition>,
+
+	/// The first condition met during the current step, if any
+	triggered: Option<String>,
+}
+
+impl StopWatcher {
+	/// Returns why the machine should stop after the step that just happened, if any condition was met.
+	/// The conditions on PC are only checked if PC moved, i.e. unless the machine stopped for good during the step.
+	pub(crate) fn check(&mut self, machine: &Machine, pc_moved: bool) -> Option<String> {
+		if let Some(reason) = self.triggered.take() {
+			return Some(reason);
+		}
+
+		if !pc_moved {
+			return None;
+		}
+
+		// The signature doesn't matter here, if PC is invalid the next step fails anyway
+		let Word::Capability(pc) = machine.read_register(Register::PC) else {
+			return None;
+		};
+		let address = pc.inner().address;
+
+		self.conditions.iter().find_map(|condition| match condition {
+			StopCondition::Address(a) if address == *a => Some(format!("PC reached {}", address)),
+
+			StopCondition::Label(label) if machine.symbols().contains(&(label.clone(), address)) => {
+				Some(format!("PC reached {} ({})", label, address))
+			}
+
+			_ => None,
+		})
+	}
+
+	fn trigger(&mut self, reason: impl FnOnce() -> String) {
+		if self.triggered.is_none() {
+			self.triggered = Some(reason());
+		}
+	}
+}
+
+impl ExecObserver for StopWatcher {
+	fn before_step(&mut self, _: &Machine) {
+		// Whatever was met before, e.g. during a step undone since, doesn't stop this step
+		self.triggered = None;
+	}
+
+	fn register_write(&mut self, machine: &Machine, register: Register, _: &Word, new: &Word) {
+		let Word::Capability(signed_capability) = new else {
+			return;
+		};
+
+		// A forged capability can't be used, so it isn't one
+		let Some(Capability { perm, .. }) = machine.verify_capability(signed_capability.clone()) else {
+			return;
+		};
+
+		if register != Register::PC && self.conditions.contains(&StopCondition::Capability(perm)) {
+			self.trigger(|| format!("{} was assigned {}", register, new));
+		}
+	}
+
+	fn memory_read(&mut self, _: &Machine, address: Address, _: &Row) {
+		let met = self.conditions.iter().any(|condition| match condition {
+			StopCondition::Read(start, end) => *start <= address.0 && address.0 < *end,
+			_ => false,
+		});
+
+		if met {
+			self.trigger(|| format!("memory read at {}", address));
+		}
+	}
+
+	fn memory_write(&mut self, _: &Machine, address: Address, _: &Row, new: &Row) {
+		let met = self.conditions.iter().any(|condition| match condition {
+			StopCondition::Write(start, end) => *start <= address.0 && address.0 < *end,
+			_ => false,
+		});
+
+		if met {
+			self.trigger(|| format!("memory write at {} ({})", address, new));
+		}
+	}
+
+	fn violation(&mut self, _: &Machine, violation: &Violation) {
+		let met = self.conditions.iter().any(|condition| match condition {
+			StopCondition::Fault(rule_or_premise) => {
+				let name = rule_or_premise.as_str();
+				violation.rule == name || violation.rule.split(' ').next() == Some(name) || violation.premise == name
+			}
+			_ => false,
+		});
+
+		if met {
+			self.trigger(|| format!("fault ({})", violation));
+		}
+	}
+}
@@ -0,0 +1,175 @@
+use serde::{Deserialize, Serialize};
+
+use super::program::{Address, Capability, LabelIdentifier, Program, Row, Word};
+
+/*
+--------------------------------------------------------------------------------
+||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||
+--------------------------------------------------------------------------------
+*/
+
+/// The labels of all the programs loaded in memory, at their absolute address,
+/// used to render addresses symbolically (e.g. malloc+3 instead of 0x212).
+///
+/// Only the labels are serialized. A deserialized table (e.g. from a dumped machine) doesn't know where the programs
+/// start and end anymore, so it treats all the labels as being part of a single program.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(from = "Vec<(LabelIdentifier, Address)>", into = "Vec<(LabelIdentifier, Address)>")]
+pub struct SymbolTable {
+	symbols: Vec<(LabelIdentifier, Address)>,
+	programs: Vec<ProgramSymbols>,
+}
+
+/// The labels of a single loaded program.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ProgramSymbols {
+	start: Address,
+
+	/// Inclusive, to cover labels at the very end of the program
+	end: Address,
+
+	/// Sorted by address
+	labels: Vec<(Address, LabelIdentifier)>,
+}
+
+impl SymbolTable {
+	/// Adds the labels of a program loaded at the given address.
+	pub fn add_program(&mut self, program: &Program, address: Address) {
+		let mut labels = program
+			.labels
+			.iter()
+			.map(|(label, offset)| (address + *offset, label.clone()))
+			.collect::<Vec<_>>();
+		labels.sort();
+
+		self.symbols
+			.extend(labels.iter().map(|(address, label)| (label.clone(), *address)));
+
+		if !labels.is_empty() {
+			self.programs.push(ProgramSymbols {
+				start: address,
+				end: address + program.rows.len(),
+				labels,
+			});
+		}
+	}
+
+	/// All the labels, at their absolute address.
+	/// Different programs may define the same label, in which case it appears multiple times.
+	pub fn symbols(&self) -> &[(LabelIdentifier, Address)] {
+		&self.symbols
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.symbols.is_empty()
+	}
+
+	/// All the labels defined exactly at the given address.
+	pub fn labels_at(&self, address: Address) -> impl Iterator<Item = &LabelIdentifier> {
+		self.symbols
+			.iter()
+			.filter(move |(_, a)| *a == address)
+			.map(|(label, _)| label)
+	}
+
+	/// The first address of every program with labels, along with the address right after its last row.
+	pub fn programs(&self) -> impl Iterator<Item = (Address, Address)> + '_ {
+		self.programs.iter().map(|program| (program.start, program.end))
+	}
+
+	/// The first and last (inclusive) address of the program with labels containing the address, if any.
+	pub fn program_bounds(&self, address: Address) -> Option<(Address, Address)> {
+		self.programs
+			.iter()
+			.find(|program| program.start <= address && address <= program.end)
+			.map(|program| (program.start, program.end))
+	}
+
+	/// The closest label defined at or before the address, in the program containing the address.
+	/// Returns None if no program with labels contains the address.
+	pub fn closest_label(&self, address: Address) -> Option<(Address, &LabelIdentifier)> {
+		let ProgramSymbols { labels, .. } = self
+			.programs
+			.iter()
+			.find(|program| program.start <= address && address <= program.end)?;
+
+		// Labels are sorted, so the first label at the closest address wins
+		let closest = labels.iter().rev().find(|(a, _)| *a <= address)?.0;
+		labels
+			.iter()
+			.find(|(a, _)| *a == closest)
+			.map(|(label_address, label)| (*label_address, label))
+	}
+
+	/// Renders the address relative to the closest label before it, in the program containing the address,
+	/// e.g. malloc+3. Returns None if no program with labels contains the address.
+	pub fn symbolize(&self, address: Address) -> Option<String> {
+		let (label_address, label) = self.closest_label(address)?;
+
+		if label_address == address {
+			Some(label.clone())
+		} else {
+			Some(format!("{}+{}", label, address.0 - label_address.0))
+		}
+	}
+
+	/// Renders the address symbolically if possible, in hexadecimal otherwise.
+	pub fn format_address(&self, address: Address) -> String {
+		self.symbolize(address).unwrap_or_else(|| address.to_string())
+	}
+
+	/// Renders the capability with symbolic addresses, e.g. (E, memcpy, memcpy_end, memcpy).
+	pub fn format_capability(&self, capability: &Capability) -> String {
+		format!(
+			"({}, {}, {}, {})",
+			capability.perm,
+			self.format_address(capability.base),
+			self.format_address(capability.end),
+			self.format_address(capability.address)
+		)
+	}
+
+	/// Renders the word like its Display implementation, but with symbolic addresses in capabilities.
+	pub fn format_word(&self, word: &Word) -> String {
+		match word {
+			Word::Capability(capability) => format!("${}", self.format_capability(capability.inner())),
+			_ => word.to_string(),
+		}
+	}
+
+	/// Renders the row like the Display implementation of its word or instruction,
+	/// but with symbolic addresses in capabilities.
+	pub fn format_row(&self, row: &Row) -> String {
+		match row {
+			Row::Word(word) => self.format_word(word),
+			Row::Instruction(instruction) => instruction.to_string(),
+		}
+	}
+}
+
+impl From<Vec<(LabelIdentifier, Address)>> for SymbolTable {
+	fn from(symbols: Vec<(LabelIdentifier, Address)>) -> Self {
+		let mut labels = symbols
+			.iter()
+			.map(|(label, address)| (*address, label.clone()))
+			.collect::<Vec<_>>();
+		labels.sort();
+
+		let programs = match (labels.first(), labels.last()) {
+			(Some((start, _)), Some((end, _))) => vec![ProgramSymbols {
+				start: *start,
+				end: *end,
+				labels: labels.clone(),
+			}],
+			_ => Vec::new(),
+		};
+
+		Self { symbols, programs }
+	}
+}
+
+impl From<SymbolTable> for Vec<(LabelIdentifier, Address)> {
+	fn from(table: SymbolTable) -> Self {
+		table.symbols
+	}
+}
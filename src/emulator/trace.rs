@@ -0,0 +1,214 @@
+use std::io::{self, Write};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+	instruction::Instruction,
+	machine::{Interrupt, Machine, State, StepOutcome},
+	observer::ExecObserver,
+	program::{Address, Register, Row, SourceLocation, Word},
+	rules::Violation,
+	step_log::StepLog,
+};
+
+/*
+--------------------------------------------------------------------------------
+||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||
+--------------------------------------------------------------------------------
+*/
+
+/// Everything that happened during a single step of the machine.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TraceStep {
+	/// The index of the step, starting at 0
+	pub step: usize,
+
+	/// The state of the machine before the step
+	pub state: State,
+
+	/// The value of PC before the step
+	pub pc: Word,
+
+	/// The address of the instruction executed during the step, if it could be fetched
+	pub address: Option<Address>,
+
+	/// The (decoded) instruction executed during the step, if it could be fetched
+	pub instruction: Option<Instruction>,
+
+	/// Where the instruction comes from in the source, if it was compiled from source
+	pub source: Option<SourceLocation>,
+
+	/// The effects of the step on registers and memory, in order
+	pub effects: Vec<Effect>,
+
+	/// Why the machine failed during the step, if it did
+	pub fault: Option<String>,
+
+	/// The premise of a rule that didn't hold if the machine failed during the step, see rules::Violation
+	#[serde(default)]
+	pub violation: Option<Violation>,
+
+	/// The state the instruction resulted in, if it was executed
+	pub result: Option<State>,
+
+	/// The interrupt the machine recovered with at the end of the step, if any
+	pub interrupt: Option<Interrupt>,
+
+	/// The state of the machine after the step
+	pub new_state: State,
+
+	/// Why the machine was stopped early at the end of the step, if it was
+	pub stop_reason: Option<String>,
+}
+
+/// A single effect of a step on the machine.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum Effect {
+	/// A register (including PC) was assigned a new value.
+	Register { register: Register, value: Word },
+
+	/// An instruction read data from memory.
+	Read { address: Address },
+
+	/// An instruction wrote to memory.
+	Write { address: Address, row: Row },
+
+	/// A jump instruction was executed, and the jump was taken or not.
+	Jump { target: Word, taken: bool },
+}
+
+/// The built-in observer keeping a structured trace of everything the machine did, one TraceStep per step.
+/// See backtrace::Backtrace for a human-readable rendering of it.
+///
+/// Only the latest steps are kept, see Machine::set_recorded_steps().
+#[derive(Clone, Debug, Default)]
+pub struct Trace {
+	steps: StepLog<TraceStep>,
+}
+
+impl Trace {
+	/// The steps kept, in order.
+	pub fn steps(&self) -> &[TraceStep] {
+		self.steps.entries()
+	}
+
+	/// The number of steps recorded so far, including the ones that aren't kept anymore.
+	pub fn recorded_steps(&self) -> usize {
+		self.steps.total()
+	}
+
+	pub(crate) fn limit(&self) -> Option<usize> {
+		self.steps.limit()
+	}
+
+	pub(crate) fn set_limit(&mut self, limit: Option<usize>) {
+		self.steps.set_limit(limit);
+	}
+
+	/// Forgets the given step and the steps after it.
+	pub(crate) fn truncate(&mut self, step: usize) {
+		self.steps.truncate(step);
+	}
+
+	/// Writes the trace to the output as JSON lines, one JSON object per step.
+	pub fn write_json_lines(&self, mut output: impl Write) -> io::Result<()> {
+		for step in self.steps() {
+			serde_json::to_writer(&mut output, step)?;
+			writeln!(output)?;
+		}
+
+		output.flush()
+	}
+}
+
+impl ExecObserver for Trace {
+	fn before_step(&mut self, machine: &Machine) {
+		self.steps.push(TraceStep {
+			step: self.steps.total(),
+			state: machine.exec_state,
+			pc: machine.read_register(Register::PC),
+			address: None,
+			instruction: None,
+			source: None,
+			effects: Vec::new(),
+			fault: None,
+			violation: None,
+			result: None,
+			interrupt: None,
+			new_state: machine.exec_state,
+			stop_reason: None,
+		});
+	}
+
+	fn before_instruction(&mut self, machine: &Machine, address: Address, instruction: &Instruction) {
+		if let Some(step) = self.steps.last_mut() {
+			step.address = Some(address);
+			step.instruction = Some(instruction.clone());
+			step.source = machine.source_location(address).cloned();
+		}
+	}
+
+	fn after_instruction(&mut self, _: &Machine, _: Address, _: &Instruction, state: State) {
+		if let Some(step) = self.steps.last_mut() {
+			step.result = Some(state);
+		}
+	}
+
+	fn register_write(&mut self, _: &Machine, register: Register, _: &Word, new: &Word) {
+		if let Some(step) = self.steps.last_mut() {
+			step.effects.push(Effect::Register {
+				register,
+				value: new.clone(),
+			});
+		}
+	}
+
+	fn memory_read(&mut self, _: &Machine, address: Address, _: &Row) {
+		if let Some(step) = self.steps.last_mut() {
+			step.effects.push(Effect::Read { address });
+		}
+	}
+
+	fn memory_write(&mut self, _: &Machine, address: Address, _: &Row, new: &Row) {
+		if let Some(step) = self.steps.last_mut() {
+			step.effects.push(Effect::Write {
+				address,
+				row: new.clone(),
+			});
+		}
+	}
+
+	fn jump(&mut self, _: &Machine, target: &Word, taken: bool) {
+		if let Some(step) = self.steps.last_mut() {
+			step.effects.push(Effect::Jump {
+				target: target.clone(),
+				taken,
+			});
+		}
+	}
+
+	fn violation(&mut self, _: &Machine, violation: &Violation) {
+		if let Some(step) = self.steps.last_mut() {
+			step.violation.get_or_insert_with(|| violation.clone());
+		}
+	}
+
+	fn fault(&mut self, _: &Machine, message: &str) {
+		if let Some(step) = self.steps.last_mut() {
+			step.fault.get_or_insert_with(|| message.to_owned());
+		}
+	}
+
+	fn interrupt(&mut self, _: &Machine, interrupt: Interrupt, _: &Word) {
+		if let Some(step) = self.steps.last_mut() {
+			step.interrupt = Some(interrupt);
+		}
+	}
+
+	fn after_step(&mut self, machine: &Machine, _: StepOutcome) {
+		if let Some(step) = self.steps.last_mut() {
+			step.new_state = machine.exec_state;
+			step.stop_reason = machine.stop_reason().map(str::to_owned);
+		}
+	}
+}
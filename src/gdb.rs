@@ -0,0 +1,591 @@
+use std::{
+	fmt::Write as _,
+	io::{self, BufRead, Write},
+};
+
+use crate::{
+	debugger::Debugger,
+	emulator::{
+		encoding::{decode_permission, encode, encode_permission},
+		machine::{Machine, State},
+		program::{Address, Capability, RegInt, Register, Row, Word, WordInt},
+	},
+};
+
+/*
+--------------------------------------------------------------------------------
+||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||
+--------------------------------------------------------------------------------
+*/
+
+/// How many bytes a memory row takes in the address space seen by GDB.
+/// GDB addresses bytes while the machine addresses 64-bit rows, so every address given to or received from GDB
+/// (memory, PC, and the bounds and addresses of capabilities) is scaled by this factor.
+pub const ROW_SIZE: u64 = 8;
+
+/// The number of general purpose registers, R0 to R255.
+const GENERAL_REGISTERS: usize = RegInt::MAX as usize + 1;
+
+/// The GDB register numbers: the values of R0 to R255, then PC, then the capability fields of R0 to R255 and PC.
+const PC_NUMBER: usize = GENERAL_REGISTERS;
+const CAPABILITY_NUMBERS: usize = PC_NUMBER + 1;
+const REGISTER_COUNT: usize = 2 * CAPABILITY_NUMBERS;
+
+/// The sizes in bytes of a register value and of the capability fields of a register.
+const VALUE_SIZE: usize = 8;
+const CAPABILITY_SIZE: usize = 4 * 8;
+
+/// The stop replies: SIGTRAP for steps and breakpoints, SIGSEGV for a failed machine, exit code 0 for a halted one.
+const STOP_TRAP: &str = "S05";
+const STOP_FAILED: &str = "S0b";
+const STOP_HALTED: &str = "W00";
+
+/// A GDB remote serial protocol stub, letting gdb or lldb attach to a machine.
+///
+/// The registers are R0 to R255 and PC, whose values are integers, or the address of a capability.
+/// Every register is paired with a `<name>_cap` register describing the capability it holds, if any,
+/// as a structure (tag, perm, base, end) where tag is 1 for capabilities, and perm is the permission encoding.
+/// These are described to GDB by the target description sent through qXfer.
+///
+/// Memory rows are read as their 64-bit integer encoding (instructions encoded, capabilities by address),
+/// and can only be written as whole rows, which become integers.
+/// The commands of the debugger are available through `monitor`, e.g. `monitor cap r1` or `monitor provenance pc`.
+///
+/// Continuing runs until a breakpoint is hit or the machine stops; it can't be interrupted.
+pub struct GdbStub {
+	debugger: Debugger,
+	no_ack: bool,
+}
+
+impl GdbStub {
+	/// Creates a stub for the given machine and boots it, like the debugger does.
+	pub fn new(machine: Machine) -> Self {
+		Self {
+			debugger: Debugger::new(machine),
+			no_ack: false,
+		}
+	}
+
+	pub fn machine(&self) -> &Machine {
+		self.debugger.machine()
+	}
+
+	/// Serves a single GDB connection, until GDB detaches or kills the machine, or until the input ends.
+	pub fn serve(&mut self, mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+		while let Some(packet) = read_packet(&mut input, &mut output, self.no_ack)? {
+			let Some(reply) = self.handle(&packet) else {
+				// Detaching is acknowledged before closing, killing isn't
+				if packet.starts_with('D') {
+					write_packet(&mut output, "OK")?;
+				}
+				break;
+			};
+
+			write_packet(&mut output, &reply)?;
+
+			if reply == STOP_HALTED {
+				break;
+			}
+		}
+
+		output.flush()
+	}
+
+	/// Handles a single packet (without its framing) and returns the reply,
+	/// or None if the connection should be closed.
+	pub fn handle(&mut self, packet: &str) -> Option<String> {
+		let (kind, args) = match packet.is_char_boundary(1) {
+			true => packet.split_at(1),
+			false => ("", packet),
+		};
+
+		let reply = match (kind, args) {
+			("?", _) => Ok(self.stop_reply()),
+			("g", _) => Ok(self.read_registers()),
+			("G", data) => self.write_registers(data),
+			("p", number) => self.read_register(number),
+			("P", assignment) => self.write_register(assignment),
+			("m", args) => self.read_memory(args),
+			("M", args) => self.write_memory(args),
+			("Z", args) => self.set_breakpoint(args, true),
+			("z", args) => self.set_breakpoint(args, false),
+			("s", _) => Ok(self.step()),
+			("c", _) => Ok(self.resume()),
+			("H", _) | ("T", _) => Ok("OK".to_owned()),
+			("D", _) | ("k", _) => return None,
+			_ => Ok(self.handle_query(packet)),
+		};
+
+		Some(reply.unwrap_or_else(|error| error.to_owned()))
+	}
+
+	/// Handles the packets with a name longer than one letter.
+	fn handle_query(&mut self, packet: &str) -> String {
+		let (name, args) = packet.split_once([':', ',', ';']).unwrap_or((packet, ""));
+
+		match name {
+			"qSupported" => "PacketSize=4000;qXfer:features:read+;QStartNoAckMode+;swbreak+;hwbreak+;\
+				ReverseStep+;ReverseContinue+"
+				.to_owned(),
+			"QStartNoAckMode" => {
+				self.no_ack = true;
+				"OK".to_owned()
+			}
+			"qXfer" => match args.strip_prefix("features:read:target.xml:") {
+				Some(range) => transfer(&target_description(), range),
+				None => String::new(),
+			},
+			"qAttached" => "1".to_owned(),
+			"qC" => "QC1".to_owned(),
+			"qfThreadInfo" => "m1".to_owned(),
+			"qsThreadInfo" => "l".to_owned(),
+			"qSymbol" => "OK".to_owned(),
+			"qRcmd" => self.monitor(args),
+			"vCont?" => "vCont;c;C;s;S".to_owned(),
+			"vCont" => match args.chars().next() {
+				Some('s' | 'S') => self.step(),
+				Some('c' | 'C') => self.resume(),
+				_ => String::new(),
+			},
+			"bs" => self.step_back(),
+			"bc" => self.resume_back(),
+
+			// Unsupported packets get an empty reply
+			_ => String::new(),
+		}
+	}
+
+	/// The reply describing why the machine stopped.
+	fn stop_reply(&self) -> String {
+		match self.machine().exec_state {
+			State::Halted => STOP_HALTED,
+			State::Failed => STOP_FAILED,
+			_ => STOP_TRAP,
+		}
+		.to_owned()
+	}
+
+	fn step(&mut self) -> String {
+		self.debugger.machine_mut().step();
+		self.stop_reply()
+	}
+
+	fn resume(&mut self) -> String {
+		if self.machine().is_running() {
+			self.debugger.run_to_breakpoint(|_| true);
+		}
+
+		self.stop_reply()
+	}
+
+	fn step_back(&mut self) -> String {
+		self.debugger.machine_mut().step_back();
+		STOP_TRAP.to_owned()
+	}
+
+	/// Steps backwards until a breakpoint is reached, or until the start of the history.
+	fn resume_back(&mut self) -> String {
+		self.debugger.run_back_to_breakpoint();
+		STOP_TRAP.to_owned()
+	}
+
+	/// Runs a debugger command, for GDB's monitor command.
+	fn monitor(&mut self, command: &str) -> String {
+		let command = String::from_utf8_lossy(&from_hex(command).unwrap_or_default()).into_owned();
+
+		match self.debugger.execute(&command) {
+			Some(out) if out.is_empty() => "OK".to_owned(),
+			Some(out) => to_hex(format!("{}\n", out).as_bytes()),
+			None => "OK".to_owned(),
+		}
+	}
+
+	fn read_registers(&self) -> String {
+		(0..REGISTER_COUNT)
+			.map(|number| to_hex(&self.register_bytes(number)))
+			.collect()
+	}
+
+	fn write_registers(&mut self, data: &str) -> Result<String, &'static str> {
+		let bytes = from_hex(data).ok_or("E01")?;
+		let mut offset = 0;
+
+		for number in 0..REGISTER_COUNT {
+			let size = register_size(number);
+			let value = bytes.get(offset..offset + size).ok_or("E01")?;
+			offset += size;
+
+			// Only write the registers GDB changed, so that the capabilities keep their provenance
+			if value != self.register_bytes(number) {
+				self.set_register_bytes(number, value)?;
+			}
+		}
+
+		Ok("OK".to_owned())
+	}
+
+	fn read_register(&self, number: &str) -> Result<String, &'static str> {
+		let number = parse_hex(number).filter(|n| *n < REGISTER_COUNT).ok_or("E01")?;
+		Ok(to_hex(&self.register_bytes(number)))
+	}
+
+	fn write_register(&mut self, assignment: &str) -> Result<String, &'static str> {
+		let (number, value) = assignment.split_once('=').ok_or("E01")?;
+		let number = parse_hex(number).filter(|n| *n < REGISTER_COUNT).ok_or("E01")?;
+		let value = from_hex(value)
+			.filter(|v| v.len() == register_size(number))
+			.ok_or("E01")?;
+
+		self.set_register_bytes(number, &value)?;
+		Ok("OK".to_owned())
+	}
+
+	/// The register of a register number, and whether the number is that of its capability fields.
+	fn register(number: usize) -> (Register, bool) {
+		let (index, capability) = match number.checked_sub(CAPABILITY_NUMBERS) {
+			Some(index) => (index, true),
+			None => (number, false),
+		};
+
+		match index {
+			PC_NUMBER => (Register::PC, capability),
+			index => (Register::R(index as RegInt), capability),
+		}
+	}
+
+	fn register_bytes(&self, number: usize) -> Vec<u8> {
+		let (register, capability) = Self::register(number);
+		let word = self.machine().read_register(register);
+
+		if capability {
+			capability_fields(&word)
+				.iter()
+				.flat_map(|field| field.to_le_bytes())
+				.collect()
+		} else {
+			word_value(&word).to_le_bytes().to_vec()
+		}
+	}
+
+	/// Writes a register from its bytes. Writing the value of a capability moves its address,
+	/// writing the capability fields of a register turns it into a capability (tag 1) or an integer (tag 0).
+	fn set_register_bytes(&mut self, number: usize, bytes: &[u8]) -> Result<(), &'static str> {
+		let (register, capability) = Self::register(number);
+		let current = self.machine().read_register(register);
+		let fields = bytes
+			.chunks(8)
+			.map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+			.collect::<Vec<_>>();
+
+		let word = match (capability, &current) {
+			(false, Word::Capability(signed)) => Word::Capability(self.machine().sign_capability(Capability {
+				address: to_address(fields[0])?,
+				..*signed.inner()
+			})),
+			(false, _) => Word::Integer(fields[0] as WordInt),
+
+			(true, _) if fields[0] == 0 => Word::Integer(word_value(&current) as WordInt),
+			(true, _) => Word::Capability(self.machine().sign_capability(Capability {
+				perm: decode_permission(fields[1]).ok_or("E01")?,
+				base: to_address(fields[2])?,
+				end: to_address(fields[3])?,
+				address: match &current {
+					Word::Capability(signed) => signed.inner().address,
+					_ => to_address(word_value(&current))?,
+				},
+			})),
+		};
+
+		self.debugger.machine_mut().write_register(register, word);
+		Ok(())
+	}
+
+	/// Reads memory as the little-endian encoding of its rows, stopping at the end of memory.
+	fn read_memory(&self, args: &str) -> Result<String, &'static str> {
+		let (start, length) = parse_address_length(args)?;
+		let end = start.checked_add(length).ok_or("E01")?;
+		let memory = &self.machine().memory;
+
+		let bytes = (start..end)
+			.map_while(|byte| {
+				let address = Address((byte / ROW_SIZE) as usize);
+				(address.0 < memory.mem_size())
+					.then(|| row_value(&memory[address]).to_le_bytes()[(byte % ROW_SIZE) as usize])
+			})
+			.collect::<Vec<_>>();
+
+		if bytes.is_empty() && length > 0 {
+			return Err("E01");
+		}
+
+		Ok(to_hex(&bytes))
+	}
+
+	/// Writes whole rows of memory as integers.
+	fn write_memory(&mut self, args: &str) -> Result<String, &'static str> {
+		let (range, data) = args.split_once(':').ok_or("E01")?;
+		let (start, length) = parse_address_length(range)?;
+		let bytes = from_hex(data)
+			.filter(|bytes| bytes.len() as u64 == length)
+			.ok_or("E01")?;
+
+		if !start.is_multiple_of(ROW_SIZE) || !length.is_multiple_of(ROW_SIZE) {
+			return Err("E02");
+		}
+
+		let machine = self.debugger.machine_mut();
+
+		for (i, row) in bytes.chunks(ROW_SIZE as usize).enumerate() {
+			let address = Address((start / ROW_SIZE) as usize + i);
+
+			if address.0 >= machine.memory.mem_size() {
+				return Err("E01");
+			}
+
+			let value = WordInt::from_le_bytes(row.try_into().unwrap());
+			machine.memory[address] = Row::Word(Word::Integer(value));
+		}
+
+		Ok("OK".to_owned())
+	}
+
+	/// Sets or removes a software or hardware breakpoint, both being the debugger's breakpoints.
+	fn set_breakpoint(&mut self, args: &str, set: bool) -> Result<String, &'static str> {
+		let mut args = args.split(',');
+
+		if !matches!(args.next(), Some("0" | "1")) {
+			// Watchpoints aren't supported
+			return Ok(String::new());
+		}
+
+		let address = args.next().and_then(parse_hex).ok_or("E01")? as u64;
+		let address = to_address(address)?;
+
+		if set {
+			self.debugger.breakpoints_mut().insert(address);
+		} else {
+			self.debugger.breakpoints_mut().remove(&address);
+		}
+
+		Ok("OK".to_owned())
+	}
+}
+
+/// The target description of the machine, see GdbStub.
+pub fn target_description() -> String {
+	let mut registers = String::new();
+
+	let names = (0..GENERAL_REGISTERS)
+		.map(|n| format!("r{}", n))
+		.chain(["pc".to_owned()])
+		.collect::<Vec<_>>();
+
+	for name in &names {
+		let kind = if name == "pc" { "code_ptr" } else { "int64" };
+		let _ = writeln!(
+			registers,
+			"    <reg name=\"{}\" bitsize=\"64\" type=\"{}\"/>",
+			name, kind
+		);
+	}
+
+	for name in &names {
+		let _ = writeln!(
+			registers,
+			"    <reg name=\"{}_cap\" bitsize=\"256\" type=\"capability\" group=\"capability\"/>",
+			name
+		);
+	}
+
+	format!(
+		r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.cerise.core">
+    <struct id="capability">
+      <field name="tag" type="uint64"/>
+      <field name="perm" type="uint64"/>
+      <field name="base" type="uint64"/>
+      <field name="end" type="uint64"/>
+    </struct>
+{}  </feature>
+</target>
+"#,
+		registers
+	)
+}
+
+fn register_size(number: usize) -> usize {
+	if number < CAPABILITY_NUMBERS {
+		VALUE_SIZE
+	} else {
+		CAPABILITY_SIZE
+	}
+}
+
+/// The value of a word as seen by GDB: integers as is, capabilities by their scaled address.
+fn word_value(word: &Word) -> u64 {
+	match word {
+		Word::Integer(z) => *z as u64,
+		Word::Char(c) => *c as u64,
+		Word::Permission(perm) => encode_permission(*perm),
+		Word::Capability(capability) => from_address(capability.inner().address),
+	}
+}
+
+/// The capability fields (tag, perm, base, end) of a word, all zero if it isn't a capability.
+fn capability_fields(word: &Word) -> [u64; 4] {
+	match word {
+		Word::Capability(capability) => {
+			let capability = capability.inner();
+			[
+				1,
+				encode_permission(capability.perm),
+				from_address(capability.base),
+				from_address(capability.end),
+			]
+		}
+		_ => [0; 4],
+	}
+}
+
+/// The value of a row as seen by GDB, with instructions encoded.
+fn row_value(row: &Row) -> u64 {
+	match row {
+		Row::Word(word) => word_value(word),
+		Row::Instruction(instruction) => encode(instruction).map_or(0, |z| z as u64),
+	}
+}
+
+fn from_address(address: Address) -> u64 {
+	address.0 as u64 * ROW_SIZE
+}
+
+fn to_address(address: u64) -> Result<Address, &'static str> {
+	if address.is_multiple_of(ROW_SIZE) {
+		Ok(Address((address / ROW_SIZE) as usize))
+	} else {
+		Err("E02")
+	}
+}
+
+/// Answers a qXfer read of the given "offset,length" of the data.
+fn transfer(data: &str, range: &str) -> String {
+	let Ok((offset, length)) = parse_address_length(range) else {
+		return "E01".to_owned();
+	};
+
+	let start = (offset as usize).min(data.len());
+	let Some(end) = start.checked_add(length as usize) else {
+		return "E01".to_owned();
+	};
+	let end = end.min(data.len());
+	let prefix = if end == data.len() { 'l' } else { 'm' };
+
+	format!("{}{}", prefix, &data[start..end])
+}
+
+fn parse_address_length(args: &str) -> Result<(u64, u64), &'static str> {
+	let (address, length) = args.split_once(',').ok_or("E01")?;
+
+	match (u64::from_str_radix(address, 16), u64::from_str_radix(length, 16)) {
+		(Ok(address), Ok(length)) => Ok((address, length)),
+		_ => Err("E01"),
+	}
+}
+
+fn parse_hex(s: &str) -> Option<usize> {
+	usize::from_str_radix(s, 16).ok()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+	bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(s: &str) -> Option<Vec<u8>> {
+	if !s.len().is_multiple_of(2) || !s.is_ascii() {
+		return None;
+	}
+
+	(0..s.len())
+		.step_by(2)
+		.map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+		.collect()
+}
+
+fn checksum(data: &[u8]) -> u8 {
+	data.iter().fold(0, |sum, byte| sum.wrapping_add(*byte))
+}
+
+/// Reads the next packet, acknowledging it unless acknowledgments are disabled.
+/// Returns None when the input ends.
+fn read_packet(input: impl BufRead, output: &mut impl Write, no_ack: bool) -> io::Result<Option<String>> {
+	let mut bytes = input.bytes();
+
+	loop {
+		// Skip acknowledgments and interrupts until the start of a packet
+		loop {
+			match bytes.next().transpose()? {
+				Some(b'$') => break,
+				Some(_) => continue,
+				None => return Ok(None),
+			}
+		}
+
+		let mut raw = Vec::new();
+
+		loop {
+			match bytes.next().transpose()? {
+				Some(b'#') => break,
+				Some(byte) => raw.push(byte),
+				None => return Ok(None),
+			}
+		}
+
+		let sum = match [bytes.next().transpose()?, bytes.next().transpose()?] {
+			[Some(high), Some(low)] => u8::from_str_radix(&String::from_utf8_lossy(&[high, low]), 16).ok(),
+			_ => return Ok(None),
+		};
+
+		if no_ack || sum == Some(checksum(&raw)) {
+			if !no_ack {
+				output.write_all(b"+")?;
+			}
+
+			// Unescape the data, where '}' escapes the next byte xored with 0x20
+			let mut data = Vec::new();
+			let mut raw = raw.into_iter();
+			while let Some(byte) = raw.next() {
+				match byte {
+					b'}' => data.extend(raw.next().map(|byte| byte ^ 0x20)),
+					byte => data.push(byte),
+				}
+			}
+
+			return Ok(Some(String::from_utf8_lossy(&data).into_owned()));
+		}
+
+		// Ask for the packet again
+		output.write_all(b"-")?;
+		output.flush()?;
+	}
+}
+
+/// Writes a packet with its framing and checksum, escaping the characters that need to be.
+/// Acknowledgments from GDB are skipped when reading the next packet, packets aren't sent again.
+fn write_packet(output: &mut impl Write, data: &str) -> io::Result<()> {
+	let mut raw = Vec::new();
+
+	for byte in data.bytes() {
+		if matches!(byte, b'$' | b'#' | b'}' | b'*') {
+			raw.extend([b'}', byte ^ 0x20]);
+		} else {
+			raw.push(byte);
+		}
+	}
+
+	output.write_all(b"$")?;
+	output.write_all(&raw)?;
+	write!(output, "#{:02x}", checksum(&raw))?;
+	output.flush()
+}
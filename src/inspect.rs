@@ -0,0 +1,327 @@
+use std::{collections::BTreeMap, ops::Range, str::FromStr};
+
+use crate::emulator::{
+	encoding::{encode, encode_permission},
+	memory::Memory,
+	program::{AddrInt, Address, Capability, Row, Word},
+};
+
+/*
+--------------------------------------------------------------------------------
+||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||
+--------------------------------------------------------------------------------
+*/
+
+/// How many rows are shown per line in the hex view.
+const HEX_ROWS_PER_LINE: usize = 4;
+
+/// The ways a memory range can be shown.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum View {
+	/// One row per line, like the memory of a machine is displayed
+	#[default]
+	Rows,
+
+	/// The rows as 64-bit words in hexadecimal, with instructions encoded
+	Hex,
+
+	/// The runs of characters, decoded as strings
+	Chars,
+
+	/// The capabilities, grouped by the region they point into
+	Capabilities,
+}
+
+impl FromStr for View {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"rows" => Ok(View::Rows),
+			"hex" => Ok(View::Hex),
+			"chars" => Ok(View::Chars),
+			"caps" => Ok(View::Capabilities),
+			_ => Err(format!("invalid view \"{}\", expected rows, hex, chars or caps", s)),
+		}
+	}
+}
+
+/// Which rows are shown in the rows view.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum RowFilter {
+	/// Every row
+	All,
+
+	/// The rows that aren't zero, or that have a label
+	#[default]
+	NonZero,
+
+	Instructions,
+	Integers,
+	Chars,
+	Capabilities,
+	Permissions,
+}
+
+impl RowFilter {
+	pub fn matches(&self, row: &Row) -> bool {
+		match self {
+			RowFilter::All => true,
+			RowFilter::NonZero => row != &Row::default(),
+			RowFilter::Instructions => matches!(row, Row::Instruction(_)),
+			RowFilter::Integers => matches!(row, Row::Word(Word::Integer(_))),
+			RowFilter::Chars => matches!(row, Row::Word(Word::Char(_))),
+			RowFilter::Capabilities => matches!(row, Row::Word(Word::Capability(_))),
+			RowFilter::Permissions => matches!(row, Row::Word(Word::Permission(_))),
+		}
+	}
+}
+
+impl FromStr for RowFilter {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"all" => Ok(RowFilter::All),
+			"nonzero" => Ok(RowFilter::NonZero),
+			"instructions" => Ok(RowFilter::Instructions),
+			"integers" => Ok(RowFilter::Integers),
+			"chars" => Ok(RowFilter::Chars),
+			"caps" => Ok(RowFilter::Capabilities),
+			"perms" => Ok(RowFilter::Permissions),
+			_ => Err(format!(
+				"invalid filter \"{}\", expected all, nonzero, instructions, integers, chars, caps or perms",
+				s
+			)),
+		}
+	}
+}
+
+/// Resolves a range of memory given as "start..end", where start and end are addresses or labels,
+/// or as a single label (from the label up to the next label), or as a single address (that row only).
+pub fn resolve_range(memory: &Memory, range: &str) -> Result<Range<Address>, String> {
+	let range = match range.split_once("..") {
+		Some((start, end)) => resolve_address(memory, start)?..resolve_address(memory, end)?,
+
+		None => match parse_address(range) {
+			Some(address) => address..address + 1,
+			None => {
+				let start = resolve_address(memory, range)?;
+				let end = memory
+					.symbols()
+					.symbols()
+					.iter()
+					.map(|(_, address)| *address)
+					.filter(|address| *address > start)
+					.min()
+					.unwrap_or(Address(memory.mem_size()));
+
+				start..end
+			}
+		},
+	};
+
+	if range.start >= range.end || range.end.0 > memory.mem_size() {
+		return Err(format!("invalid memory range {}..{}", range.start, range.end));
+	}
+
+	Ok(range)
+}
+
+fn parse_address(s: &str) -> Option<Address> {
+	match s.strip_prefix("0x") {
+		Some(hex) => AddrInt::from_str_radix(hex, 16).ok(),
+		None => s.parse().ok(),
+	}
+	.map(Address)
+}
+
+fn resolve_address(memory: &Memory, s: &str) -> Result<Address, String> {
+	if let Some(address) = parse_address(s) {
+		return Ok(address);
+	}
+
+	let addresses = memory
+		.symbols()
+		.symbols()
+		.iter()
+		.filter(|(label, _)| label == s)
+		.map(|(_, address)| *address)
+		.collect::<Vec<_>>();
+
+	match addresses.as_slice() {
+		[address] => Ok(*address),
+		[] => Err(format!("\"{}\" is neither an address nor a known label", s)),
+		_ => Err(format!("label \"{}\" is ambiguous", s)),
+	}
+}
+
+/// Renders the memory range in the given view.
+pub fn view(memory: &Memory, range: Range<Address>, view: View, filter: RowFilter) -> String {
+	match view {
+		View::Rows => rows(memory, range, filter),
+		View::Hex => hex(memory, range),
+		View::Chars => chars(memory, range),
+		View::Capabilities => capabilities(memory, range),
+	}
+}
+
+/// One row per line, with the rows not matching the filter collapsed.
+/// Rows with labels are always shown with the NonZero filter, like when displaying the whole memory.
+pub fn rows(memory: &Memory, range: Range<Address>, filter: RowFilter) -> String {
+	memory.format_rows(range, |address, row| {
+		filter.matches(row) || (filter == RowFilter::NonZero && memory.symbols().labels_at(address).next().is_some())
+	})
+}
+
+/// The rows as 64-bit words in hexadecimal, a few rows per line followed by the printable characters among them.
+/// Instructions and permissions are shown encoded, and capabilities (which have no integer encoding) by permission.
+/// Runs of lines containing only zeros are collapsed into a single "*" line.
+pub fn hex(memory: &Memory, range: Range<Address>) -> String {
+	let address_padding = format!("{}", range.end - 1).chars().count();
+
+	let mut out = Vec::new();
+	let mut is_zero = false;
+
+	for start in (range.start.0..range.end.0).step_by(HEX_ROWS_PER_LINE) {
+		let rows = (start..(start + HEX_ROWS_PER_LINE).min(range.end.0))
+			.map(|address| &memory[Address(address)])
+			.collect::<Vec<_>>();
+
+		if rows.iter().all(|row| *row == &Row::default()) {
+			if !is_zero {
+				out.push("*".to_owned());
+				is_zero = true;
+			}
+			continue;
+		}
+		is_zero = false;
+
+		let words = rows.iter().map(|row| hex_row(row)).collect::<Vec<_>>().join(" ");
+
+		let chars = rows
+			.iter()
+			.map(|row| match row {
+				Row::Word(Word::Char(c)) if !c.is_control() => *c,
+				_ => '.',
+			})
+			.collect::<String>();
+
+		out.push(format!(
+			"{:<address_padding$} | {:<width$} | {}",
+			Address(start),
+			words,
+			chars,
+			width = HEX_ROWS_PER_LINE * 17 - 1
+		));
+	}
+
+	out.join("\n")
+}
+
+fn hex_row(row: &Row) -> String {
+	match row {
+		Row::Word(Word::Integer(z)) => format!("{:016x}", *z as u64),
+		Row::Word(Word::Char(c)) => format!("{:016x}", *c as u32),
+		Row::Word(Word::Permission(perm)) => format!("{:016x}", encode_permission(*perm)),
+		Row::Word(Word::Capability(capability)) => format!("{:^16}", format!("cap:{}", capability.inner().perm)),
+		Row::Instruction(instruction) => match encode(instruction) {
+			Ok(z) => format!("{:016x}", z as u64),
+			Err(_) => format!("{:^16}", "?"),
+		},
+	}
+}
+
+/// Every run of consecutive characters, decoded as a string.
+pub fn chars(memory: &Memory, range: Range<Address>) -> String {
+	let mut runs = Vec::<(Address, String)>::new();
+	let mut previous_is_char = false;
+
+	for address in (range.start.0..range.end.0).map(Address) {
+		match &memory[address] {
+			Row::Word(Word::Char(c)) => {
+				match runs.last_mut() {
+					Some((_, string)) if previous_is_char => string.push(*c),
+					_ => runs.push((address, c.to_string())),
+				}
+				previous_is_char = true;
+			}
+			_ => previous_is_char = false,
+		}
+	}
+
+	if runs.is_empty() {
+		return "(No characters)".to_owned();
+	}
+
+	runs.iter()
+		.map(|(start, string)| {
+			let length = string.chars().count();
+			format!(
+				"{}..{} {}({} chars) | {:?}",
+				start,
+				*start + length,
+				label(memory, *start),
+				length,
+				string
+			)
+		})
+		.collect::<Vec<_>>()
+		.join("\n")
+}
+
+/// Every capability, decoded and grouped by the region (base and end) it points into.
+pub fn capabilities(memory: &Memory, range: Range<Address>) -> String {
+	let symbols = memory.symbols();
+	let mut regions = BTreeMap::<(Address, Address), Vec<(Address, Capability)>>::new();
+
+	for address in (range.start.0..range.end.0).map(Address) {
+		if let Row::Word(Word::Capability(capability)) = &memory[address] {
+			let capability = *capability.inner();
+			regions
+				.entry((capability.base, capability.end))
+				.or_default()
+				.push((address, capability));
+		}
+	}
+
+	if regions.is_empty() {
+		return "(No capabilities)".to_owned();
+	}
+
+	let mut out = Vec::new();
+
+	for ((base, end), capabilities) in regions {
+		let length = end.0 as i128 - base.0 as i128;
+		out.push(format!(
+			"[{}, {}) {}..{} ({} rows, {} capabilit{})",
+			base,
+			end,
+			symbols.format_address(base),
+			symbols.format_address(end),
+			length,
+			capabilities.len(),
+			if capabilities.len() == 1 { "y" } else { "ies" }
+		));
+
+		for (address, capability) in capabilities {
+			out.push(format!(
+				"  {} {}| {} at {}",
+				address,
+				label(memory, address),
+				capability.perm,
+				symbols.format_address(capability.address)
+			));
+		}
+	}
+
+	out.join("\n")
+}
+
+/// The address relative to the closest label before it, followed by a space, or nothing if there is no such label.
+fn label(memory: &Memory, address: Address) -> String {
+	match memory.symbols().closest_label(address) {
+		Some(_) => format!("({}) ", memory.symbols().format_address(address)),
+		None => String::new(),
+	}
+}
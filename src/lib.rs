@@ -1,15 +1,37 @@
-use std::io::{self, Read, Write};
+use std::{
+	io::{self, BufReader, Read, Write},
+	net::TcpListener,
+	sync::{Arc, Mutex},
+};
 
+use dap::DapServer;
+use debugger::Debugger;
 use emulator::{
+	coverage::Coverage,
+	diff::MachineDiff,
+	dump::MachineDump,
+	leak::LeakChecker,
+	machine::Machine,
 	machine_config::{MachineConfig, ProgramConfig},
-	program::Program,
+	program::{Address, Program, Register, Word},
+	stop_condition::StopCondition,
 };
+use gdb::GdbStub;
+use inspect::{RowFilter, View};
 use ron::ser::PrettyConfig;
 use serde::Serialize;
+use tui::Tui;
+use visualizer::Visualizer;
 
 pub mod compiler;
+pub mod dap;
+pub mod debugger;
 pub mod emulator;
+pub mod gdb;
+pub mod inspect;
+pub mod tui;
 pub mod util;
+pub mod visualizer;
 
 /*
 --------------------------------------------------------------------------------
@@ -28,13 +50,18 @@ where
 	.expect("Couldn't serialize output to RON. This should not happen.")
 }
 
-pub fn compile(input: impl Read, mut output: impl Write) {
+pub fn compile(input: impl Read, mut output: impl Write, encode: bool) {
 	// To pass file to stdin (not required, just in case):
 	// windows cmd: cargo run -- < test.asm
 	// nushell: open test.asm | cargo run
 
 	// Read input file and compile program
-	let program = ProgramConfig::from_reader_as_source(input).compiled();
+	let mut program = ProgramConfig::from_reader_as_source(input).compiled();
+
+	if encode {
+		// Replace instructions by their integer encoding
+		program = program.encoded().expect("Couldn't encode program");
+	}
 
 	println!("Finished compiling. Program is {} rows long.", program.rows.len());
 
@@ -47,37 +74,164 @@ pub fn compile(input: impl Read, mut output: impl Write) {
 		.expect("Could not write to output writer.");
 }
 
-pub fn emulate(input: impl Read, mut output: impl Write, compile: bool, dump: bool, backtrace: bool) {
-	// Create machine config to emulate depending on input
-	let machine_config = if compile {
+/// Reads the machine config to emulate from the input, which is either an assembly file to compile,
+/// or a RON file containing a compiled program, a program config, or a machine config.
+pub(crate) fn read_machine_config(input: impl Read, compile: bool) -> MachineConfig {
+	if compile {
 		// If it needs compiling, compile the program from source
 		MachineConfig::from_program_config(ProgramConfig::from_reader_as_source(input))
 	} else {
-		// Otherwise parse it from the given file as RON
-		// We try to either parse the RON as a compiled program, a program config, or as a machine config
-		let source = io::read_to_string(input).expect("Couldn't read input file.");
-
-		if let Ok(program) = ron::de::from_str::<Program>(&source) {
-			// Source is a compiled program, initialize machine
-			MachineConfig::from_program(program)
-		} else if let Ok(program_config) = ron::de::from_str::<ProgramConfig>(&source) {
-			// Source is a program config, compile it and initialize machine
-			MachineConfig::from_program_config(program_config)
-		} else if let Ok(machine_config) = ron::de::from_str::<MachineConfig>(&source) {
-			// Source is a machine config, initialize machine from it
-			machine_config
-		} else {
-			panic!("Could not de-serialize input as a compiled program, a program config, or as a machine config; is it a correct RON file?")
-		}
-	};
+		try_read_machine_config(input).unwrap_or_else(|e| panic!("{}", e))
+	}
+}
+
+/// Reads the machine config from a RON file containing a compiled program, a program config, or a machine config.
+/// Unlike read_machine_config(), returns an error instead of panicking.
+pub(crate) fn try_read_machine_config(input: impl Read) -> Result<MachineConfig, String> {
+	// We try to either parse the RON as a compiled program, a program config, or as a machine config
+	let source = io::read_to_string(input).map_err(|e| format!("Couldn't read input file: {}", e))?;
+
+	if let Ok(program) = ron::de::from_str::<Program>(&source) {
+		// Source is a compiled program, initialize machine
+		Ok(MachineConfig::from_program(program))
+	} else if let Ok(program_config) = ron::de::from_str::<ProgramConfig>(&source) {
+		// Source is a program config, compile it and initialize machine
+		program_config.try_compiled().map(MachineConfig::from_program)
+	} else if let Ok(machine_config) = ron::de::from_str::<MachineConfig>(&source) {
+		// Source is a machine config, initialize machine from it
+		Ok(machine_config)
+	} else {
+		Err("Could not de-serialize input as a compiled program, a program config, or as a machine config; is it a correct RON file?".to_owned())
+	}
+}
+
+/// The reports to output after emulating a machine, besides the machine itself.
+#[derive(Default)]
+pub struct Reports {
+	/// Print the backtrace
+	pub backtrace: bool,
+
+	/// Write the structured trace as JSON lines to the given output
+	pub trace: Option<Box<dyn Write>>,
+
+	/// Print the instruction-level profile
+	pub profile: bool,
+
+	/// Write the call stacks of the profile in the collapsed stack format to the given output
+	pub collapsed_stacks: Option<Box<dyn Write>>,
+
+	/// Write the code coverage in the lcov format to the given output
+	pub coverage: Option<Box<dyn Write>>,
+
+	/// Print the source listing annotated with the code coverage
+	pub annotate: bool,
+
+	/// Print the derivation chain of the capabilities in the given registers
+	pub provenance: Vec<Register>,
+
+	/// Print the capabilities leaked at domain transitions, according to the domains of the machine config
+	pub leaks: bool,
+}
+
+pub fn emulate(
+	input: impl Read,
+	mut output: impl Write,
+	compile: bool,
+	dump: bool,
+	stop_conditions: Vec<StopCondition>,
+	reports: Reports,
+) {
+	// Create machine config to emulate depending on input
+	let mut machine_config = read_machine_config(input, compile);
+	machine_config.stop_conditions.extend(stop_conditions);
+
+	let leak_checker = reports
+		.leaks
+		.then(|| Arc::new(Mutex::new(LeakChecker::new(machine_config.domains.clone()))));
 
 	// Run the emulator
-	let post_machine = emulator::emulate(machine_config);
+	let mut post_machine = Machine::initialize_from_config(machine_config);
+
+	if let Some(leak_checker) = &leak_checker {
+		post_machine.add_observer(leak_checker.clone());
+	}
+
+	let coverage = (reports.coverage.is_some() || reports.annotate).then(|| {
+		let coverage = Arc::new(Mutex::new(Coverage::default()));
+		post_machine.add_observer(coverage.clone());
+		coverage
+	});
+
+	if !reports.provenance.is_empty() {
+		post_machine.record_provenance();
+	}
+
+	post_machine.exec_machine();
 
-	if backtrace {
+	if reports.backtrace {
 		post_machine.print_backtrace();
 	}
 
+	if let Some(trace) = reports.trace {
+		// Output the structured trace as JSON lines, one object per step
+		post_machine
+			.trace()
+			.write_json_lines(trace)
+			.expect("Could not write trace to output writer.");
+	}
+
+	if reports.profile || reports.collapsed_stacks.is_some() {
+		let profile = post_machine.profile();
+
+		if reports.profile {
+			profile.print();
+		}
+
+		if let Some(collapsed_stacks) = reports.collapsed_stacks {
+			// Output the call stacks for flamegraph tools
+			profile
+				.write_collapsed_stacks(collapsed_stacks)
+				.expect("Could not write collapsed stacks to output writer.");
+		}
+	}
+
+	if let Some(coverage) = coverage {
+		let coverage = coverage.lock().unwrap();
+
+		if reports.annotate {
+			println!(
+				"Machine coverage:\n{}",
+				coverage.annotated_listing(&post_machine.memory)
+			);
+		}
+
+		if let Some(output) = reports.coverage {
+			// Output the coverage for lcov tools
+			coverage
+				.write_lcov(&post_machine.memory, output)
+				.expect("Could not write coverage to output writer.");
+		}
+	}
+
+	for register in &reports.provenance {
+		match post_machine.read_register(*register) {
+			Word::Capability(capability) => {
+				let chain = post_machine
+					.format_provenance(capability.inner())
+					.expect("The provenance should have been recorded.");
+				println!("Provenance of {} = {}\n", register, chain);
+			}
+			word => println!("Provenance of {}: not a capability ({})\n", register, word),
+		}
+	}
+
+	if let Some(leak_checker) = leak_checker {
+		println!(
+			"Capability leaks:\n{}\n",
+			leak_checker.lock().unwrap().report(post_machine.symbol_table())
+		);
+	}
+
 	println!("\n\n{}\n\n", post_machine);
 	post_machine.print_status();
 
@@ -92,3 +246,117 @@ pub fn emulate(input: impl Read, mut output: impl Write, compile: bool, dump: bo
 			.expect("Could not write to output writer.");
 	}
 }
+
+pub fn debug(input: impl Read, compile: bool) {
+	// Create the machine to debug depending on input
+	let machine = Machine::initialize_from_config(read_machine_config(input, compile));
+
+	println!("Starting debugger, type \"help\" for a list of commands.");
+
+	// Drive the machine from commands read on stdin
+	Debugger::new(machine).run(io::stdin().lock(), io::stdout());
+}
+
+/// Shows the machine full-screen on the terminal, driven by debugger commands read on stdin.
+pub fn tui(input: impl Read, compile: bool) {
+	let machine = Machine::initialize_from_config(read_machine_config(input, compile));
+
+	Tui::new(machine)
+		.run(io::stdin().lock(), io::stdout().lock())
+		.expect("Could not write to the terminal.");
+}
+
+/// Serves editors over the Debug Adapter Protocol on stdin and stdout, the program to debug being given by the client.
+pub fn dap() {
+	DapServer::new()
+		.serve(io::stdin().lock(), io::stdout().lock())
+		.expect("The connection to the client failed.");
+}
+
+/// Serves the machine to gdb or lldb over the GDB remote serial protocol, on the given local TCP port,
+/// or on stdin and stdout if there is none (e.g. for gdb's `target remote | cerisemu gdb ...`).
+pub fn gdb(input: impl Read, compile: bool, port: Option<u16>) {
+	let mut stub = GdbStub::new(Machine::initialize_from_config(read_machine_config(input, compile)));
+
+	match port {
+		Some(port) => {
+			let listener = TcpListener::bind(("127.0.0.1", port)).expect("Couldn't listen on the given port.");
+			eprintln!("Waiting for gdb to connect on 127.0.0.1:{}...", port);
+
+			let (stream, address) = listener.accept().expect("Couldn't accept the connection.");
+			eprintln!("Connected to {}.", address);
+
+			let reader = stream.try_clone().expect("Couldn't clone the connection.");
+			stub.serve(BufReader::new(reader), stream)
+		}
+		None => stub.serve(io::stdin().lock(), io::stdout().lock()),
+	}
+	.expect("The connection to gdb failed.");
+
+	eprintln!("gdb disconnected. Machine status: {}", stub.machine().exec_state);
+}
+
+/// Serves the web visualizer of the machine on the given local TCP port, one request at a time, until killed.
+pub fn serve(input: impl Read, compile: bool, port: u16) {
+	let mut visualizer = Visualizer::new(Machine::initialize_from_config(read_machine_config(input, compile)));
+
+	let listener = TcpListener::bind(("127.0.0.1", port)).expect("Couldn't listen on the given port.");
+	eprintln!("Serving the visualizer on http://127.0.0.1:{}/", port);
+
+	for stream in listener.incoming() {
+		let Ok(stream) = stream else {
+			continue;
+		};
+
+		// An idle connection shouldn't hold up the others for long
+		if let Err(e) = stream.set_read_timeout(Some(visualizer::READ_TIMEOUT)) {
+			eprintln!("Couldn't set a timeout on the connection: {}", e);
+			continue;
+		}
+
+		let reader = stream.try_clone().expect("Couldn't clone the connection.");
+
+		// A client going away mid-request shouldn't stop the server
+		if let Err(e) = visualizer.serve(BufReader::new(reader), stream) {
+			eprintln!("Couldn't serve a request: {}", e);
+		}
+	}
+}
+
+/// Reads a machine to look at after the fact, which is either a machine dumped by `emulate --dump`,
+/// or an input like for emulate() that is emulated first.
+fn read_machine_dump(input: impl Read, compile: bool) -> MachineDump {
+	let source = io::read_to_string(input).expect("Couldn't read input file.");
+
+	match ron::de::from_str::<MachineDump>(&source) {
+		Ok(dump) if !compile => dump,
+		_ => {
+			let mut machine = Machine::initialize_from_config(read_machine_config(source.as_bytes(), compile));
+			machine.exec_machine();
+			MachineDump::from(&machine)
+		}
+	}
+}
+
+pub fn inspect(input: impl Read, compile: bool, range: Option<&str>, view: View, filter: RowFilter) {
+	let memory = read_machine_dump(input, compile).memory;
+
+	let range = match range {
+		Some(range) => inspect::resolve_range(&memory, range).unwrap_or_else(|error| panic!("{}", error)),
+		None => Address(0)..Address(memory.mem_size()),
+	};
+
+	println!("{}", inspect::view(&memory, range, view, filter));
+}
+
+/// Prints the differences between the two machines, and returns whether there are any.
+pub fn diff(a: impl Read, b: impl Read, compile: bool) -> bool {
+	let a = read_machine_dump(a, compile);
+	let b = read_machine_dump(b, compile);
+
+	let diff = MachineDiff::new(&a, &b);
+
+	println!("{}", diff.format(a.memory.symbols()));
+
+	!diff.is_empty()
+}
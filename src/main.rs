@@ -2,9 +2,19 @@ use std::{
 	fs::OpenOptions,
 	io::{self, Read, Write},
 	path::PathBuf,
+	process,
 };
 
-use clap::{command, Arg, ArgAction, Command};
+use cerisemu::{
+	emulator::{
+		permission::Permission,
+		program::{AddrInt, Register},
+		stop_condition::StopCondition,
+	},
+	inspect::{RowFilter, View},
+	Reports,
+};
+use clap::{command, Arg, ArgAction, ArgMatches, Command};
 
 /*
 --------------------------------------------------------------------------------
@@ -18,7 +28,8 @@ fn main() {
 		Some(("compile", compile_matches)) => {
 			let input = make_reader(compile_matches.get_one::<PathBuf>("in").cloned());
 			let output = make_writer(compile_matches.get_one::<PathBuf>("out").cloned());
-			cerisemu::compile(input, output)
+			let encode = compile_matches.get_flag("encode");
+			cerisemu::compile(input, output, encode)
 		}
 
 		Some(("emulate", compile_matches)) => {
@@ -26,8 +37,85 @@ fn main() {
 			let output = make_writer(compile_matches.get_one::<PathBuf>("out").cloned());
 			let compile = compile_matches.get_flag("compile");
 			let dump = compile_matches.get_flag("dump");
-			let backtrace = compile_matches.get_flag("backtrace");
-			cerisemu::emulate(input, output, compile, dump, backtrace)
+			let stop_conditions = stop_conditions(compile_matches);
+			let reports = Reports {
+				backtrace: compile_matches.get_flag("backtrace"),
+				trace: compile_matches
+					.get_one::<PathBuf>("trace")
+					.cloned()
+					.map(|path| make_writer(Some(path))),
+				profile: compile_matches.get_flag("profile"),
+				collapsed_stacks: compile_matches
+					.get_one::<PathBuf>("collapsed-stacks")
+					.cloned()
+					.map(|path| make_writer(Some(path))),
+				coverage: compile_matches
+					.get_one::<PathBuf>("coverage")
+					.cloned()
+					.map(|path| make_writer(Some(path))),
+				annotate: compile_matches.get_flag("annotate"),
+				provenance: compile_matches
+					.get_many::<Register>("provenance")
+					.unwrap_or_default()
+					.copied()
+					.collect(),
+				leaks: compile_matches.get_flag("check-leaks"),
+			};
+			cerisemu::emulate(input, output, compile, dump, stop_conditions, reports)
+		}
+
+		Some(("debug", debug_matches)) => {
+			let input = make_reader(debug_matches.get_one::<PathBuf>("in").cloned());
+			let compile = debug_matches.get_flag("compile");
+			cerisemu::debug(input, compile)
+		}
+
+		Some(("tui", tui_matches)) => {
+			let input = make_reader(tui_matches.get_one::<PathBuf>("in").cloned());
+			let compile = tui_matches.get_flag("compile");
+			cerisemu::tui(input, compile)
+		}
+
+		Some(("dap", _)) => cerisemu::dap(),
+
+		Some(("gdb", gdb_matches)) => {
+			let input = make_reader(gdb_matches.get_one::<PathBuf>("in").cloned());
+			let compile = gdb_matches.get_flag("compile");
+			let port = match gdb_matches.get_flag("stdio") {
+				true => None,
+				false => gdb_matches.get_one::<u16>("port").copied(),
+			};
+			cerisemu::gdb(input, compile, port)
+		}
+
+		Some(("serve", serve_matches)) => {
+			let input = make_reader(serve_matches.get_one::<PathBuf>("in").cloned());
+			let compile = serve_matches.get_flag("compile");
+			let port = *serve_matches.get_one::<u16>("port").unwrap();
+			cerisemu::serve(input, compile, port)
+		}
+
+		Some(("inspect", inspect_matches)) => {
+			let input = make_reader(inspect_matches.get_one::<PathBuf>("in").cloned());
+			let compile = inspect_matches.get_flag("compile");
+			let range = inspect_matches.get_one::<String>("range").map(String::as_str);
+			let view = inspect_matches.get_one::<View>("view").copied().unwrap_or_default();
+			let filter = inspect_matches
+				.get_one::<RowFilter>("filter")
+				.copied()
+				.unwrap_or_default();
+			cerisemu::inspect(input, compile, range, view, filter)
+		}
+
+		Some(("diff", diff_matches)) => {
+			let a = make_reader(diff_matches.get_one::<PathBuf>("a").cloned());
+			let b = make_reader(diff_matches.get_one::<PathBuf>("b").cloned());
+			let compile = diff_matches.get_flag("compile");
+
+			// Like diff, exit with 1 if the machines differ
+			if cerisemu::diff(a, b, compile) {
+				process::exit(1);
+			}
 		}
 
 		_ => unreachable!("A subcommand hasn't been properly programmed! This should not happen."),
@@ -53,6 +141,14 @@ fn cli() -> Command {
 					.help("Set the output file for the compiler to write the compiled code to. Writes to stdout if not specified.")
 					.value_parser(clap::value_parser!(PathBuf))
 					.action(ArgAction::Set)
+			)
+			.arg(
+				Arg::new("encode")
+					.long("encode")
+					.short('e')
+					.help("Indicates that instructions should be output as encoded integer words, for machines running with encoded instructions.")
+					.required(false)
+					.action(ArgAction::SetTrue)
 			),
 	).subcommand(Command::new("emulate")
 			.about("Emulate a capability machine given a single program or a machine config.")
@@ -96,9 +192,333 @@ fn cli() -> Command {
 					.required(false)
 					.action(ArgAction::SetTrue)
 			)
+			.arg(
+				Arg::new("trace")
+					.long("trace")
+					.short('t')
+					.help("Set a file to write the structured trace of the emulation to, as JSON lines (one JSON object per step).")
+					.value_parser(clap::value_parser!(PathBuf))
+					.action(ArgAction::Set)
+			)
+			.arg(
+				Arg::new("profile")
+					.long("profile")
+					.short('p')
+					.help("Indicates that the instruction-level profile (instructions executed per address, label and opcode, and the hottest loops) should be printed after emulation.")
+					.required(false)
+					.action(ArgAction::SetTrue)
+			)
+			.arg(
+				Arg::new("collapsed-stacks")
+					.long("collapsed-stacks")
+					.help("Set a file to write the call stacks of the profile to, in the collapsed stack format understood by flamegraph tools.")
+					.value_parser(clap::value_parser!(PathBuf))
+					.action(ArgAction::Set)
+			)
+			.arg(
+				Arg::new("coverage")
+					.long("coverage")
+					.help("Set a file to write the code coverage (executed lines, and directions taken by every jnz) to, in the lcov format.")
+					.value_parser(clap::value_parser!(PathBuf))
+					.action(ArgAction::Set)
+			)
+			.arg(
+				Arg::new("annotate")
+					.long("annotate")
+					.short('a')
+					.help("Indicates that the source listing of the programs, annotated with how many times every line was executed, should be printed after emulation.")
+					.required(false)
+					.action(ArgAction::SetTrue)
+			)
+			.arg(
+				Arg::new("provenance")
+					.long("provenance")
+					.help("Print how the capability in the given register (e.g. r1 or pc) was derived after emulation, step by step back to the master capability. Can be repeated.")
+					.value_parser(parse_register)
+					.action(ArgAction::Append)
+			)
+			.arg(
+				Arg::new("check-leaks")
+					.long("check-leaks")
+					.help("Indicates that every register still holding a capability when control passes through an E-capability into another code region should be reported, if the region may not receive it (see the domains of the machine config).")
+					.action(ArgAction::SetTrue)
+			)
+			.arg(
+				Arg::new("stop-at")
+					.long("stop-at")
+					.help("Stop the machine when PC reaches the given address or label. Can be repeated.")
+					.value_parser(parse_address_or_label)
+					.action(ArgAction::Append)
+			)
+			.arg(
+				Arg::new("stop-on-read")
+					.long("stop-on-read")
+					.help("Stop the machine when an instruction reads memory in the given range (e.g. 0x100 or 0x100..0x200). Can be repeated.")
+					.value_parser(parse_range)
+					.action(ArgAction::Append)
+			)
+			.arg(
+				Arg::new("stop-on-write")
+					.long("stop-on-write")
+					.help("Stop the machine when an instruction writes memory in the given range (e.g. 0x100 or 0x100..0x200). Can be repeated.")
+					.value_parser(parse_range)
+					.action(ArgAction::Append)
+			)
+			.arg(
+				Arg::new("stop-on-cap")
+					.long("stop-on-cap")
+					.help("Stop the machine when a register is assigned a capability with the given permission (e.g. RWX). Can be repeated.")
+					.value_parser(parse_permission)
+					.action(ArgAction::Append)
+			)
+			.arg(
+				Arg::new("stop-on-fault")
+					.long("stop-on-fault")
+					.help("Stop the machine when it fails because a premise of the given rule doesn't hold, the rule being given by its instruction (e.g. load) or its name, or because the given premise doesn't hold (e.g. \"b ≤ a + z < e\"). Can be repeated.")
+					.action(ArgAction::Append)
+			)
+	).subcommand(Command::new("debug")
+			.about("Interactively debug a capability machine given a single program or a machine config, one instruction at a time.")
+			.arg(
+				Arg::new("in")
+					.long("in")
+					.short('i')
+					.help("Set the input file for the debugger to read from. If the --compile flag is not set, will be interpreted either as a compiled program or a config file. Debugger commands are read from stdin.")
+					.value_parser(clap::value_parser!(PathBuf))
+					.required(true)
+					.action(ArgAction::Set)
+			)
+			.arg(
+				Arg::new("compile")
+					.long("compile")
+					.short('c')
+					.help("Indicates that the input file needs to first be compiled. If set, the input file must be an uncompiled ASM file. If not set, the input file must be a proper RON file.")
+					.required(false)
+					.action(ArgAction::SetTrue)
+			)
+	).subcommand(Command::new("tui")
+			.about("Show a capability machine full-screen on the terminal: registers, code, memory, interrupt table and backtrace, updated as debugger commands step or run it.")
+			.arg(
+				Arg::new("in")
+					.long("in")
+					.short('i')
+					.help("Set the input file to read the machine from. If the --compile flag is not set, will be interpreted either as a compiled program or a config file. Commands are read from stdin.")
+					.value_parser(clap::value_parser!(PathBuf))
+					.required(true)
+					.action(ArgAction::Set)
+			)
+			.arg(
+				Arg::new("compile")
+					.long("compile")
+					.short('c')
+					.help("Indicates that the input file needs to first be compiled. If set, the input file must be an uncompiled ASM file. If not set, the input file must be a proper RON file.")
+					.required(false)
+					.action(ArgAction::SetTrue)
+			)
+	).subcommand(Command::new("dap")
+			.about("Serve editors like VS Code over the Debug Adapter Protocol on stdin and stdout. The program to debug (an ASM file, or a RON file like for the emulate subcommand) is given by the launch request.")
+	).subcommand(Command::new("gdb")
+			.about("Serve a capability machine to gdb or lldb over the GDB remote serial protocol, e.g. for gdb's `target remote localhost:1234`. Debugger commands are available through gdb's `monitor` command.")
+			.arg(
+				Arg::new("in")
+					.long("in")
+					.short('i')
+					.help("Set the input file to read the machine from. If the --compile flag is not set, will be interpreted either as a compiled program or a config file.")
+					.value_parser(clap::value_parser!(PathBuf))
+					.required(true)
+					.action(ArgAction::Set)
+			)
+			.arg(
+				Arg::new("compile")
+					.long("compile")
+					.short('c')
+					.help("Indicates that the input file needs to first be compiled. If set, the input file must be an uncompiled ASM file. If not set, the input file must be a proper RON file.")
+					.required(false)
+					.action(ArgAction::SetTrue)
+			)
+			.arg(
+				Arg::new("port")
+					.long("port")
+					.short('p')
+					.help("Set the local TCP port to wait for gdb on.")
+					.value_parser(clap::value_parser!(u16))
+					.default_value("1234")
+					.action(ArgAction::Set)
+			)
+			.arg(
+				Arg::new("stdio")
+					.long("stdio")
+					.help("Indicates that the protocol should be spoken on stdin and stdout instead of a TCP port, e.g. for gdb's `target remote | cerisemu gdb --stdio -i <file>`.")
+					.required(false)
+					.action(ArgAction::SetTrue)
+			)
+	).subcommand(Command::new("serve")
+			.about("Serve a local web visualizer of a capability machine: the memory map with its programs, the capabilities in registers and memory drawn as arrows to the ranges they cover, and controls to step it forwards and backwards.")
+			.arg(
+				Arg::new("in")
+					.long("in")
+					.short('i')
+					.help("Set the input file to read the machine from. If the --compile flag is not set, will be interpreted either as a compiled program or a config file.")
+					.value_parser(clap::value_parser!(PathBuf))
+					.required(true)
+					.action(ArgAction::Set)
+			)
+			.arg(
+				Arg::new("compile")
+					.long("compile")
+					.short('c')
+					.help("Indicates that the input file needs to first be compiled. If set, the input file must be an uncompiled ASM file. If not set, the input file must be a proper RON file.")
+					.required(false)
+					.action(ArgAction::SetTrue)
+			)
+			.arg(
+				Arg::new("port")
+					.long("port")
+					.short('p')
+					.help("Set the local TCP port to serve the visualizer on.")
+					.value_parser(clap::value_parser!(u16))
+					.default_value("8080")
+					.action(ArgAction::Set)
+			)
+	).subcommand(Command::new("inspect")
+			.about("Show a range of the memory of a dumped machine, or of a machine after emulating it, in a selectable view.")
+			.arg(
+				Arg::new("in")
+					.long("in")
+					.short('i')
+					.help("Set the input file to read from. Either a machine dumped by emulate --dump, or an input to emulate first (like for the emulate subcommand). Reads from stdin if not specified.")
+					.value_parser(clap::value_parser!(PathBuf))
+					.action(ArgAction::Set)
+			)
+			.arg(
+				Arg::new("compile")
+					.long("compile")
+					.short('c')
+					.help("Indicates that the input file needs to first be compiled and emulated. If set, the input file must be an uncompiled ASM file.")
+					.required(false)
+					.action(ArgAction::SetTrue)
+			)
+			.arg(
+				Arg::new("range")
+					.long("range")
+					.short('r')
+					.help("Set the range of memory to show, as start..end where start and end are addresses or labels, as a single label (up to the next label), or as a single address. Shows the whole memory if not specified.")
+					.action(ArgAction::Set)
+			)
+			.arg(
+				Arg::new("view")
+					.long("view")
+					.short('v')
+					.help("Set how the memory is shown: rows (one row per line), hex (words in hexadecimal with the characters alongside), chars (runs of characters decoded as strings), or caps (capabilities grouped by the region they point into).")
+					.value_parser(|s: &str| s.parse::<View>())
+					.default_value("rows")
+					.action(ArgAction::Set)
+			)
+			.arg(
+				Arg::new("filter")
+					.long("filter")
+					.short('f')
+					.help("Set which rows the rows view shows, the others being collapsed: all, nonzero, instructions, integers, chars, caps or perms.")
+					.value_parser(|s: &str| s.parse::<RowFilter>())
+					.default_value("nonzero")
+					.action(ArgAction::Set)
+			)
+	).subcommand(Command::new("diff")
+			.about("Compare two dumped machines, or two machines after emulating them, and report their differing exec state, registers, interrupt table entries and memory rows. Capabilities are compared regardless of their signatures. Exits with 1 if the machines differ.")
+			.arg(
+				Arg::new("a")
+					.help("The first machine, either dumped by emulate --dump or an input to emulate first (like for the emulate subcommand).")
+					.value_parser(clap::value_parser!(PathBuf))
+					.required(true)
+					.action(ArgAction::Set)
+			)
+			.arg(
+				Arg::new("b")
+					.help("The second machine, either dumped by emulate --dump or an input to emulate first (like for the emulate subcommand).")
+					.value_parser(clap::value_parser!(PathBuf))
+					.required(true)
+					.action(ArgAction::Set)
+			)
+			.arg(
+				Arg::new("compile")
+					.long("compile")
+					.short('c')
+					.help("Indicates that both input files need to first be compiled and emulated. If set, the input files must be uncompiled ASM files.")
+					.required(false)
+					.action(ArgAction::SetTrue)
+			)
 	)
 }
 
+fn stop_conditions(matches: &ArgMatches) -> Vec<StopCondition> {
+	let mut conditions = Vec::new();
+
+	for condition in matches.get_many::<StopCondition>("stop-at").unwrap_or_default() {
+		conditions.push(condition.clone());
+	}
+
+	for (start, end) in matches
+		.get_many::<(AddrInt, AddrInt)>("stop-on-read")
+		.unwrap_or_default()
+	{
+		conditions.push(StopCondition::Read(*start, *end));
+	}
+
+	for (start, end) in matches
+		.get_many::<(AddrInt, AddrInt)>("stop-on-write")
+		.unwrap_or_default()
+	{
+		conditions.push(StopCondition::Write(*start, *end));
+	}
+
+	for perm in matches.get_many::<Permission>("stop-on-cap").unwrap_or_default() {
+		conditions.push(StopCondition::Capability(*perm));
+	}
+
+	for text in matches.get_many::<String>("stop-on-fault").unwrap_or_default() {
+		conditions.push(StopCondition::Fault(text.clone()));
+	}
+
+	conditions
+}
+
+fn parse_address(s: &str) -> Result<AddrInt, String> {
+	match s.strip_prefix("0x") {
+		Some(hex) => AddrInt::from_str_radix(hex, 16),
+		None => s.parse(),
+	}
+	.map_err(|_| format!("invalid address \"{}\"", s))
+}
+
+fn parse_address_or_label(s: &str) -> Result<StopCondition, String> {
+	Ok(parse_address(s).map_or_else(|_| StopCondition::Label(s.to_owned()), StopCondition::Address))
+}
+
+fn parse_range(s: &str) -> Result<(AddrInt, AddrInt), String> {
+	match s.split_once("..") {
+		Some((start, end)) => Ok((parse_address(start)?, parse_address(end)?)),
+		None => parse_address(s).map(|address| (address, address + 1)),
+	}
+}
+
+fn parse_register(s: &str) -> Result<Register, String> {
+	let register = s.to_lowercase();
+
+	if register == "pc" {
+		return Ok(Register::PC);
+	}
+
+	register
+		.strip_prefix('r')
+		.and_then(|n| n.parse().ok())
+		.map(Register::R)
+		.ok_or_else(|| format!("invalid register \"{}\"", s))
+}
+
+fn parse_permission(s: &str) -> Result<Permission, String> {
+	ron::de::from_str(&s.to_uppercase()).map_err(|_| format!("invalid permission \"{}\"", s))
+}
+
 pub fn make_reader(in_path: Option<PathBuf>) -> Box<dyn Read> {
 	in_path
 		.map(|path| {
@@ -0,0 +1,494 @@
+use std::{
+	cell::Cell,
+	io::{self, BufRead, Write},
+	time::{Duration, Instant},
+};
+
+use terminal_size::{Height, Width};
+
+use crate::{
+	debugger::{parse_register, Debugger},
+	emulator::{
+		machine::Machine,
+		program::{Address, Capability, Register, Row, Word},
+	},
+};
+
+/*
+--------------------------------------------------------------------------------
+||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||
+--------------------------------------------------------------------------------
+*/
+
+/// The terminal size used when it can't be queried.
+const DEFAULT_SIZE: (usize, usize) = (120, 40);
+
+/// The width of the registers and memory windows on the left of the screen.
+const LEFT_WIDTH: usize = 56;
+
+/// The number of lines of the command output window at the bottom of the screen.
+const OUTPUT_HEIGHT: usize = 6;
+
+/// How often the screen is redrawn while continuing, by default.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+const HELP: &str = "\
+Type debugger commands below (e.g. \"s\" to step, \"c\" to continue, \"b <label>\" to set a breakpoint, \"help\" for all),
+\"follow <reg|addr>\" to show the memory of the capability in a register or memory row, and \"q\" to quit.
+An empty line repeats the last command.";
+
+/// What the memory window follows: the capability in a register, or in a memory row.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Follow {
+	Register(Register),
+	Address(Address),
+}
+
+/// A full-screen terminal view of a machine, redrawn after every command.
+///
+/// The screen shows the registers, the code around PC, the memory of a capability, the interrupt table,
+/// and the latest entries of the backtrace. Commands are read a line at a time and are those of the debugger,
+/// plus `follow` to choose the capability whose memory is shown.
+/// By default the memory window follows the first general purpose register that holds a capability.
+/// While continuing, the screen is redrawn periodically to show the progress of the machine.
+pub struct Tui {
+	debugger: Debugger,
+	follow: Option<Follow>,
+	output: String,
+	last_command: String,
+	redraw_interval: Duration,
+}
+
+impl Tui {
+	/// Creates a view of the given machine and boots it, like the debugger does.
+	pub fn new(machine: Machine) -> Self {
+		Self {
+			debugger: Debugger::new(machine),
+			follow: None,
+			output: HELP.to_owned(),
+			last_command: String::new(),
+			redraw_interval: REDRAW_INTERVAL,
+		}
+	}
+
+	pub fn machine(&self) -> &Machine {
+		self.debugger.machine()
+	}
+
+	/// Sets how often the screen is redrawn while continuing.
+	pub fn set_redraw_interval(&mut self, interval: Duration) {
+		self.redraw_interval = interval;
+	}
+
+	/// Runs the view on the terminal, redrawing the screen before reading every command,
+	/// until "quit" or until the input ends.
+	pub fn run(&mut self, mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+		// Switch to the alternate screen, to leave the terminal as it was when quitting
+		write!(output, "\x1b[?1049h")?;
+
+		loop {
+			draw(self, &mut output)?;
+
+			let mut line = String::new();
+			if input.read_line(&mut line)? == 0 || !self.execute_redrawing(&line, &mut |tui| draw(tui, &mut output))? {
+				break;
+			}
+		}
+
+		write!(output, "\x1b[?1049l")?;
+		output.flush()
+	}
+
+	/// Executes a single command, the output of which is shown on the next redraw.
+	/// Returns false if the view should quit.
+	pub fn execute(&mut self, command: &str) -> bool {
+		self.execute_redrawing(command, &mut |_| Ok(()))
+			.expect("redrawing without a terminal can't fail")
+	}
+
+	/// Like execute, but calls redraw every redraw interval while continuing.
+	fn execute_redrawing(
+		&mut self,
+		command: &str,
+		redraw: &mut dyn FnMut(&Self) -> io::Result<()>,
+	) -> io::Result<bool> {
+		let command = match command.trim() {
+			"" => self.last_command.clone(),
+			command => command.to_owned(),
+		};
+
+		let words = command.split_whitespace().collect::<Vec<_>>();
+
+		self.output = match words.as_slice() {
+			[] => HELP.to_owned(),
+			["follow", arg] => self.follow(arg).unwrap_or_else(|e| format!("Error: {}", e)),
+			["follow", ..] => "Error: usage: follow <reg|addr>".to_owned(),
+			[c] if self.machine().is_running() && ["c", "continue"].contains(&c.to_lowercase().as_str()) => {
+				self.continue_redrawing(|_| true, redraw)?
+			}
+			[u] if self.machine().is_running() && ["u", "until"].contains(&u.to_lowercase().as_str()) => {
+				let state = self.machine().exec_state;
+				self.continue_redrawing(|machine| machine.exec_state == state, redraw)?
+			}
+			_ => match self.debugger.execute(&command) {
+				Some(output) => output,
+				None => return Ok(false),
+			},
+		};
+
+		self.last_command = command;
+
+		Ok(true)
+	}
+
+	/// Runs like the continue command of the debugger, but in slices of the redraw interval,
+	/// redrawing the screen between them. Returns the output of the command.
+	fn continue_redrawing(
+		&mut self,
+		condition: impl Fn(&Machine) -> bool,
+		redraw: &mut dyn FnMut(&Self) -> io::Result<()>,
+	) -> io::Result<String> {
+		self.output = "Running...".to_owned();
+
+		loop {
+			let start = Instant::now();
+			let paused = Cell::new(false);
+
+			let hit = self.debugger.run_to_breakpoint(|machine| {
+				let holds = condition(machine);
+				paused.set(holds && start.elapsed() >= self.redraw_interval);
+				holds && !paused.get()
+			});
+
+			if hit {
+				return Ok(format!("Breakpoint hit\n{}", self.debugger.location()));
+			}
+
+			if !paused.get() || !self.machine().is_running() {
+				return Ok(self.debugger.location());
+			}
+
+			redraw(self)?;
+		}
+	}
+
+	fn follow(&mut self, arg: &str) -> Result<String, String> {
+		let follow = match parse_register(arg) {
+			Some(register) => Follow::Register(register),
+			None => Follow::Address(self.debugger.resolve_address(arg)?),
+		};
+
+		self.follow = Some(follow);
+
+		Ok(format!("Following {}", arg))
+	}
+
+	/// Renders the screen as lines of text of the given width, with ANSI escapes for emphasis,
+	/// and the given number of lines including the prompt line following it.
+	pub fn render(&self, width: usize, height: usize) -> String {
+		let right_width = width.saturating_sub(LEFT_WIDTH + 3).max(20);
+		let windows_height = height.saturating_sub(OUTPUT_HEIGHT + 3).max(8);
+		let top_height = windows_height.div_ceil(2);
+		let bottom_height = windows_height - top_height;
+
+		let interrupts = self.interrupt_table();
+		let interrupts_height = (interrupts.len() + 1).min(bottom_height / 2);
+
+		let mut lines = vec![emphasize(&fit(&self.debugger.location(), width), Emphasis::Reverse)];
+
+		lines.extend(side_by_side(
+			window("Registers", &self.registers(), LEFT_WIDTH, top_height),
+			window("Code", &self.code(top_height - 1), right_width, top_height),
+		));
+
+		let mut right = window("Interrupt table", &interrupts, right_width, interrupts_height);
+		right.extend(window(
+			"Backtrace",
+			&self.backtrace(bottom_height - interrupts_height - 1),
+			right_width,
+			bottom_height - interrupts_height,
+		));
+
+		let (memory_title, memory) = self.memory(bottom_height - 1);
+		lines.extend(side_by_side(
+			window(&memory_title, &memory, LEFT_WIDTH, bottom_height),
+			right,
+		));
+
+		let output = self.output.lines().map(str::to_owned).collect::<Vec<_>>();
+		let skipped = output.len().saturating_sub(OUTPUT_HEIGHT);
+		lines.extend(window("Output", &output[skipped..], width, OUTPUT_HEIGHT + 1));
+
+		lines.join("\n")
+	}
+
+	fn registers(&self) -> Vec<String> {
+		let machine = self.machine();
+		let symbols = machine.symbol_table();
+
+		let mut registers = machine.registers().iter().collect::<Vec<_>>();
+		registers.sort_by_key(|(register, _)| **register);
+
+		registers
+			.into_iter()
+			.map(|(register, word)| format!("{:<4} {}", register, symbols.format_word(word)))
+			.collect()
+	}
+
+	/// The rows around PC, a few before it and the rest after it, with their labels,
+	/// PC being marked with an arrow and breakpoints with a star.
+	fn code(&self, height: usize) -> Vec<String> {
+		let machine = self.machine();
+		let size = machine.memory.mem_size();
+
+		let Some(pc) = machine.get_register_capability(Register::PC).map(|pc| pc.address) else {
+			return vec![format!(
+				"PC isn't a capability: {}",
+				machine.read_register(Register::PC)
+			)];
+		};
+
+		let start = pc.0.saturating_sub(height / 4).min(size.saturating_sub(1));
+
+		let mut lines = Vec::new();
+		for address in (start..size).map(Address) {
+			for (label, _) in machine.symbols().iter().filter(|(_, a)| *a == address) {
+				lines.push(format!("{}:", label));
+			}
+
+			let marker = match (address == pc, self.debugger.breakpoints().contains(&address)) {
+				(true, true) => "=>*",
+				(true, false) => "=> ",
+				(false, true) => "  *",
+				(false, false) => "   ",
+			};
+
+			let line = format!(
+				"{} {:>6}  {}",
+				marker,
+				address,
+				machine.symbol_table().format_row(&machine.memory[address])
+			);
+			lines.push(match address == pc {
+				true => emphasize(&line, Emphasis::Bold),
+				false => line,
+			});
+
+			if lines.len() >= height {
+				break;
+			}
+		}
+
+		lines.truncate(height);
+		lines
+	}
+
+	/// The rows of the followed capability, around its address, along with a title describing the capability.
+	fn memory(&self, height: usize) -> (String, Vec<String>) {
+		let machine = self.machine();
+		let symbols = machine.symbol_table();
+
+		let (name, capability) = match self.follow {
+			Some(follow) => match self.followed_capability(follow) {
+				Ok(followed) => followed,
+				Err(e) => return ("Memory".to_owned(), vec![e]),
+			},
+			None => {
+				let mut registers = machine.registers().iter().collect::<Vec<_>>();
+				registers.sort_by_key(|(register, _)| **register);
+
+				let first = registers
+					.into_iter()
+					.find_map(|(register, word)| match (register, word) {
+						(Register::R(_), Word::Capability(capability)) => {
+							Some((register.to_string(), *capability.inner()))
+						}
+						_ => None,
+					});
+
+				match first {
+					Some(first) => first,
+					None => {
+						return (
+							"Memory".to_owned(),
+							vec![
+								"No register holds a capability, type \"follow <reg|addr>\" to choose one.".to_owned(),
+							],
+						)
+					}
+				}
+			}
+		};
+
+		let title = format!("Memory of {} = {}", name, symbols.format_capability(&capability));
+
+		let Capability { base, end, address, .. } = capability;
+		let end = Address(end.0.min(machine.memory.mem_size()));
+
+		if base >= end {
+			return (title, vec!["(Empty)".to_owned()]);
+		}
+
+		let start = address
+			.0
+			.saturating_sub(height / 4)
+			.min(end.0.saturating_sub(height))
+			.max(base.0);
+
+		let lines = (start..end.0)
+			.map(Address)
+			.take(height)
+			.map(|row| {
+				let marker = if row == address { "=>" } else { "  " };
+				let label = symbols.symbolize(row).map(|s| format!(" <{}>", s)).unwrap_or_default();
+				format!(
+					"{} {:>6}{} | {}",
+					marker,
+					row,
+					label,
+					symbols.format_row(&machine.memory[row])
+				)
+			})
+			.collect();
+
+		(title, lines)
+	}
+
+	fn followed_capability(&self, follow: Follow) -> Result<(String, Capability), String> {
+		let machine = self.machine();
+
+		let (name, word) = match follow {
+			Follow::Register(register) => (register.to_string(), machine.read_register(register)),
+			Follow::Address(address) => {
+				if address.0 >= machine.memory.mem_size() {
+					return Err(format!("Address {} is out of memory.", address));
+				}
+
+				match &machine.memory[address] {
+					Row::Word(word) => (address.to_string(), word.clone()),
+					Row::Instruction(_) => return Err(format!("{} holds an instruction, not a capability", address)),
+				}
+			}
+		};
+
+		match word {
+			Word::Capability(capability) => Ok((name, *capability.inner())),
+			word => Err(format!("{} doesn't hold a capability: {}", name, word)),
+		}
+	}
+
+	fn interrupt_table(&self) -> Vec<String> {
+		let machine = self.machine();
+
+		let mut table = machine.interrupt_table().iter().collect::<Vec<_>>();
+		table.sort();
+
+		if table.is_empty() {
+			return vec!["(None)".to_owned()];
+		}
+
+		table
+			.into_iter()
+			.map(|(interrupt, address)| {
+				format!(
+					"{:<5} -> {}",
+					interrupt.to_string(),
+					machine.symbol_table().format_address(*address)
+				)
+			})
+			.collect()
+	}
+
+	/// The latest entries of the backtrace, the most recent last, leaving out the running state of steps.
+	fn backtrace(&self, height: usize) -> Vec<String> {
+		let entries = self.machine().backtrace().entries();
+		let skipped = entries.len().saturating_sub(height);
+
+		entries[skipped..]
+			.iter()
+			.map(|entry| {
+				entry
+					.iter()
+					.filter(|column| *column != "State: Running")
+					.cloned()
+					.collect::<Vec<_>>()
+					.join(" | ")
+			})
+			.collect()
+	}
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Emphasis {
+	Bold,
+	Reverse,
+}
+
+fn emphasize(line: &str, emphasis: Emphasis) -> String {
+	let code = match emphasis {
+		Emphasis::Bold => 1,
+		Emphasis::Reverse => 7,
+	};
+
+	format!("\x1b[{}m{}\x1b[0m", code, line)
+}
+
+/// Pads or truncates the line to the given width, ignoring ANSI escapes.
+fn fit(line: &str, width: usize) -> String {
+	let mut out = String::new();
+	let mut visible = 0;
+	let mut chars = line.chars().peekable();
+
+	while let Some(c) = chars.next() {
+		if c == '\x1b' {
+			// Copy the escape sequence up to its final letter
+			out.push(c);
+			for c in chars.by_ref() {
+				out.push(c);
+				if c.is_ascii_alphabetic() {
+					break;
+				}
+			}
+		} else if visible < width {
+			out.push(c);
+			visible += 1;
+		}
+	}
+
+	out.extend(std::iter::repeat_n(' ', width - visible));
+	out
+}
+
+/// Renders a window of exactly the given size: a title line, followed by the first lines of the content.
+fn window(title: &str, content: &[String], width: usize, height: usize) -> Vec<String> {
+	let title = format!("── {} ", title);
+	let title = format!("{}{}", title, "─".repeat(width.saturating_sub(title.chars().count())));
+
+	let mut lines = vec![emphasize(&fit(&title, width), Emphasis::Bold)];
+	lines.extend(content.iter().map(|line| fit(line, width)));
+	lines.resize(height, " ".repeat(width));
+	lines.truncate(height);
+
+	lines
+}
+
+fn side_by_side(left: Vec<String>, right: Vec<String>) -> Vec<String> {
+	left.into_iter()
+		.zip(right)
+		.map(|(left, right)| format!("{} │ {}", left, right))
+		.collect()
+}
+
+/// Clears the screen and draws the view, sized to the terminal, followed by the prompt.
+fn draw(tui: &Tui, output: &mut impl Write) -> io::Result<()> {
+	let (width, height) = screen_size().unwrap_or(DEFAULT_SIZE);
+	write!(output, "\x1b[2J\x1b[H{}\n> ", tui.render(width, height))?;
+	output.flush()
+}
+
+/// Queries the size of the terminal as (columns, lines), if there is one.
+fn screen_size() -> Option<(usize, usize)> {
+	let (Width(width), Height(height)) = terminal_size::terminal_size()?;
+
+	Some((width as usize, height as usize))
+}
@@ -14,7 +14,8 @@ use crate::{
 		instruction::{Instruction, RegisterOrWord},
 		machine::{Interrupt, State},
 		permission::Permission,
-		program::{AddrInt, Address, Capability, Program, Register, Row, Word, WordInt},
+		program::{AddrInt, Address, Capability, Program, Register, Row, SourceLocation, Word, WordInt},
+		rules::Violation,
 	},
 };
 
@@ -46,6 +47,7 @@ where
 	let mut out = String::new();
 
 	let mut entries = hashmap.iter().collect::<Vec<(&K, &V)>>();
+	#[allow(clippy::unnecessary_sort_by)]
 	entries.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
 
 	for (k, v) in entries {
@@ -115,10 +117,13 @@ impl Display for Instruction {
 			Instruction::Fail => f.pad("fail"),
 			Instruction::Halt => f.pad("halt"),
 			Instruction::Mov(r, p) => f.pad(&format!("mov {} {}", r, p)),
-			Instruction::Load(r1, r2) => f.pad(&format!("load {} {}", r1, r2)),
-			Instruction::Store(r, p) => f.pad(&format!("store {} {}", r, p)),
+			Instruction::Load(r1, r2, RegisterOrWord::Word(Word::Integer(0))) => f.pad(&format!("load {} {}", r1, r2)),
+			Instruction::Load(r1, r2, p) => f.pad(&format!("load {} {} {}", r1, r2, p)),
+			Instruction::Store(r, RegisterOrWord::Word(Word::Integer(0)), p) => f.pad(&format!("store {} {}", r, p)),
+			Instruction::Store(r, p1, p2) => f.pad(&format!("store {} {} {}", r, p1, p2)),
 			Instruction::Jmp(r) => f.pad(&format!("jmp {}", r)),
 			Instruction::Jnz(r1, r2) => f.pad(&format!("jnz {} {}", r1, r2)),
+			Instruction::Jal(r1, r2) => f.pad(&format!("jal {} {}", r1, r2)),
 			Instruction::Restrict(r1, perm) => f.pad(&format!("restrict {} {}", r1, perm)),
 			Instruction::Subseg(r, p1, p2) => f.pad(&format!("subseg {} {} {}", r, p1, p2)),
 			Instruction::Lea(r, p) => f.pad(&format!("lea {} {}", r, p)),
@@ -130,6 +135,13 @@ impl Display for Instruction {
 			Instruction::Gete(r1, r2) => f.pad(&format!("gete {} {}", r1, r2)),
 			Instruction::Geta(r1, r2) => f.pad(&format!("geta {} {}", r1, r2)),
 			Instruction::Isptr(r1, r2) => f.pad(&format!("isptr {} {}", r1, r2)),
+			Instruction::Getl(r1, r2) => f.pad(&format!("getl {} {}", r1, r2)),
+			Instruction::Geto(r1, r2) => f.pad(&format!("geto {} {}", r1, r2)),
+			Instruction::Seta(r, p) => f.pad(&format!("seta {} {}", r, p)),
+			Instruction::Setbounds(r, p) => f.pad(&format!("setbounds {} {}", r, p)),
+			Instruction::Subset(r1, r2, r3) => f.pad(&format!("subset {} {} {}", r1, r2, r3)),
+			Instruction::Ceq(r1, r2, r3) => f.pad(&format!("ceq {} {} {}", r1, r2, r3)),
+			Instruction::Flows(r, p1, p2) => f.pad(&format!("flows {} {} {}", r, p1, p2)),
 		}
 	}
 }
@@ -184,6 +196,17 @@ impl Display for Address {
 	}
 }
 
+impl Display for SourceLocation {
+	fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+		let file = self.file.as_deref().unwrap_or("<source>");
+
+		match &self.label {
+			Some(label) => f.pad(&format!("{}:{}:{} in {}", file, self.line, self.column, label)),
+			None => f.pad(&format!("{}:{}:{}", file, self.line, self.column)),
+		}
+	}
+}
+
 impl Display for State {
 	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
 		f.pad(&format!("{:?}", self))
@@ -199,6 +222,18 @@ impl Display for Interrupt {
 	}
 }
 
+impl Display for Violation {
+	fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+		write!(f, "{}: requires {}", self.subject, self.premise)?;
+
+		for (i, (term, value)) in self.values.iter().enumerate() {
+			write!(f, "{} {} = {}", if i == 0 { ", but" } else { "," }, term, value)?;
+		}
+
+		Ok(())
+	}
+}
+
 /*
 --------------------------------------------------------------------------------
 ||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||
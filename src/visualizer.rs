@@ -0,0 +1,377 @@
+use std::{
+	io::{self, BufRead, Write},
+	time::Duration,
+};
+
+use serde_json::{json, Value};
+
+use crate::{
+	debugger::Debugger,
+	emulator::{
+		machine::Machine,
+		memory::Memory,
+		program::{Address, Capability, Register, Row, Word},
+	},
+};
+
+/*
+--------------------------------------------------------------------------------
+||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||||
+--------------------------------------------------------------------------------
+*/
+
+/// The page of the visualizer, with its styles and scripts inline so that it needs nothing but the server.
+const INDEX: &str = include_str!("visualizer/index.html");
+
+/// Runs of zero rows outside of programs at least this long are collapsed into gaps on the memory map.
+const MIN_GAP: usize = 16;
+
+/// The most rows returned by a single memory request.
+const MAX_ROWS: usize = 64;
+
+/// The largest request body accepted, debugger commands being a line long.
+const MAX_BODY: usize = 4096;
+
+/// How long a connection may stay idle while its request is read, as requests are served one at a time.
+pub const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// An HTTP response, with a body of the given content type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Response {
+	pub status: u16,
+	pub content_type: &'static str,
+	pub body: String,
+}
+
+impl Response {
+	fn json(value: Value) -> Self {
+		Self {
+			status: 200,
+			content_type: "application/json",
+			body: value.to_string(),
+		}
+	}
+
+	fn error(status: u16, message: &str) -> Self {
+		Self {
+			status,
+			content_type: "text/plain; charset=utf-8",
+			body: message.to_owned(),
+		}
+	}
+
+	fn reason(&self) -> &'static str {
+		match self.status {
+			200 => "OK",
+			400 => "Bad Request",
+			403 => "Forbidden",
+			404 => "Not Found",
+			405 => "Method Not Allowed",
+			413 => "Payload Too Large",
+			_ => "Error",
+		}
+	}
+}
+
+/// A web visualizer of a machine, served locally over HTTP.
+///
+/// The page draws the memory map with the programs loaded in it, and every capability held in a register
+/// or in memory as an arrow to the range it covers. It is driven by the commands of the debugger,
+/// which step the machine forwards and backwards.
+///
+/// The routes are:
+/// - `GET /`: the page
+/// - `GET /state`: the machine as JSON, see Visualizer::state()
+/// - `GET /memory?start=<addr>&end=<addr>`: the rows in the range as JSON, at most 64 of them
+/// - `POST /command`: runs the debugger command in the body, returning its output and the new state as JSON
+///
+/// Commands are only accepted from the page itself: the Host must be a loopback address, and the Origin
+/// (if any) must be the page's, so that other sites open in the browser cannot drive the machine.
+pub struct Visualizer {
+	debugger: Debugger,
+}
+
+impl Visualizer {
+	/// Creates a visualizer for the given machine and boots it, like the debugger does.
+	pub fn new(machine: Machine) -> Self {
+		Self {
+			debugger: Debugger::new(machine),
+		}
+	}
+
+	pub fn machine(&self) -> &Machine {
+		self.debugger.machine()
+	}
+
+	/// Serves a single HTTP request read from the input, closing the connection after the response.
+	pub fn serve(&mut self, mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+		let mut request_line = String::new();
+		input.read_line(&mut request_line)?;
+
+		let mut content_length = 0;
+		let mut host = None;
+		let mut origin = None;
+		loop {
+			let mut header = String::new();
+			if input.read_line(&mut header)? == 0 || header.trim().is_empty() {
+				break;
+			}
+
+			if let Some((name, value)) = header.split_once(':') {
+				let value = value.trim().to_owned();
+
+				match name.trim().to_ascii_lowercase().as_str() {
+					"content-length" => content_length = value.parse().unwrap_or(0),
+					"host" => host = Some(value),
+					"origin" => origin = Some(value),
+					_ => {}
+				}
+			}
+		}
+
+		let response = match request_line.split_whitespace().collect::<Vec<_>>().as_slice() {
+			_ if content_length > MAX_BODY => Response::error(413, "Request body too large."),
+			[method, target, _] => {
+				let mut body = vec![0; content_length];
+				input.read_exact(&mut body)?;
+
+				if *method == "POST" && !same_origin(host.as_deref(), origin.as_deref()) {
+					Response::error(403, "Commands are only accepted from the visualizer page.")
+				} else {
+					self.handle(method, target, &String::from_utf8_lossy(&body))
+				}
+			}
+			_ => Response::error(400, "Malformed request line."),
+		};
+
+		write!(
+			output,
+			"HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+			response.status,
+			response.reason(),
+			response.content_type,
+			response.body.len(),
+			response.body
+		)?;
+
+		output.flush()
+	}
+
+	/// Handles a request for the given method and target (path and query), with the given body.
+	pub fn handle(&mut self, method: &str, target: &str, body: &str) -> Response {
+		let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+		match (method, path) {
+			("GET", "/") => Response {
+				status: 200,
+				content_type: "text/html; charset=utf-8",
+				body: INDEX.to_owned(),
+			},
+			("GET", "/state") => Response::json(self.state()),
+			("GET", "/memory") => match self.memory(query) {
+				Ok(rows) => Response::json(rows),
+				Err(e) => Response::error(400, &e),
+			},
+			("POST", "/command") => {
+				// Quitting makes no sense here, the page stays open
+				let output = self.debugger.execute(body).unwrap_or_default();
+				Response::json(json!({ "output": output, "state": self.state() }))
+			}
+			(_, "/" | "/state" | "/memory" | "/command") => Response::error(405, "Method not allowed."),
+			_ => Response::error(404, "Not found."),
+		}
+	}
+
+	/// The machine as JSON: its location and state, the segments of the memory map, the labels,
+	/// the capabilities held in registers and in memory, and the interrupt table.
+	/// Addresses are integers, and capabilities are objects with perm, base, end and address fields.
+	pub fn state(&self) -> Value {
+		let machine = self.machine();
+		let symbols = machine.symbol_table();
+
+		let capability = |holder: String, capability: &Capability| {
+			json!({
+				"holder": holder,
+				"perm": capability.perm.to_string(),
+				"base": capability.base.0,
+				"end": capability.end.0,
+				"address": capability.address.0,
+				"text": symbols.format_capability(capability),
+			})
+		};
+
+		let mut registers = machine.registers().iter().collect::<Vec<_>>();
+		registers.sort_by_key(|(register, _)| **register);
+
+		let mut capabilities = registers
+			.iter()
+			.filter_map(|(register, word)| match word {
+				Word::Capability(signed) => Some(capability(register.to_string(), signed.inner())),
+				_ => None,
+			})
+			.collect::<Vec<_>>();
+
+		capabilities.extend((0..machine.memory.mem_size()).map(Address).filter_map(|address| {
+			match &machine.memory[address] {
+				Row::Word(Word::Capability(signed)) => {
+					Some(capability(symbols.format_address(address), signed.inner()))
+				}
+				_ => None,
+			}
+		}));
+
+		let mut labels = symbols.symbols().to_vec();
+		labels.sort_by_key(|(_, address)| *address);
+
+		let labels = labels
+			.iter()
+			.map(|(label, address)| json!({ "label": label, "address": address.0 }))
+			.collect::<Vec<_>>();
+
+		let registers = registers
+			.iter()
+			.map(|(register, word)| json!({ "register": register.to_string(), "value": symbols.format_word(word) }))
+			.collect::<Vec<_>>();
+
+		let mut interrupt_table = machine.interrupt_table().iter().collect::<Vec<_>>();
+		interrupt_table.sort();
+
+		let interrupt_table = interrupt_table
+			.iter()
+			.map(|(interrupt, address)| json!({ "interrupt": interrupt.to_string(), "address": address.0 }))
+			.collect::<Vec<_>>();
+
+		json!({
+			"location": self.debugger.location(),
+			"state": machine.exec_state.to_string(),
+			"pc": machine.get_register_capability(Register::PC).map(|pc| pc.address.0),
+			"size": machine.memory.mem_size(),
+			"segments": self.segments(),
+			"labels": labels,
+			"registers": registers,
+			"capabilities": capabilities,
+			"interrupt_table": interrupt_table,
+		})
+	}
+
+	/// Splits the memory into the segments drawn on the memory map: the programs with labels, named after their
+	/// first label and their source file, long runs of zero rows outside of programs, collapsed into gaps,
+	/// and the other rows, as data.
+	fn segments(&self) -> Vec<Value> {
+		let machine = self.machine();
+		let symbols = machine.symbol_table();
+		let size = machine.memory.mem_size();
+
+		let mut programs = symbols
+			.programs()
+			.map(|(start, end)| (start.0, end.0.min(size)))
+			.collect::<Vec<_>>();
+		programs.sort();
+
+		let mut segments = Vec::new();
+		let mut address = 0;
+		for (start, end) in programs {
+			if start < address {
+				continue;
+			}
+
+			segments.extend(outside_segments(&machine.memory, address, start));
+
+			let name = symbols.labels_at(Address(start)).next().cloned().unwrap_or_default();
+			let file = machine
+				.source_location(Address(start))
+				.and_then(|location| location.file.clone());
+			segments.push(json!({ "kind": "program", "start": start, "end": end, "name": name, "file": file }));
+
+			address = end;
+		}
+
+		segments.extend(outside_segments(&machine.memory, address, size));
+
+		segments
+	}
+
+	/// The rows in the range given by the start and end parameters of the query.
+	fn memory(&self, query: &str) -> Result<Value, String> {
+		let parameter = |name: &str| {
+			query
+				.split('&')
+				.filter_map(|parameter| parameter.split_once('='))
+				.find(|(key, _)| *key == name)
+				.ok_or(format!("Missing parameter \"{}\".", name))
+				.and_then(|(_, value)| self.debugger.resolve_address(value))
+		};
+
+		let machine = self.machine();
+		let symbols = machine.symbol_table();
+
+		let start = parameter("start")?;
+		let end = Address(
+			parameter("end")?
+				.0
+				.min(machine.memory.mem_size())
+				.min(start.0 + MAX_ROWS),
+		);
+
+		let rows = (start.0..end.0.max(start.0))
+			.map(Address)
+			.map(|address| {
+				json!({
+					"address": address.0,
+					"symbol": symbols.symbolize(address),
+					"row": symbols.format_row(&machine.memory[address]),
+				})
+			})
+			.collect::<Vec<_>>();
+
+		Ok(Value::Array(rows))
+	}
+}
+
+/// The segments of the rows from start to end outside of programs: the runs of zero rows long enough to be gaps,
+/// and the rest as data.
+fn outside_segments(memory: &Memory, start: usize, end: usize) -> Vec<Value> {
+	let is_zero = |address: usize| memory[Address(address)] == Row::default();
+
+	// The runs as (start, end, gap)
+	let mut runs = Vec::<(usize, usize, bool)>::new();
+	let mut address = start;
+	while address < end {
+		let zero = is_zero(address);
+		let run_end = (address..end).find(|&a| is_zero(a) != zero).unwrap_or(end);
+
+		let gap = zero && run_end - address >= MIN_GAP;
+		match runs.last_mut() {
+			Some((_, last_end, false)) if !gap => *last_end = run_end,
+			_ => runs.push((address, run_end, gap)),
+		}
+
+		address = run_end;
+	}
+
+	runs.into_iter()
+		.map(|(start, end, gap)| {
+			json!({
+				"kind": if gap { "gap" } else { "data" },
+				"start": start,
+				"end": end,
+			})
+		})
+		.collect()
+}
+
+/// Whether a request comes from the page served on a loopback address: the Host must be one,
+/// and the Origin, which browsers send along with cross-site requests, must match it.
+fn same_origin(host: Option<&str>, origin: Option<&str>) -> bool {
+	let Some(host) = host else {
+		return false;
+	};
+
+	let name = match host.rsplit_once(':') {
+		Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+		_ => host,
+	};
+
+	matches!(name, "localhost" | "127.0.0.1" | "[::1]")
+		&& origin.is_none_or(|origin| origin.strip_prefix("http://") == Some(host))
+}
@@ -0,0 +1,80 @@
+use cerisemu::{
+	compiler,
+	emulator::{
+		machine::Machine,
+		machine_config::{MachineConfig, ProgramConfig},
+		program::{Address, SourceLocation},
+	},
+};
+
+fn location(line: usize, column: usize, label: Option<&str>) -> SourceLocation {
+	SourceLocation {
+		file: None,
+		line,
+		column,
+		label: label.map(str::to_owned),
+	}
+}
+
+#[test]
+fn rows_map_to_lines() {
+	let program = compiler::compile("mov r1 1\nstart:\n\tmov r2 2, halt\n\n  end: 42").unwrap();
+
+	assert_eq!(
+		program.debug_info,
+		vec![
+			location(1, 1, None),
+			location(3, 2, Some("start")),
+			location(3, 12, Some("start")),
+			location(5, 8, Some("end")),
+		]
+	);
+}
+
+#[test]
+fn strings_map_to_their_literal() {
+	let program = compiler::compile("mov r1 1\n\"ab\"").unwrap();
+
+	assert_eq!(program.rows.len(), 3);
+	assert_eq!(program.debug_info[1], location(2, 1, None));
+	assert_eq!(program.debug_info[2], location(2, 1, None));
+}
+
+#[test]
+fn gotos_map_to_the_goto() {
+	let program = compiler::compile("loop:\n\tgoto loop").unwrap();
+
+	// The temporary label of the goto isn't a valid enclosing label
+	assert_eq!(program.debug_info, vec![location(2, 2, Some("loop"))]);
+}
+
+#[test]
+fn source_files_are_kept() {
+	let program = ProgramConfig::from_path_as_source("asm/malloc.asm").compiled();
+
+	assert_eq!(program.debug_info.len(), program.rows.len());
+	assert!(program
+		.debug_info
+		.iter()
+		.all(|location| location.file.as_deref() == Some("asm/malloc.asm")));
+	assert_eq!(program.debug_info[0].label.as_deref(), Some("init"));
+	assert_eq!(program.debug_info[0].to_string(), "asm/malloc.asm:8:2 in init");
+}
+
+#[test]
+fn machine_source_locations() {
+	let config = ron::de::from_str::<MachineConfig>(
+		r#"MachineConfig(size: 0x100, programs: { 0x00: Source("halt"), 0x20: SourceFile("asm/malloc.asm") })"#,
+	)
+	.unwrap();
+	let machine = Machine::initialize_from_config(config);
+
+	assert_eq!(machine.source_location(Address(0)), Some(&location(1, 1, None)));
+	assert_eq!(machine.source_location(Address(1)), None);
+	assert_eq!(machine.source_location(Address(0x20)).unwrap().line, 8);
+
+	let memory = machine.memory.to_string();
+	assert!(memory
+		.lines()
+		.any(|line| line.contains("| halt") && line.ends_with("; <source>:1:1")));
+}
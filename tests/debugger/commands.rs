@@ -0,0 +1,140 @@
+use cerisemu::{
+	debugger::Debugger,
+	emulator::{
+		machine::{Machine, State},
+		machine_config::MachineConfig,
+		program::{Register, Word},
+	},
+};
+
+fn debugger(source: &str) -> Debugger {
+	let config = ron::de::from_str::<MachineConfig>(&format!(
+		r#"
+			MachineConfig(
+				size: 0x100,
+				programs: {{
+					0x00: Source("{source}"),
+				}},
+				interrupt_table: {{
+					Halt: 0xFF,
+				}},
+			)
+		"#
+	))
+	.unwrap();
+
+	Debugger::new(Machine::initialize_from_config(config))
+}
+
+#[test]
+fn step() {
+	let mut debugger = debugger("mov r1 1, mov r1 2, mov r1 3, halt");
+
+	debugger.execute("step").unwrap();
+	assert_eq!(debugger.machine().read_register(Register::R(1)), Word::Integer(1));
+
+	debugger.execute("step 2").unwrap();
+	assert_eq!(debugger.machine().read_register(Register::R(1)), Word::Integer(3));
+	assert_eq!(debugger.machine().exec_state, State::Running);
+}
+
+#[test]
+fn breakpoint_label() {
+	let mut debugger = debugger("mov r1 1, mov r1 2\ntarget: mov r1 3, halt");
+
+	debugger.execute("break target").unwrap();
+	let out = debugger.execute("continue").unwrap();
+
+	assert!(out.contains("<target>"), "{}", out);
+	assert_eq!(debugger.machine().read_register(Register::R(1)), Word::Integer(2));
+
+	debugger.execute("delete target").unwrap();
+	debugger.execute("continue").unwrap();
+
+	assert_eq!(debugger.machine().read_register(Register::R(1)), Word::Integer(3));
+	assert_eq!(debugger.machine().exec_state, State::Halted);
+}
+
+#[test]
+fn until_state_change() {
+	// Halting jumps to the interrupt handler at 0xFF, which is an integer word, and then fails on it
+	let mut debugger = debugger("mov r1 1, halt");
+
+	debugger.execute("until").unwrap();
+	assert!(matches!(debugger.machine().exec_state, State::Interrupted(_)));
+
+	debugger.execute("until").unwrap();
+	assert_eq!(debugger.machine().exec_state, State::Halted);
+
+	let out = debugger.execute("step").unwrap();
+	assert!(out.starts_with("Error"), "{}", out);
+}
+
+#[test]
+fn set_register() {
+	let mut debugger = debugger("add r1 r1 1, halt");
+
+	debugger.execute("set r1 41").unwrap();
+	debugger.execute("set r2 cap RW 0x10 0x20 0x15").unwrap();
+	debugger.execute("step 2").unwrap();
+
+	assert_eq!(debugger.machine().read_register(Register::R(1)), Word::Integer(42));
+
+	let out = debugger.execute("cap r2").unwrap();
+	assert!(out.contains("signature:  valid"), "{}", out);
+	assert!(out.contains("offset 5"), "{}", out);
+}
+
+#[test]
+fn invalid_commands() {
+	let mut debugger = debugger("halt");
+
+	for command in ["foo", "step x", "break nowhere", "set r1", "cap r1", "mem 0x20 0x10"] {
+		let out = debugger.execute(command).unwrap();
+		assert!(out.starts_with("Error"), "{}: {}", command, out);
+	}
+
+	assert_eq!(debugger.execute("quit"), None);
+}
+
+#[test]
+fn back_and_goto() {
+	let mut debugger = debugger("mov r1 1, mov r1 2, mov r1 3, halt");
+
+	debugger.execute("step 3").unwrap();
+	assert!(debugger.execute("back 2").unwrap().starts_with("[step 1]"));
+	assert_eq!(debugger.machine().read_register(Register::R(1)), Word::Integer(1));
+
+	assert!(debugger.execute("back 5").unwrap().starts_with("Error"));
+	assert_eq!(debugger.machine().read_register(Register::R(1)), Word::Integer(0));
+
+	debugger.execute("goto 2").unwrap();
+	assert_eq!(debugger.machine().read_register(Register::R(1)), Word::Integer(2));
+}
+
+#[test]
+fn lastwrite() {
+	let mut debugger = debugger("mov r1 1, mov r2 1, mov r2 2, halt");
+
+	debugger.execute("step 3").unwrap();
+	debugger.execute("lastwrite r1").unwrap();
+
+	// The machine is right before the write
+	assert_eq!(debugger.machine().read_register(Register::R(1)), Word::Integer(0));
+	assert!(debugger.execute("lw r3").unwrap().starts_with("Error"));
+}
+
+#[test]
+fn provenance() {
+	let mut debugger = debugger("mov r1 PC, lea r1 5, restrict r1 RO, halt");
+
+	debugger.execute("step 3").unwrap();
+
+	let out = debugger.execute("provenance r1").unwrap();
+	assert!(out.starts_with("R1 = (RO, 0x0, 0x100, 0x5)\n"));
+	assert!(out.contains("derived at step 2 by restrict R1 RO at 0x2 (<source>:1:22)"));
+	assert!(out.contains("derived at step 1 by lea R1 5 at 0x1 (<source>:1:12)"));
+	assert!(out.ends_with("= the boot-time master capability"));
+
+	assert!(debugger.execute("pv r2").unwrap().starts_with("Error"));
+}
@@ -0,0 +1,287 @@
+use std::{
+	env, fs,
+	io::{self, Cursor},
+};
+
+use cerisemu::{dap::DapServer, emulator::machine::State};
+use serde_json::{json, Value};
+
+const PROGRAM: &str = "main:\n\tmov r1 5\n\tmov r2 PC\n\n\tlea r2 3\n\thalt\n";
+
+/// Writes the program to a file of its own, so that tests can run in parallel.
+fn program_file(name: &str, source: &str) -> String {
+	let path = env::temp_dir().join(format!("cerisemu_dap_{}.asm", name));
+	fs::write(&path, source).unwrap();
+	path.display().to_string()
+}
+
+fn request(server: &mut DapServer, command: &str, arguments: Value) -> Vec<Value> {
+	server.handle(&json!({ "seq": 1, "type": "request", "command": command, "arguments": arguments }))
+}
+
+fn events(messages: &[Value]) -> Vec<&str> {
+	messages[1..]
+		.iter()
+		.map(|message| message["event"].as_str().unwrap())
+		.collect()
+}
+
+fn launch(name: &str, source: &str, stop_on_entry: bool) -> (DapServer, String) {
+	let path = program_file(name, source);
+	let mut server = DapServer::new();
+
+	let initialize = request(&mut server, "initialize", json!({ "adapterID": "cerisemu" }));
+	assert_eq!(initialize[0]["body"]["supportsStepBack"], true);
+
+	let launch = request(
+		&mut server,
+		"launch",
+		json!({ "program": path, "stopOnEntry": stop_on_entry }),
+	);
+	assert_eq!(launch[0]["success"], true);
+	assert_eq!(events(&launch), vec!["initialized"]);
+
+	(server, path)
+}
+
+#[test]
+fn breakpoints_and_stack_trace() {
+	let (mut server, path) = launch("breakpoints", PROGRAM, false);
+
+	let breakpoints = request(
+		&mut server,
+		"setBreakpoints",
+		json!({ "source": { "path": path }, "breakpoints": [{ "line": 4 }, { "line": 5 }] }),
+	);
+	assert_eq!(breakpoints[0]["body"]["breakpoints"][0]["verified"], false);
+	assert_eq!(breakpoints[0]["body"]["breakpoints"][1]["verified"], true);
+
+	let done = request(&mut server, "configurationDone", Value::Null);
+	assert_eq!(events(&done), vec!["stopped"]);
+	assert_eq!(done[1]["body"]["reason"], "breakpoint");
+
+	let stack = request(&mut server, "stackTrace", json!({ "threadId": 1 }));
+	let frame = &stack[0]["body"]["stackFrames"][0];
+	assert_eq!(frame["line"], 5);
+	assert_eq!(frame["column"], 2);
+	assert_eq!(frame["name"], "main+2");
+	assert_eq!(
+		frame["source"]["path"],
+		fs::canonicalize(&path).unwrap().display().to_string()
+	);
+
+	let next = request(&mut server, "next", json!({ "threadId": 1 }));
+	assert_eq!(next[1]["body"]["reason"], "step");
+
+	let back = request(&mut server, "reverseContinue", json!({ "threadId": 1 }));
+	assert_eq!(back[1]["body"]["reason"], "breakpoint");
+
+	// Halting ends the session
+	let end = request(&mut server, "continue", json!({ "threadId": 1 }));
+	assert_eq!(events(&end), vec!["output", "exited", "terminated"]);
+	assert_eq!(end[2]["body"]["exitCode"], 0);
+	assert_eq!(server.machine().unwrap().exec_state, State::Halted);
+}
+
+#[test]
+fn variables() {
+	let (mut server, _) = launch("variables", PROGRAM, true);
+
+	let done = request(&mut server, "configurationDone", Value::Null);
+	assert_eq!(done[1]["body"]["reason"], "entry");
+
+	request(&mut server, "next", json!({ "threadId": 1 }));
+	request(&mut server, "next", json!({ "threadId": 1 }));
+
+	let scopes = request(&mut server, "scopes", json!({ "frameId": 0 }));
+	let scopes = &scopes[0]["body"]["scopes"];
+	assert_eq!(scopes[0]["name"], "Registers");
+	// A program on its own gets just enough memory
+	assert_eq!(scopes[1]["indexedVariables"], 4);
+
+	let registers = request(
+		&mut server,
+		"variables",
+		json!({ "variablesReference": scopes[0]["variablesReference"] }),
+	);
+	let registers = registers[0]["body"]["variables"].as_array().unwrap();
+	assert_eq!(registers.len(), 257);
+	assert_eq!(registers[0]["name"], "PC");
+	assert_eq!(registers[2]["value"], "5");
+	assert_eq!(registers[2]["variablesReference"], 0);
+	assert_eq!(registers[3]["type"], "capability");
+
+	let fields = request(
+		&mut server,
+		"variables",
+		json!({ "variablesReference": registers[3]["variablesReference"] }),
+	);
+	let fields = fields[0]["body"]["variables"]
+		.as_array()
+		.unwrap()
+		.iter()
+		.map(|field| (field["name"].as_str().unwrap(), field["value"].as_str().unwrap()))
+		.collect::<Vec<_>>();
+	assert_eq!(
+		fields,
+		vec![
+			("perm", "RWX"),
+			("base", "0x0 <main>"),
+			("end", "0x4 <main+4>"),
+			("address", "0x1 <main+1>")
+		]
+	);
+
+	let memory = request(
+		&mut server,
+		"variables",
+		json!({ "variablesReference": scopes[1]["variablesReference"], "start": 2, "count": 5 }),
+	);
+	let memory = memory[0]["body"]["variables"].as_array().unwrap();
+	assert_eq!(memory.len(), 2);
+	assert_eq!(memory[0]["name"], "0x2 <main+2>");
+	assert_eq!(memory[0]["value"], "lea R2 3");
+	assert_eq!(memory[1]["value"], "halt");
+
+	// A count past the end of the memory is cut at the end, however large
+	let memory = request(
+		&mut server,
+		"variables",
+		json!({ "variablesReference": scopes[1]["variablesReference"], "start": 2, "count": u64::MAX }),
+	);
+	assert_eq!(memory[0]["body"]["variables"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn evaluate_and_failures() {
+	let (mut server, _) = launch("evaluate", "mov r1 PC\nload r2 r0\nhalt\n", false);
+
+	let done = request(&mut server, "configurationDone", Value::Null);
+	assert_eq!(done[2]["body"]["reason"], "exception");
+
+	let hover = request(
+		&mut server,
+		"evaluate",
+		json!({ "expression": "r1", "context": "hover" }),
+	);
+	assert_eq!(hover[0]["body"]["result"], "$(RWX, 0x0, 0x3, 0x0)");
+	assert_ne!(hover[0]["body"]["variablesReference"], 0);
+
+	let command = request(
+		&mut server,
+		"evaluate",
+		json!({ "expression": "cap r1", "context": "repl" }),
+	);
+	assert!(command[0]["body"]["result"].as_str().unwrap().contains("RWX"));
+
+	let unknown = request(
+		&mut server,
+		"evaluate",
+		json!({ "expression": "cap r1", "context": "hover" }),
+	);
+	assert_eq!(unknown[0]["success"], false);
+
+	// Continuing a failed machine ends the session
+	let end = request(&mut server, "continue", json!({ "threadId": 1 }));
+	assert_eq!(events(&end), vec!["exited", "terminated"]);
+	assert_eq!(end[1]["body"]["exitCode"], 1);
+}
+
+#[test]
+fn compile_error() {
+	let path = program_file("compile_error", "mov r1 5\nrestrict r1 R\n");
+	let mut server = DapServer::new();
+
+	let launch = request(&mut server, "launch", json!({ "program": path }));
+	assert_eq!(launch[0]["success"], false);
+	assert!(launch[0]["message"]
+		.as_str()
+		.unwrap()
+		.starts_with(&format!("Couldn't compile {}:2: error while", path)));
+}
+
+#[test]
+fn load_errors() {
+	let mut server = DapServer::new();
+
+	let invalid = env::temp_dir().join("cerisemu_dap_invalid.ron").display().to_string();
+	fs::write(&invalid, "").unwrap();
+
+	let launch = request(&mut server, "launch", json!({ "program": invalid }));
+	assert_eq!(launch[0]["success"], false);
+	assert!(launch[0]["message"]
+		.as_str()
+		.unwrap()
+		.starts_with(&format!("Couldn't load {}: Could not de-serialize input", invalid)));
+
+	// A machine config whose program doesn't compile
+	let config = env::temp_dir()
+		.join("cerisemu_dap_compile_error.ron")
+		.display()
+		.to_string();
+	fs::write(
+		&config,
+		r#"MachineConfig(size: 0x10, programs: { 0x00: Source("restrict r1 R") })"#,
+	)
+	.unwrap();
+
+	let launch = request(&mut server, "launch", json!({ "program": config }));
+	assert_eq!(launch[0]["success"], false);
+	assert!(launch[0]["message"].as_str().unwrap().starts_with(&format!(
+		"Couldn't load {}: Couldn't load the program at 0x0: Couldn't compile <source>:1: error while",
+		config
+	)));
+}
+
+#[test]
+fn serve() {
+	let path = program_file("serve", PROGRAM);
+
+	let input = [
+		json!({ "seq": 1, "type": "request", "command": "initialize", "arguments": {} }),
+		json!({ "seq": 2, "type": "request", "command": "launch", "arguments": { "program": path } }),
+		json!({ "seq": 3, "type": "request", "command": "disconnect", "arguments": {} }),
+		json!({ "seq": 4, "type": "request", "command": "threads" }),
+	]
+	.iter()
+	.map(|message| {
+		let content = message.to_string();
+		format!("Content-Length: {}\r\n\r\n{}", content.len(), content)
+	})
+	.collect::<String>();
+
+	let mut output = Vec::new();
+	DapServer::new().serve(Cursor::new(input), &mut output).unwrap();
+
+	let output = String::from_utf8(output).unwrap();
+	let messages = output
+		.split("Content-Length: ")
+		.skip(1)
+		.map(|message| {
+			let (length, content) = message.split_once("\r\n\r\n").unwrap();
+			assert_eq!(length.parse::<usize>().unwrap(), content.len());
+			serde_json::from_str::<Value>(content).unwrap()
+		})
+		.collect::<Vec<_>>();
+
+	// The requests after disconnecting aren't handled
+	let seqs = messages
+		.iter()
+		.map(|message| message["seq"].as_i64().unwrap())
+		.collect::<Vec<_>>();
+	assert_eq!(seqs, vec![1, 2, 3, 4]);
+	assert_eq!(messages[1]["request_seq"], 2);
+	assert_eq!(messages[2]["event"], "initialized");
+	assert_eq!(messages[3]["command"], "disconnect");
+}
+
+#[test]
+fn serve_rejects_large_messages() {
+	let input = "Content-Length: 1000000000\r\n\r\n{}";
+
+	let mut output = Vec::new();
+	let error = DapServer::new().serve(Cursor::new(input), &mut output).unwrap_err();
+
+	assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+	assert!(output.is_empty());
+}
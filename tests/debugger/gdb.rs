@@ -0,0 +1,206 @@
+use std::io::Cursor;
+
+use cerisemu::{
+	emulator::{
+		machine::{Machine, State},
+		machine_config::MachineConfig,
+		program::{Register, Word},
+	},
+	gdb::{target_description, GdbStub},
+};
+
+fn stub(source: &str) -> GdbStub {
+	let config = ron::de::from_str::<MachineConfig>(&format!(
+		r#"MachineConfig(size: 0x20, programs: {{ 0x00: Source("{source}") }})"#
+	))
+	.unwrap();
+
+	GdbStub::new(Machine::initialize_from_config(config))
+}
+
+fn handle(stub: &mut GdbStub, packet: &str) -> String {
+	stub.handle(packet).unwrap()
+}
+
+fn hex_u64(value: u64) -> String {
+	value.to_le_bytes().iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn packet(data: &str) -> String {
+	format!(
+		"${}#{:02x}",
+		data,
+		data.bytes().fold(0u8, |sum, byte| sum.wrapping_add(byte))
+	)
+}
+
+const PROGRAM: &str = "mov r1 5\\nmov r2 PC\\nlea r2 3\\nrestrict r2 RO\\nhalt";
+
+#[test]
+fn registers() {
+	let mut stub = stub(PROGRAM);
+
+	// PC is register 0x100, its capability fields 0x201
+	assert_eq!(handle(&mut stub, "p100"), hex_u64(0));
+	assert_eq!(
+		handle(&mut stub, "p201"),
+		[hex_u64(1), hex_u64(5), hex_u64(0), hex_u64(0x20 * 8)].concat()
+	);
+
+	assert_eq!(handle(&mut stub, "s"), "S05");
+	assert_eq!(handle(&mut stub, "p1"), hex_u64(5));
+	assert_eq!(handle(&mut stub, "p100"), hex_u64(8));
+
+	// R1 holds an integer, so it has no capability fields
+	assert_eq!(handle(&mut stub, "p102"), "0".repeat(64));
+
+	let registers = handle(&mut stub, "g");
+	assert_eq!(registers.len(), 2 * (257 * 8 + 257 * 32));
+	assert_eq!(&registers[16..32], hex_u64(5));
+}
+
+#[test]
+fn write_registers() {
+	let mut stub = stub(PROGRAM);
+
+	assert_eq!(handle(&mut stub, &format!("P3={}", hex_u64(42))), "OK");
+	assert_eq!(stub.machine().read_register(Register::R(3)), Word::Integer(42));
+
+	// Turn r3 into a capability, then move its address
+	let fields = [hex_u64(1), hex_u64(4), hex_u64(0x8), hex_u64(0x40)].concat();
+	assert_eq!(handle(&mut stub, &format!("P104={}", fields)), "E02");
+
+	assert_eq!(handle(&mut stub, &format!("P3={}", hex_u64(0x10))), "OK");
+	assert_eq!(handle(&mut stub, &format!("P104={}", fields)), "OK");
+	assert_eq!(handle(&mut stub, &format!("P3={}", hex_u64(0x18))), "OK");
+
+	let capability = stub.machine().get_register_capability(Register::R(3)).unwrap();
+	assert_eq!((capability.base.0, capability.end.0, capability.address.0), (1, 8, 3));
+
+	// Unaligned addresses can't be written
+	assert_eq!(handle(&mut stub, &format!("P3={}", hex_u64(0x19))), "E02");
+}
+
+#[test]
+fn breakpoints_and_continue() {
+	let mut stub = stub(PROGRAM);
+
+	assert_eq!(handle(&mut stub, "Z0,18,8"), "OK");
+	assert_eq!(handle(&mut stub, "c"), "S05");
+	assert_eq!(handle(&mut stub, "p100"), hex_u64(0x18));
+	assert_eq!(handle(&mut stub, "p2"), hex_u64(4 * 8));
+	assert_eq!(
+		handle(&mut stub, "p103"),
+		[hex_u64(1), hex_u64(5), hex_u64(0), hex_u64(0x20 * 8)].concat()
+	);
+
+	// Stepping backwards to the breakpoint, then forward again
+	assert_eq!(handle(&mut stub, "s"), "S05");
+	assert_eq!(handle(&mut stub, "bc"), "S05");
+	assert_eq!(handle(&mut stub, "p100"), hex_u64(0x18));
+
+	assert_eq!(handle(&mut stub, "z0,18,8"), "OK");
+	assert_eq!(handle(&mut stub, "c"), "W00");
+	assert_eq!(stub.machine().exec_state, State::Halted);
+}
+
+#[test]
+fn failure() {
+	let mut stub = stub("mov r1 1\\nload r2 r1\\nhalt");
+
+	assert_eq!(handle(&mut stub, "c"), "S0b");
+	assert_eq!(handle(&mut stub, "?"), "S0b");
+}
+
+#[test]
+fn memory() {
+	let mut stub = stub("mov r1 1\\nhalt\\n7\\n'a'");
+
+	// Rows 2 and 3, then a read crossing the end of memory
+	assert_eq!(handle(&mut stub, "m10,10"), [hex_u64(7), hex_u64('a' as u64)].concat());
+	assert_eq!(handle(&mut stub, "m11,2"), "0000");
+	assert_eq!(handle(&mut stub, "mf8,10"), hex_u64(0));
+	assert_eq!(handle(&mut stub, "m100,8"), "E01");
+	assert_eq!(handle(&mut stub, "mffffffffffffffff,10"), "E01");
+
+	assert_eq!(handle(&mut stub, &format!("M20,8:{}", hex_u64(9))), "OK");
+	assert_eq!(handle(&mut stub, "m20,8"), hex_u64(9));
+	assert_eq!(handle(&mut stub, &format!("M21,8:{}", hex_u64(9))), "E02");
+}
+
+#[test]
+fn target_description_transfer() {
+	let mut stub = stub(PROGRAM);
+	let description = target_description();
+
+	assert!(description.contains("<reg name=\"r255\" bitsize=\"64\" type=\"int64\"/>"));
+	assert!(description.contains("<reg name=\"pc_cap\" bitsize=\"256\" type=\"capability\" group=\"capability\"/>"));
+
+	// Read in chunks like GDB does
+	let mut transferred = String::new();
+	loop {
+		let reply = handle(
+			&mut stub,
+			&format!("qXfer:features:read:target.xml:{:x},400", transferred.len()),
+		);
+		transferred.push_str(&reply[1..]);
+
+		if reply.starts_with('l') {
+			break;
+		}
+	}
+	assert_eq!(transferred, description);
+
+	assert_eq!(
+		handle(&mut stub, "qXfer:features:read:target.xml:10,ffffffffffffffff"),
+		"E01"
+	);
+}
+
+#[test]
+fn monitor() {
+	let mut stub = stub(PROGRAM);
+
+	let command = "cap pc".bytes().map(|byte| format!("{:02x}", byte)).collect::<String>();
+	let reply = handle(&mut stub, &format!("qRcmd,{}", command));
+
+	let bytes = (0..reply.len())
+		.step_by(2)
+		.map(|i| u8::from_str_radix(&reply[i..i + 2], 16).unwrap())
+		.collect::<Vec<_>>();
+	assert!(String::from_utf8(bytes).unwrap().contains("RWX"));
+}
+
+#[test]
+fn serve() {
+	let mut stub = stub(PROGRAM);
+
+	let input = [
+		"+".to_owned(),
+		packet("?"),
+		"+".to_owned(),
+		"$s#00".to_owned(), // Bad checksum, asked again
+		packet("s"),
+		packet("QStartNoAckMode"),
+		"$p1#ff".to_owned(), // Checksums aren't checked anymore
+		packet("k"),
+	]
+	.concat();
+
+	let mut output = Vec::new();
+	stub.serve(Cursor::new(input), &mut output).unwrap();
+
+	assert_eq!(
+		String::from_utf8(output).unwrap(),
+		[
+			"+",
+			&packet("S05"),
+			"-+",
+			&packet("S05"),
+			"+",
+			&packet("OK"),
+			&packet(&hex_u64(5)),
+		]
+		.concat()
+	);
+}
@@ -0,0 +1,143 @@
+use std::{io::Cursor, time::Duration};
+
+use cerisemu::{
+	emulator::{
+		machine::{Machine, State},
+		machine_config::MachineConfig,
+	},
+	tui::Tui,
+};
+
+const PROGRAM: &str = "main:\\n\\tmov r1 PC\\n\\tlea r1 4\\n\\tmov r2 1\\n\\thalt\\ndata:\\n\\t'a'\\n\\t'b'";
+
+fn tui(source: &str) -> Tui {
+	let config = ron::de::from_str::<MachineConfig>(&format!(
+		r#"MachineConfig(size: 0x10, programs: {{ 0x00: Source("{source}") }})"#
+	))
+	.unwrap();
+
+	Tui::new(Machine::initialize_from_config(config))
+}
+
+/// Renders the screen without its ANSI escapes, and without the padding at the end of lines.
+fn screen(tui: &Tui) -> Vec<String> {
+	let mut screen = String::new();
+	let mut chars = tui.render(100, 30).chars().collect::<Vec<_>>().into_iter();
+
+	while let Some(c) = chars.next() {
+		if c == '\x1b' {
+			chars.by_ref().find(char::is_ascii_alphabetic);
+		} else {
+			screen.push(c);
+		}
+	}
+
+	screen.lines().map(|line| line.trim_end().to_owned()).collect()
+}
+
+#[test]
+fn windows() {
+	let mut tui = tui(PROGRAM);
+
+	let lines = screen(&tui);
+	assert_eq!(lines.len(), 29);
+	assert_eq!(lines[0], "[step 0] Running at 0x0 <main> | Instruction(mov R1 PC)");
+	assert!(lines[1].starts_with("── Registers ─"));
+	assert!(lines[1].contains("│ ── Code ─"));
+	assert!(lines[2].starts_with("PC   $(RWX, main, 0x10, main)"));
+	assert!(lines[2].ends_with("│ main:"));
+	assert!(lines[3].ends_with("│ =>     0x0  mov R1 PC"));
+	assert!(lines[7].ends_with("│ data:"));
+	assert!(lines
+		.iter()
+		.any(|line| line.starts_with("No register holds a capability")));
+	assert!(lines.iter().any(|line| line.ends_with("│ (None)")));
+
+	tui.execute("b 2");
+	assert!(tui.execute("s 2"));
+
+	let lines = screen(&tui);
+	assert_eq!(lines[0], "[step 2] Running at 0x2 | Instruction(mov R2 1)");
+	assert!(lines[3].starts_with("R1   $(RWX, main, 0x10, data)"));
+	assert!(lines[4].ends_with("│        0x1  lea R1 4"));
+	assert!(lines[5].ends_with("│ =>*    0x2  mov R2 1"));
+	assert!(lines
+		.iter()
+		.any(|line| line.starts_with("── Memory of R1 = (RWX, main, 0x10, data) ─")));
+	assert!(lines.iter().any(|line| line.starts_with("=>    0x4 <data> | 'a'")));
+	assert!(lines
+		.iter()
+		.any(|line| line.contains("│ PC: $(RWX, main, 0x10, main+1) | Instruc")));
+}
+
+#[test]
+fn commands() {
+	let mut tui = tui(PROGRAM);
+
+	assert!(tui.execute("follow r2"));
+	assert!(screen(&tui)
+		.iter()
+		.any(|line| line.starts_with("R2 doesn't hold a capability: 0 ")));
+
+	assert!(tui.execute("follow"));
+	assert!(screen(&tui)
+		.iter()
+		.any(|line| line == "Error: usage: follow <reg|addr>"));
+
+	// An empty line repeats the last command
+	assert!(tui.execute("s"));
+	assert!(tui.execute(""));
+	assert!(tui.execute("\n"));
+	assert_eq!(tui.machine().exec_state, State::Running);
+	assert!(tui.execute(""));
+	assert_eq!(tui.machine().exec_state, State::Halted);
+
+	assert!(tui.execute("follow 0"));
+	let lines = screen(&tui);
+	assert!(lines.iter().any(|line| line.starts_with("── Output ─")));
+	assert!(lines.iter().any(|line| line == "Following 0"));
+	assert!(lines
+		.iter()
+		.any(|line| line.starts_with("0x0 holds an instruction, not a capability ")));
+
+	assert!(!tui.execute("quit"));
+}
+
+#[test]
+fn run() {
+	let mut tui = tui(PROGRAM);
+	let mut output = Vec::new();
+
+	tui.run(Cursor::new("c\nq\n"), &mut output).unwrap();
+
+	let output = String::from_utf8(output).unwrap();
+	assert!(output.starts_with("\x1b[?1049h\x1b[2J\x1b[H"));
+	assert!(output.ends_with("\x1b[?1049l"));
+	assert_eq!(output.matches("\x1b[2J").count(), 2);
+	assert_eq!(tui.machine().exec_state, State::Halted);
+}
+
+#[test]
+fn continue_redraws() {
+	let mut tui = tui(PROGRAM);
+	tui.set_redraw_interval(Duration::ZERO);
+	let mut output = Vec::new();
+
+	tui.run(Cursor::new("c\nq\n"), &mut output).unwrap();
+
+	// With no interval, the screen is redrawn after each of the first three steps, before halting
+	let output = String::from_utf8(output).unwrap();
+	assert_eq!(output.matches("\x1b[2J").count(), 5);
+	assert_eq!(output.matches("Running...").count(), 3);
+	assert_eq!(tui.machine().exec_state, State::Halted);
+}
+
+#[test]
+fn continue_redrawing_stops_at_breakpoints() {
+	let mut tui = tui(PROGRAM);
+	tui.set_redraw_interval(Duration::ZERO);
+	tui.execute("b 2");
+	assert!(tui.execute("c"));
+	assert!(screen(&tui).iter().any(|line| line.starts_with("Breakpoint hit")));
+	assert_eq!(tui.machine().exec_state, State::Running);
+}
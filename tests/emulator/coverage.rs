@@ -0,0 +1,146 @@
+use std::sync::{Arc, Mutex};
+
+use cerisemu::emulator::{
+	coverage::{Branch, Coverage, NO_FILE},
+	machine::Machine,
+	program::Address,
+};
+
+fn run_with_coverage(config: &str) -> (Machine, Arc<Mutex<Coverage>>) {
+	let mut machine = crate::machine(config);
+	let coverage = Arc::new(Mutex::new(Coverage::default()));
+
+	machine.add_observer(coverage.clone());
+	machine.exec_machine();
+
+	(machine, coverage)
+}
+
+// Loops until r1 reaches 0, and never executes the fail at the end
+const LOOP: &str = r#"
+	MachineConfig(
+		size: 0x20,
+		programs: {
+			0x00: Source("mov r1 2\nloop:\nsub r1 r1 1\nmov r2 PC\nlea r2 [-1]\njnz r2 r1\nhalt\nfail\nend:\n7"),
+		},
+	)
+"#;
+
+#[test]
+fn coverage_rows_and_branches() {
+	let (_, coverage) = run_with_coverage(LOOP);
+	let coverage = coverage.lock().unwrap();
+
+	assert_eq!(coverage.rows()[&Address(0)], 1);
+	assert_eq!(coverage.rows()[&Address(1)], 2);
+	assert_eq!(coverage.rows().get(&Address(6)), None);
+
+	assert_eq!(coverage.branches()[&Address(4)], Branch { taken: 1, not_taken: 1 });
+}
+
+#[test]
+fn coverage_source_lines() {
+	let (machine, coverage) = run_with_coverage(LOOP);
+	let files = coverage.lock().unwrap().source_lines(&machine.memory);
+	let lines = &files[NO_FILE];
+
+	// The data row at the end isn't code
+	assert_eq!(lines.len(), 7);
+
+	assert_eq!(lines[&1].hits, 1);
+	assert_eq!(lines[&3].hits, 2);
+	assert_eq!(lines[&3].labels, vec!["loop".to_owned()]);
+	assert_eq!(lines[&8].hits, 0);
+	assert_eq!(lines[&6].branches.len(), 1);
+}
+
+#[test]
+fn coverage_lcov() {
+	let (machine, coverage) = run_with_coverage(LOOP);
+
+	let mut output = Vec::new();
+	coverage
+		.lock()
+		.unwrap()
+		.write_lcov(&machine.memory, &mut output)
+		.unwrap();
+	let lcov = String::from_utf8(output).unwrap();
+
+	assert!(lcov.starts_with("TN:\nSF:<source>\nFN:3,loop\nFNDA:2,loop\n"));
+	assert!(lcov.contains("BRDA:6,0,0,1\nBRDA:6,0,1,1\nBRF:2\nBRH:2\n"));
+	assert!(lcov.contains("DA:3,2\n"));
+	assert!(lcov.contains("DA:8,0\n"));
+	assert!(lcov.ends_with("LF:7\nLH:6\nend_of_record\n"));
+}
+
+#[test]
+fn coverage_annotated_listing() {
+	let (machine, coverage) = run_with_coverage(LOOP);
+	let listing = coverage.lock().unwrap().annotated_listing(&machine.memory);
+
+	assert!(listing.starts_with("<source> (6/7 lines executed)\n"));
+	assert!(listing.contains("        2 | sub R1 R1 1\n"));
+	assert!(listing.contains("        2 | jnz R2 R1\n          | ^ jnz jumped 1, not jumped 1\n"));
+	assert!(listing.contains("    ##### | fail\n"));
+}
+
+#[test]
+fn coverage_encoded_instructions() {
+	let (machine, coverage) = run_with_coverage(
+		r#"
+			MachineConfig(
+				size: 0x20,
+				encoded_instructions: true,
+				programs: {
+					0x00: Source("mov r1 PC\nlea r1 4\njnz r1 r1\nfail\nhalt"),
+				},
+			)
+		"#,
+	);
+	let files = coverage.lock().unwrap().source_lines(&machine.memory);
+	let lines = &files[NO_FILE];
+
+	assert_eq!(lines.len(), 5);
+	assert_eq!(lines[&4].hits, 0);
+	assert_eq!(lines[&3].branches, vec![Some(Branch { taken: 1, not_taken: 0 })]);
+}
+
+#[test]
+fn coverage_memcpy() {
+	let (machine, coverage) = run_with_coverage(
+		r#"
+			MachineConfig(
+				size: 0x100,
+				registers: {
+					R(0):  Capability(E , 0x00, 0x02, 0x01),
+					R(1):  Capability(RO, 0x50, 0x5B, 0x50),
+					R(2):  Capability(RW, 0x60, 0x6B, 0x60),
+					R(16): Capability(E , 0x10, 0x32, 0x10),
+				},
+				programs: {
+					0x00: Source("jmp r16, halt"),
+					0x10: SourceFile("asm/memcpy.asm"),
+					0x50: SourceFile("asm/lorem.asm"),
+				},
+			)
+		"#,
+	);
+
+	let mut output = Vec::new();
+	coverage
+		.lock()
+		.unwrap()
+		.write_lcov(&machine.memory, &mut output)
+		.unwrap();
+	let lcov = String::from_utf8(output).unwrap();
+
+	// The size check never fails, and the copy loops once per character
+	assert!(lcov.contains("SF:asm/memcpy.asm\n"));
+	assert!(lcov.contains("BRDA:22,0,0,0\nBRDA:22,0,1,1\n"));
+	assert!(lcov.contains("BRDA:46,0,0,10\nBRDA:46,0,1,1\n"));
+	assert!(lcov.contains("DA:24,0\n"));
+
+	let listing = coverage.lock().unwrap().annotated_listing(&machine.memory);
+	assert!(listing.contains("asm/memcpy.asm (24/25 lines executed)\n"));
+	assert!(listing.contains("       11 | \t\tload r3 r1\n"));
+}
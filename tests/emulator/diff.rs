@@ -0,0 +1,102 @@
+use cerisemu::emulator::{
+	diff::MachineDiff,
+	dump::MachineDump,
+	machine::{Interrupt, Machine, State},
+	machine_config::MachineConfig,
+	program::{Address, Register, Row, Word},
+};
+
+fn run(source: &str, fail: usize) -> Machine {
+	let config = ron::de::from_str::<MachineConfig>(&format!(
+		r#"
+			MachineConfig(
+				size: 0x20,
+				programs: {{
+					0x00: Source("{source}"),
+				}},
+				interrupt_table: {{
+					Fail: {fail},
+				}},
+			)
+		"#
+	))
+	.unwrap();
+
+	let mut machine = Machine::initialize_from_config(config);
+	machine.exec_machine();
+	machine
+}
+
+// Stores a capability to itself right after the program
+const PROGRAM: &str = "mov r1 PC\\nlea r1 5\\nmov r2 r1\\nstore r1 r2\\nhalt";
+
+#[test]
+fn identical_runs() {
+	// Both runs sign their capabilities with different keys
+	let a = MachineDump::from(&run(PROGRAM, 0x1F));
+	let b = MachineDump::from(&run(PROGRAM, 0x1F));
+
+	let diff = MachineDiff::new(&a, &b);
+	assert!(diff.is_empty());
+	assert_eq!(diff.format(a.memory.symbols()), "The machines are identical.");
+}
+
+#[test]
+fn identical_dump() {
+	let machine = run(PROGRAM, 0x1F);
+	let dump = ron::de::from_str::<MachineDump>(&ron::ser::to_string(&machine).unwrap()).unwrap();
+
+	assert!(MachineDiff::new(&MachineDump::from(&machine), &dump).is_empty());
+}
+
+#[test]
+fn differing_runs() {
+	let a = MachineDump::from(&run(PROGRAM, 0x1F));
+	let b = MachineDump::from(&run("mov r1 PC\\nlea r1 5\\nmov r2 r1\\nstore r1 7\\nfail", 0x1E));
+
+	let diff = MachineDiff::new(&a, &b);
+
+	assert_eq!(diff.exec_state, Some((State::Halted, State::Failed)));
+	// Halting looked up the halt interrupt, which defaults to 0x0
+	assert_eq!(
+		diff.interrupt_table,
+		vec![
+			(Interrupt::Halt, Some(Address(0x0)), None),
+			(Interrupt::Fail, Some(Address(0x1F)), Some(Address(0x1E)))
+		]
+	);
+	// The failing machine jumped to its fail interrupt
+	let registers = diff
+		.registers
+		.iter()
+		.map(|(register, _, _)| *register)
+		.collect::<Vec<_>>();
+	assert_eq!(registers, vec![Register::PC]);
+	assert_eq!(diff.memory_size, None);
+
+	let addresses = diff.memory.iter().map(|(address, _, _)| *address).collect::<Vec<_>>();
+	assert_eq!(addresses, vec![Address(3), Address(4), Address(5)]);
+	assert!(matches!(
+		diff.memory[2],
+		(_, Row::Word(Word::Capability(_)), Row::Word(Word::Integer(7)))
+	));
+
+	let formatted = diff.format(a.memory.symbols());
+	assert!(formatted.starts_with("Exec state:\n  - Halted\n  + Failed\n"));
+	assert!(formatted.contains("Memory 0x5:\n  - $(RWX, 0x0, 0x20, 0x5)\n  + 7"));
+}
+
+#[test]
+fn differing_registers() {
+	let a = MachineDump::from(&run("mov r1 PC\\nhalt", 0x1F));
+	let b = MachineDump::from(&run("mov r1 PC\\nlea r1 1\\nhalt", 0x1F));
+
+	let diff = MachineDiff::new(&a, &b);
+
+	let registers = diff
+		.registers
+		.iter()
+		.map(|(register, _, _)| *register)
+		.collect::<Vec<_>>();
+	assert_eq!(registers, vec![Register::PC, Register::R(1)]);
+}
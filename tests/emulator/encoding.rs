@@ -0,0 +1,193 @@
+use cerisemu::{
+	compiler,
+	emulator::{
+		self,
+		encoding::{decode, encode, ENCODING_VERSION},
+		instruction::Instruction,
+		machine::{Machine, State},
+		machine_config::{MachineConfig, ProgramConfig},
+		program::{Program, Register, Row, Word},
+	},
+};
+
+#[test]
+fn roundtrip() {
+	let program = compiler::compile(
+		r#"
+			fail, halt
+			mov R0 PC, mov r255 [-1], mov r1 'x', mov r2 [-1 + 0xFFFF]
+			load r1 r2, load r1 r2 [-7], store r1 r2, store PC 'c', store r1 r2 r3
+			jmp r3, jnz r4 r5, jal r6 PC
+			restrict r1 RWX, restrict r1 O, restrict r1 r2, flows r1 E r2
+			subseg r1 0 0x10000, subseg r1 r2 r3
+			lea r1 [-1048576], lea PC 1048575
+			add r1 r2 3, sub r1 [-3] r2, lt r1 'a' 'b'
+			getp r1 r2, getb r1 r2, gete r1 r2, geta r1 r2, isptr r1 r2
+			getl r1 r2, geto r1 r2, seta r1 0x100, setbounds r1 r2, subset r1 r2 r3, ceq r1 r2 r3
+		"#,
+	)
+	.unwrap();
+
+	for row in program.rows {
+		let Row::Instruction(instruction) = row else {
+			panic!("Expected only instructions")
+		};
+
+		let z = encode(&instruction).unwrap();
+		assert!(z >= 0);
+		assert_eq!(decode(z).unwrap(), instruction);
+	}
+}
+
+#[test]
+fn zero_decodes_to_fail() {
+	assert_eq!(decode(0).unwrap(), Instruction::Fail);
+}
+
+#[test]
+fn decode_rejects_invalid() {
+	assert!(decode(-1).is_err());
+	assert!(decode(0xFF).is_err());
+	assert!(decode(1 << 62).is_err());
+}
+
+#[test]
+fn encode_rejects_unencodable() {
+	let program = compiler::compile("mov r1 1048576, lea r1 [-1048577]").unwrap();
+
+	for row in program.rows {
+		let Row::Instruction(instruction) = row else {
+			panic!("Expected only instructions")
+		};
+
+		assert!(encode(&instruction).is_err());
+	}
+}
+
+#[test]
+fn encoded_program_runs() {
+	let config = ron::de::from_str::<MachineConfig>(
+		r#"
+			MachineConfig(
+				size: 0x100,
+				encoded_instructions: true,
+				programs: {
+					0x00: Source("mov r1 5, add r1 r1 r1, halt"),
+				},
+			)
+		"#,
+	)
+	.unwrap();
+
+	let machine = emulator::emulate(config);
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Halted);
+	assert_eq!(machine.read_register(Register::R(1)), Word::Integer(10));
+}
+
+#[test]
+fn code_can_be_copied() {
+	// Copies the 'halt' at 0x40 to 0x10 and jumps to it
+	let config = ron::de::from_str::<MachineConfig>(
+		r#"
+			MachineConfig(
+				size: 0x100,
+				encoded_instructions: true,
+				registers: {
+					R(1): Capability(RO, 0x40, 0x41, 0x40),
+					R(3): Capability(RW, 0x10, 0x11, 0x10),
+					R(4): Capability(E , 0x10, 0x11, 0x10),
+				},
+				programs: {
+					0x00: Source("load R2 R1, store R3 R2, jmp R4"),
+					0x40: Source("halt"),
+				},
+			)
+		"#,
+	)
+	.unwrap();
+
+	let machine = emulator::emulate(config);
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Halted);
+}
+
+#[test]
+fn code_cannot_be_copied_unencoded() {
+	let config = ron::de::from_str::<MachineConfig>(
+		r#"
+			MachineConfig(
+				size: 0x100,
+				registers: {
+					R(1): Capability(RO, 0x40, 0x41, 0x40),
+					R(3): Capability(RW, 0x10, 0x11, 0x10),
+					R(4): Capability(E , 0x10, 0x11, 0x10),
+				},
+				programs: {
+					0x00: Source("load R2 R1, store R3 R2, jmp R4"),
+					0x40: Source("halt"),
+				},
+			)
+		"#,
+	)
+	.unwrap();
+
+	let machine = emulator::emulate(config);
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Failed);
+}
+
+#[test]
+fn unencodable_program_fails_to_load() {
+	let config = ron::de::from_str::<MachineConfig>(
+		r#"
+			MachineConfig(
+				size: 0x100,
+				encoded_instructions: true,
+				programs: {
+					0x10: Source("mov r1 5, mov r1 1048576, halt"),
+				},
+			)
+		"#,
+	)
+	.unwrap();
+
+	let error = Machine::try_initialize_from_config(config).unwrap_err();
+	assert!(error.starts_with("Couldn't load the program at 0x10: couldn't encode \"mov R1 1048576\""));
+}
+
+#[test]
+fn encoded_program_records_layout() {
+	let program = compiler::compile("mov r1 5, halt").unwrap().encoded().unwrap();
+	assert_eq!(program.encoding_version, Some(ENCODING_VERSION));
+
+	let config = |program| MachineConfig {
+		size: 0x100,
+		encoded_instructions: true,
+		programs: [(0x00, ProgramConfig::CompiledProgram(program))].into(),
+		..Default::default()
+	};
+
+	let mut machine = Machine::try_initialize_from_config(config(program.clone())).unwrap();
+	machine.exec_machine();
+	assert_eq!(machine.exec_state, State::Halted);
+
+	// Programs encoded with an older layout would decode into other instructions
+	let older = Program {
+		encoding_version: Some(ENCODING_VERSION - 1),
+		..program.clone()
+	};
+	let error = Machine::try_initialize_from_config(config(older)).unwrap_err();
+	assert!(error.contains(&format!("encoded with layout version {}", ENCODING_VERSION - 1)));
+
+	// Encoded rows are integers, which a machine decoding nothing would fault on
+	let error = Machine::try_initialize_from_config(MachineConfig {
+		encoded_instructions: false,
+		..config(program)
+	})
+	.unwrap_err();
+	assert!(error.contains("the program is encoded, but the machine doesn't run with encoded instructions"));
+}
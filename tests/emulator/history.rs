@@ -0,0 +1,162 @@
+use cerisemu::emulator::{
+	machine::{Interrupt, Machine, State},
+	machine_config::MachineConfig,
+	program::{Address, Register, Row, Word},
+};
+
+fn machine(source: &str) -> Machine {
+	let config = ron::de::from_str::<MachineConfig>(&format!(
+		r#"MachineConfig(size: 0x20, programs: {{ 0x00: Source("{source}") }}, interrupt_table: {{ Halt: 0x1F }})"#
+	))
+	.unwrap();
+
+	let mut machine = Machine::initialize_from_config(config);
+	machine.boot();
+	machine.record_history();
+	machine
+}
+
+#[test]
+fn no_history() {
+	let config = ron::de::from_str::<MachineConfig>(r#"MachineConfig(size: 0x10, programs: { 0x00: Source("halt") })"#);
+	let mut machine = Machine::initialize_from_config(config.unwrap());
+	machine.boot();
+	machine.step();
+
+	assert!(machine.history().is_none());
+	assert!(!machine.step_back());
+	assert!(!machine.goto_step(0));
+}
+
+#[test]
+fn step_back_registers() {
+	let mut machine = machine("mov r1 1, mov r1 2, mov r2 r1, halt");
+
+	machine.run_for(3);
+	assert_eq!(machine.read_register(Register::R(2)), Word::Integer(2));
+
+	assert!(machine.step_back());
+	assert_eq!(machine.read_register(Register::R(2)), Word::Integer(0));
+	assert_eq!(machine.read_register(Register::R(1)), Word::Integer(2));
+
+	assert!(machine.step_back());
+	assert_eq!(machine.read_register(Register::R(1)), Word::Integer(1));
+
+	assert!(machine.step_back());
+	assert_eq!(machine.read_register(Register::R(1)), Word::Integer(0));
+	assert_eq!(machine.get_register_capability(Register::PC).unwrap().address.0, 0);
+
+	assert!(!machine.step_back());
+}
+
+#[test]
+fn step_back_memory_and_state() {
+	let mut machine = machine("mov r1 PC, lea r1 5, store r1 42, halt");
+
+	machine.run_until(|_| false);
+	assert_eq!(machine.exec_state, State::Halted);
+	assert_eq!(machine.memory[Address(5)], Row::Word(Word::Integer(42)));
+
+	// Undo the failed fetch at the interrupt destination, then the halt
+	assert!(machine.goto_step(3));
+	assert_eq!(machine.exec_state, State::Running);
+	assert_eq!(machine.memory[Address(5)], Row::Word(Word::Integer(42)));
+
+	assert!(machine.step_back());
+	assert_ne!(machine.memory[Address(5)], Row::Word(Word::Integer(42)));
+}
+
+#[test]
+fn step_back_backtrace() {
+	let mut machine = machine("mov r1 1, mov r1 2, halt");
+
+	machine.step();
+	let entries = machine.backtrace().entries().to_vec();
+
+	machine.step();
+	assert_ne!(machine.backtrace().entries(), entries.as_slice());
+
+	machine.step_back();
+	assert_eq!(machine.backtrace().entries(), entries.as_slice());
+}
+
+#[test]
+fn goto_step() {
+	let mut machine = machine("mov r1 1, mov r1 2, mov r1 3, halt");
+
+	assert!(machine.goto_step(2));
+	assert_eq!(machine.read_register(Register::R(1)), Word::Integer(2));
+
+	assert!(machine.goto_step(1));
+	assert_eq!(machine.read_register(Register::R(1)), Word::Integer(1));
+
+	assert!(machine.goto_step(3));
+	assert_eq!(machine.read_register(Register::R(1)), Word::Integer(3));
+	assert_eq!(machine.history().unwrap().steps().len(), 3);
+
+	// The machine stops long before reaching step 100
+	assert!(!machine.goto_step(100));
+	assert_eq!(machine.exec_state, State::Halted);
+}
+
+#[test]
+fn last_write() {
+	let mut machine = machine("mov r1 PC, lea r1 8, store r1 1, mov r2 1, store r1 2, mov r2 2, halt");
+
+	machine.run_for(6);
+	let history = machine.history().unwrap();
+
+	assert_eq!(history.last_write(Address(8)), Some(4));
+	assert_eq!(history.last_write(Address(9)), None);
+	assert_eq!(history.last_register_write(Register::R(1)), Some(1));
+	assert_eq!(history.last_register_write(Register::R(2)), Some(5));
+	assert_eq!(history.last_register_write(Register::R(3)), None);
+	assert_eq!(history.steps()[4].address, Some(Address(4)));
+}
+
+#[test]
+fn step_back_removes_new_registers() {
+	let mut machine = machine("mov r1 1, halt");
+
+	machine.step();
+	assert!(machine.registers().contains_key(&Register::R(1)));
+
+	assert!(machine.step_back());
+	assert!(!machine.registers().contains_key(&Register::R(1)));
+}
+
+#[test]
+fn step_back_interrupt_table() {
+	// Failing adds the Fail interrupt to the table, at 0x0
+	let mut machine = machine("mov r1 1, jmp r1");
+
+	machine.run_until(|_| false);
+	assert_eq!(machine.exec_state, State::Failed);
+	assert_eq!(machine.interrupt_table().get(&Interrupt::Fail), Some(&Address(0x0)));
+
+	assert!(machine.step_back());
+	assert_eq!(machine.interrupt_table().get(&Interrupt::Fail), None);
+	assert_eq!(machine.interrupt_table().get(&Interrupt::Halt), Some(&Address(0x1F)));
+}
+
+#[test]
+fn step_back_latest_steps() {
+	let mut machine = machine("mov r1 PC, add r2 r2 1, jmp r1");
+	machine.set_recorded_steps(Some(4));
+	machine.run_for(9);
+
+	let history = machine.history().unwrap();
+	assert_eq!(history.position(), 9);
+	assert_eq!(history.first_step(), 5);
+	assert_eq!(history.steps().len(), 4);
+	assert_eq!(history.last_register_write(Register::R(2)), Some(7));
+
+	// Steps that aren't kept anymore can't be undone
+	assert!(!machine.goto_step(2));
+	assert_eq!(machine.history().unwrap().position(), 5);
+	assert_eq!(machine.read_register(Register::R(2)), Word::Integer(2));
+	assert!(!machine.step_back());
+
+	assert!(machine.goto_step(9));
+	assert_eq!(machine.read_register(Register::R(2)), Word::Integer(3));
+}
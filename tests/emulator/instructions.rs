@@ -1,8 +1,14 @@
+mod flows;
 mod geta;
+mod jal;
 mod jmp;
 mod lea;
+mod load;
 mod lt;
 mod mov;
 mod restrict;
+mod seta;
+mod setbounds;
 mod store;
 mod subseg;
+mod subset;
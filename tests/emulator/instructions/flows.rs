@@ -0,0 +1,53 @@
+use cerisemu::emulator::{
+	self,
+	machine::State,
+	machine_config::MachineConfig,
+	program::{Register, Word},
+};
+
+#[test]
+fn flows_compares_permissions() {
+	let config = ron::de::from_str::<MachineConfig>(
+		r#"
+			MachineConfig(
+				size: 0x200,
+				registers: {
+					R(0): Capability(RW, 0x000, 0x001, 0x000), // Random Capability
+				},
+				programs: {
+					0x00: Source("getp R1 R0, flows R2 RO R1, flows R3 RX R1, flows R4 R1 R1, flows R5 E RWX, halt")
+				},
+			)
+		"#,
+	)
+	.unwrap();
+
+	let machine = emulator::emulate(config);
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Halted);
+	assert_eq!(machine.read_register(Register::R(2)), Word::Integer(1));
+	assert_eq!(machine.read_register(Register::R(3)), Word::Integer(0));
+	assert_eq!(machine.read_register(Register::R(4)), Word::Integer(1));
+	assert_eq!(machine.read_register(Register::R(5)), Word::Integer(1));
+}
+
+#[test]
+fn flows_fails_not_permission() {
+	let config = ron::de::from_str::<MachineConfig>(
+		r#"
+			MachineConfig(
+				size: 0x200,
+				programs: {
+					0x00: Source("flows R1 RO 5, halt")
+				},
+			)
+		"#,
+	)
+	.unwrap();
+
+	let machine = emulator::emulate(config);
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Failed);
+}
@@ -0,0 +1,79 @@
+use cerisemu::emulator::{
+	self, machine::State, machine_config::MachineConfig, permission::Permission::*, program::Register,
+};
+
+use crate::assert_register_capability;
+
+#[test]
+fn jal_links_and_returns() {
+	let config = ron::de::from_str::<MachineConfig>(
+		r#"
+			MachineConfig(
+				size: 0x200,
+				registers: {
+					R(1): Capability(E, 0x10, 0x12, 0x10),
+				},
+				programs: {
+					0x00: Source("jal R1 R0, halt"),
+					0x10: Source("mov R2 R0, jmp R0"),
+				},
+			)
+		"#,
+	)
+	.unwrap();
+
+	let machine = emulator::emulate(config);
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Halted);
+	assert_register_capability!(machine, Register::R(2), (E, 0x00, 0x200, 0x01));
+}
+
+#[test]
+fn jal_same_register() {
+	let config = ron::de::from_str::<MachineConfig>(
+		r#"
+			MachineConfig(
+				size: 0x200,
+				registers: {
+					R(0): Capability(E, 0x10, 0x11, 0x10),
+				},
+				programs: {
+					0x00: Source("jal R0 R0, halt"),
+					0x10: Source("jmp R0"),
+				},
+			)
+		"#,
+	)
+	.unwrap();
+
+	let machine = emulator::emulate(config);
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Halted);
+}
+
+#[test]
+fn jal_fails_bad_target() {
+	let config = ron::de::from_str::<MachineConfig>(
+		r#"
+			MachineConfig(
+				size: 0x200,
+				registers: {
+					R(1): Capability(RW, 0x10, 0x11, 0x10),
+				},
+				programs: {
+					0x00: Source("jal R1 R0, halt"),
+					0x10: Source("halt"),
+				},
+			)
+		"#,
+	)
+	.unwrap();
+
+	let machine = emulator::emulate(config);
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Failed);
+	assert_register_capability!(machine, Register::R(0), (E, 0x00, 0x200, 0x01));
+}
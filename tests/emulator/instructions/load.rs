@@ -101,3 +101,52 @@ fn load_fails_permission() {
 
 	assert_eq!(machine.exec_state, State::Failed);
 }
+
+#[test]
+fn load_offset() {
+	let config = ron::de::from_str::<MachineConfig>(
+		r#"
+			MachineConfig(
+				size: 0x100,
+				registers: {
+					R(0): Capability(RO, 0xF0, 0x100, 0xF0),
+				},
+				programs: {
+					0x00: Source("load R1 R0 [0xFF - 0xF0], mov R3 0xF, load R2 R0 R3, halt"),
+					0xFF: Source("42"),
+				},
+			)
+		"#,
+	)
+	.unwrap();
+
+	let machine = emulator::emulate(config);
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Halted);
+	assert_eq!(machine.read_register(Register::R(1)), Word::Integer(42));
+	assert_eq!(machine.read_register(Register::R(2)), Word::Integer(42));
+}
+
+#[test]
+fn load_offset_fails_bounds() {
+	let config = ron::de::from_str::<MachineConfig>(
+		r#"
+			MachineConfig(
+				size: 0x100,
+				registers: {
+					R(0): Capability(RO, 0xF0, 0x100, 0xF0),
+				},
+				programs: {
+					0x00: Source("load R1 R0 [-1], halt"),
+				},
+			)
+		"#,
+	)
+	.unwrap();
+
+	let machine = emulator::emulate(config);
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Failed);
+}
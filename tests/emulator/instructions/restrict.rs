@@ -1,5 +1,10 @@
 use cerisemu::emulator::{
-	self, machine::State, machine_config::MachineConfig, permission::Permission::*, program::Register,
+	self,
+	instruction::{Instruction, RegisterOrWord},
+	machine::State,
+	machine_config::MachineConfig,
+	permission::Permission::*,
+	program::{Register, Word},
 };
 
 use crate::assert_register_capability;
@@ -120,3 +125,94 @@ fn restrict_fail_ro_to_rx() {
 
 	assert_eq!(machine.exec_state, State::Failed);
 }
+
+#[test]
+fn restrict_register_operand() {
+	let config = ron::de::from_str::<MachineConfig>(
+		r#"
+			MachineConfig(
+				size: 0x200,
+				registers: {
+					R(0): Capability(RWX, 0x000, 0x001, 0x000), // Random Capability
+					R(1): Permission(RO),
+				},
+				programs: {
+					0x00: Source("restrict R0 R1, halt")
+				},
+			)
+		"#,
+	)
+	.unwrap();
+
+	let machine = emulator::emulate(config);
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Halted);
+	assert_register_capability!(machine, Register::R(0), (RO, _, _, _));
+}
+
+#[test]
+fn restrict_permission_from_memory() {
+	let config = ron::de::from_str::<MachineConfig>(
+		r#"
+			MachineConfig(
+				size: 0x200,
+				registers: {
+					R(0): Capability(RWX, 0x100, 0x101, 0x100), // Random Capability
+				},
+				programs: {
+					0x00: Source("load R1 R0, restrict R0 R1, halt"),
+					0x100: Source("RX"),
+				},
+			)
+		"#,
+	)
+	.unwrap();
+
+	let machine = emulator::emulate(config);
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Halted);
+	assert_register_capability!(machine, Register::R(0), (RX, _, _, _));
+}
+
+#[test]
+fn restrict_fails_register_not_permission() {
+	let config = ron::de::from_str::<MachineConfig>(
+		r#"
+			MachineConfig(
+				size: 0x200,
+				registers: {
+					R(0): Capability(RWX, 0x000, 0x001, 0x000), // Random Capability
+					R(1): Integer(2),
+				},
+				programs: {
+					0x00: Source("restrict R0 R1, halt")
+				},
+			)
+		"#,
+	)
+	.unwrap();
+
+	let machine = emulator::emulate(config);
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Failed);
+}
+
+#[test]
+fn restrict_deserializes_bare_permission() {
+	// Compiled programs written before restrict took a register or word operand still load
+	let old = ron::de::from_str::<Instruction>("Restrict(R(1), RO)").unwrap();
+	let new = ron::de::from_str::<Instruction>("Restrict(R(1), Word(Permission(RO)))").unwrap();
+
+	assert_eq!(old, new);
+	assert_eq!(
+		new,
+		Instruction::Restrict(Register::R(1), RegisterOrWord::Word(Word::Permission(RO)))
+	);
+	assert_eq!(
+		ron::de::from_str::<Instruction>(&ron::ser::to_string(&new).unwrap()).unwrap(),
+		new
+	);
+}
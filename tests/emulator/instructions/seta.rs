@@ -0,0 +1,122 @@
+use cerisemu::emulator::{
+	self, machine::State, machine_config::MachineConfig, permission::Permission::*, program::Register,
+};
+
+use crate::assert_register_capability;
+
+#[test]
+fn seta_sets_address() {
+	let config = ron::de::from_str::<MachineConfig>(
+		r#"
+			MachineConfig(
+				size: 0x200,
+				registers: {
+					R(0): Capability(RW, 0x100, 0x104, 0x101), // Random Capability
+				},
+				programs: {
+					0x00: Source("seta R0 0x1FF, halt")
+				},
+			)
+		"#,
+	)
+	.unwrap();
+
+	let machine = emulator::emulate(config);
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Halted);
+	assert_register_capability!(machine, Register::R(0), (RW, 0x100, 0x104, 0x1FF));
+}
+
+#[test]
+fn seta_fails_e() {
+	let config = ron::de::from_str::<MachineConfig>(
+		r#"
+			MachineConfig(
+				size: 0x200,
+				registers: {
+					R(0): Capability(E, 0x100, 0x104, 0x101), // Random Capability
+				},
+				programs: {
+					0x00: Source("seta R0 0x100, halt")
+				},
+			)
+		"#,
+	)
+	.unwrap();
+
+	let machine = emulator::emulate(config);
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Failed);
+}
+
+#[test]
+fn seta_fails_negative() {
+	let config = ron::de::from_str::<MachineConfig>(
+		r#"
+			MachineConfig(
+				size: 0x200,
+				registers: {
+					R(0): Capability(RW, 0x100, 0x104, 0x101), // Random Capability
+				},
+				programs: {
+					0x00: Source("seta R0 [-1], halt")
+				},
+			)
+		"#,
+	)
+	.unwrap();
+
+	let machine = emulator::emulate(config);
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Failed);
+}
+
+#[test]
+fn seta_allows_addresses_outside_memory() {
+	let config = ron::de::from_str::<MachineConfig>(
+		r#"
+			MachineConfig(
+				size: 0x200,
+				registers: {
+					R(0): Capability(RW, 0x100, 0x104, 0x101), // Random Capability
+				},
+				programs: {
+					0x00: Source("seta R0 0x1000, halt")
+				},
+			)
+		"#,
+	)
+	.unwrap();
+
+	let machine = emulator::emulate(config);
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Halted);
+	assert_register_capability!(machine, Register::R(0), (RW, 0x100, 0x104, 0x1000));
+}
+
+#[test]
+fn seta_outside_memory_fails_on_access() {
+	let config = ron::de::from_str::<MachineConfig>(
+		r#"
+			MachineConfig(
+				size: 0x200,
+				registers: {
+					R(0): Capability(RW, 0x100, 0x104, 0x101), // Random Capability
+				},
+				programs: {
+					0x00: Source("seta R0 0x1000, load R1 R0, halt")
+				},
+			)
+		"#,
+	)
+	.unwrap();
+
+	let machine = emulator::emulate(config);
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Failed);
+}
@@ -0,0 +1,98 @@
+use cerisemu::emulator::{
+	self, machine::State, machine_config::MachineConfig, permission::Permission::*, program::Register,
+};
+
+use crate::assert_register_capability;
+
+#[test]
+fn setbounds_shrinks() {
+	let config = ron::de::from_str::<MachineConfig>(
+		r#"
+			MachineConfig(
+				size: 0x200,
+				registers: {
+					R(0): Capability(RW, 0x100, 0x110, 0x104), // Random Capability
+				},
+				programs: {
+					0x00: Source("setbounds R0 0xC, halt")
+				},
+			)
+		"#,
+	)
+	.unwrap();
+
+	let machine = emulator::emulate(config);
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Halted);
+	assert_register_capability!(machine, Register::R(0), (RW, 0x104, 0x110, 0x104));
+}
+
+#[test]
+fn setbounds_fails_too_long() {
+	let config = ron::de::from_str::<MachineConfig>(
+		r#"
+			MachineConfig(
+				size: 0x200,
+				registers: {
+					R(0): Capability(RW, 0x100, 0x110, 0x104), // Random Capability
+				},
+				programs: {
+					0x00: Source("setbounds R0 0xD, halt")
+				},
+			)
+		"#,
+	)
+	.unwrap();
+
+	let machine = emulator::emulate(config);
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Failed);
+}
+
+#[test]
+fn setbounds_fails_below_base() {
+	let config = ron::de::from_str::<MachineConfig>(
+		r#"
+			MachineConfig(
+				size: 0x200,
+				registers: {
+					R(0): Capability(RW, 0x100, 0x110, 0x0FF), // Random Capability
+				},
+				programs: {
+					0x00: Source("setbounds R0 1, halt")
+				},
+			)
+		"#,
+	)
+	.unwrap();
+
+	let machine = emulator::emulate(config);
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Failed);
+}
+
+#[test]
+fn setbounds_fails_e() {
+	let config = ron::de::from_str::<MachineConfig>(
+		r#"
+			MachineConfig(
+				size: 0x200,
+				registers: {
+					R(0): Capability(E, 0x100, 0x110, 0x104), // Random Capability
+				},
+				programs: {
+					0x00: Source("setbounds R0 1, halt")
+				},
+			)
+		"#,
+	)
+	.unwrap();
+
+	let machine = emulator::emulate(config);
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Failed);
+}
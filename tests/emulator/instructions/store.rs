@@ -2,7 +2,7 @@ use cerisemu::emulator::{
 	self,
 	machine::State,
 	machine_config::MachineConfig,
-	program::{Address, Row, Word},
+	program::{Address, Register, Row, Word},
 };
 
 #[test]
@@ -51,3 +51,56 @@ fn store_fails_missing_permission() {
 
 	assert_eq!(machine.exec_state, State::Failed);
 }
+
+#[test]
+fn store_offset() {
+	let config = ron::de::from_str::<MachineConfig>(
+		r#"
+			MachineConfig(
+				size: 0x200,
+				registers: {
+					R(0): Capability(RW, 0x100, 0x104, 0x103), // Random Capability
+					R(1): Capability(RW, 0x100, 0x104, 0x103), // Random Capability
+				},
+				programs: {
+					0x00: Source("mov R2 [-2], store R0 [-1] 42, store R1 R2 'c', halt")
+				},
+			)
+		"#,
+	)
+	.unwrap();
+
+	let machine = emulator::emulate(config);
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Halted);
+	assert_eq!(machine.memory[Address(0x102)], Row::Word(Word::Integer(42)));
+	assert_eq!(machine.memory[Address(0x101)], Row::Word(Word::Char('c')));
+	assert_eq!(
+		machine.get_register_capability(Register::R(0)).unwrap().address,
+		Address(0x103)
+	);
+}
+
+#[test]
+fn store_offset_fails_bounds() {
+	let config = ron::de::from_str::<MachineConfig>(
+		r#"
+			MachineConfig(
+				size: 0x200,
+				registers: {
+					R(0): Capability(RW, 0x000, 0x004, 0x003), // Random Capability
+				},
+				programs: {
+					0x00: Source("store R0 1 42, halt")
+				},
+			)
+		"#,
+	)
+	.unwrap();
+
+	let machine = emulator::emulate(config);
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Failed);
+}
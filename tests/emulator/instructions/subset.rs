@@ -0,0 +1,87 @@
+use cerisemu::emulator::{
+	self,
+	machine::State,
+	machine_config::MachineConfig,
+	program::{Register, Word},
+};
+
+#[test]
+fn subset_and_ceq() {
+	let config = ron::de::from_str::<MachineConfig>(
+		r#"
+			MachineConfig(
+				size: 0x200,
+				registers: {
+					R(0): Capability(RW, 0x100, 0x110, 0x104),
+					R(1): Capability(RO, 0x102, 0x110, 0x100),
+					R(2): Capability(RX, 0x102, 0x110, 0x100),
+					R(3): Capability(RW, 0x100, 0x111, 0x104),
+				},
+				programs: {
+					0x00: Source("subset R10 R0 R1, subset R11 R0 R2, subset R12 R0 R3, subset R13 R0 R0, ceq R14 R0 R0, ceq R15 R0 R3, halt")
+				},
+			)
+		"#,
+	)
+	.unwrap();
+
+	let machine = emulator::emulate(config);
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Halted);
+	assert_eq!(machine.read_register(Register::R(10)), Word::Integer(1));
+	assert_eq!(machine.read_register(Register::R(11)), Word::Integer(0));
+	assert_eq!(machine.read_register(Register::R(12)), Word::Integer(0));
+	assert_eq!(machine.read_register(Register::R(13)), Word::Integer(1));
+	assert_eq!(machine.read_register(Register::R(14)), Word::Integer(1));
+	assert_eq!(machine.read_register(Register::R(15)), Word::Integer(0));
+}
+
+#[test]
+fn getl_geto() {
+	let config = ron::de::from_str::<MachineConfig>(
+		r#"
+			MachineConfig(
+				size: 0x200,
+				registers: {
+					R(0): Capability(O, 0x100, 0x110, 0x104),
+				},
+				programs: {
+					0x00: Source("getl R1 R0, geto R2 R0, halt")
+				},
+			)
+		"#,
+	)
+	.unwrap();
+
+	let machine = emulator::emulate(config);
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Halted);
+	assert_eq!(machine.read_register(Register::R(1)), Word::Integer(0x10));
+	assert_eq!(machine.read_register(Register::R(2)), Word::Integer(0x4));
+}
+
+#[test]
+fn subset_fails_not_capability() {
+	let config = ron::de::from_str::<MachineConfig>(
+		r#"
+			MachineConfig(
+				size: 0x200,
+				registers: {
+					R(0): Capability(RW, 0x100, 0x110, 0x104),
+					R(1): Integer(5),
+				},
+				programs: {
+					0x00: Source("subset R10 R0 R1, halt")
+				},
+			)
+		"#,
+	)
+	.unwrap();
+
+	let machine = emulator::emulate(config);
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Failed);
+}
@@ -0,0 +1,156 @@
+use std::{
+	fs,
+	sync::{Arc, Mutex},
+};
+
+use cerisemu::emulator::{
+	leak::{DomainPolicy, Grant, LeakChecker},
+	machine::{Machine, State},
+	machine_config::MachineConfig,
+	permission::Permission,
+	program::{Address, Register},
+};
+
+/// Enters the routine at 0x5 through an E-capability in r1, with r2 still holding (RWX, 0x0, 0x10, 0x3).
+const CALLER: &str = "mov r1 PC, lea r1 5, restrict r1 E, mov r2 PC, jmp r1, halt";
+
+fn check(config: MachineConfig) -> (Machine, Arc<Mutex<LeakChecker>>) {
+	let checker = Arc::new(Mutex::new(LeakChecker::new(config.domains.clone())));
+
+	let mut machine = Machine::initialize_from_config(config);
+	machine.add_observer(checker.clone());
+	machine.exec_machine();
+
+	(machine, checker)
+}
+
+fn config(source: &str, domains: &str) -> MachineConfig {
+	ron::de::from_str::<MachineConfig>(&format!(
+		r#"MachineConfig(size: 0x10, programs: {{ 0x00: Source("{source}") }}, domains: {domains})"#
+	))
+	.unwrap()
+}
+
+/// The routine at 0x5 is a region of its own, which may only receive O- and E-capabilities.
+const CALLEE: &str = "[(start: 0x5, end: 0x10)]";
+
+#[test]
+fn forgotten_register() {
+	let (machine, checker) = check(config(CALLER, CALLEE));
+	assert_eq!(machine.exec_state, State::Halted);
+
+	let checker = checker.lock().unwrap();
+	let leaks = checker.leaks();
+	assert_eq!(leaks.len(), 1);
+	assert_eq!(leaks[0].step, 4);
+	assert_eq!(leaks[0].address, Address(4));
+	assert_eq!(leaks[0].target, Address(5));
+	assert_eq!(leaks[0].register, Register::R(2));
+	assert_eq!(leaks[0].capability.perm, Permission::RWX);
+
+	assert_eq!(
+		checker.report(machine.symbol_table()),
+		"[step 4] jmp R1 at 0x4 @ <source>:1:48 enters 0x5, but R2 still holds (RWX, 0x0, 0x10, 0x3)"
+	);
+
+	// Clearing the register before jumping, and jumps not through E-capabilities, leak nothing
+	let (_, checker) = check(config(
+		"mov r1 PC, lea r1 6, restrict r1 E, mov r2 PC, mov r2 0, jmp r1, halt",
+		"[(start: 0x6, end: 0x10)]",
+	));
+	assert!(checker.lock().unwrap().leaks().is_empty());
+
+	let (_, checker) = check(config(
+		"mov r2 PC, mov r1 PC, lea r1 3, jmp r1, halt",
+		"[(start: 0x4, end: 0x10)]",
+	));
+	assert!(checker.lock().unwrap().leaks().is_empty());
+	assert_eq!(
+		checker.lock().unwrap().report(machine.symbol_table()),
+		"No capability leaked at domain transitions."
+	);
+}
+
+#[test]
+fn policy() {
+	// The region may receive read-write capabilities, but not executable ones
+	let (_, checker) = check(config(
+		CALLER,
+		"[(start: 0x5, end: 0x10, receives: [(perm: RW, base: 0x0, end: 0x10)])]",
+	));
+	assert_eq!(checker.lock().unwrap().leaks().len(), 1);
+
+	let (_, checker) = check(config(
+		CALLER,
+		"[(start: 0x5, end: 0x10, receives: [(perm: RWX, base: 0x0, end: 0x10)])]",
+	));
+	assert!(checker.lock().unwrap().leaks().is_empty());
+
+	// The policy of another region doesn't apply
+	let (_, checker) = check(config(
+		CALLER,
+		"[(start: 0x0, end: 0x5, receives: [(perm: RWX, base: 0x0, end: 0x10)]), (start: 0x5, end: 0x10)]",
+	));
+	assert_eq!(checker.lock().unwrap().leaks().len(), 1);
+
+	let policy = DomainPolicy {
+		start: 0,
+		end: 0x10,
+		receives: vec![Grant {
+			perm: Permission::RW,
+			base: 0x8,
+			end: 0x10,
+		}],
+	};
+	let capability = |perm, base, end| cerisemu::emulator::program::Capability {
+		perm,
+		base: Address(base),
+		end: Address(end),
+		address: Address(base),
+	};
+	assert!(policy.allows(&capability(Permission::RO, 0x8, 0xA)));
+	assert!(policy.allows(&capability(Permission::E, 0x0, 0x100)));
+	assert!(policy.allows(&capability(Permission::O, 0x0, 0x100)));
+	assert!(!policy.allows(&capability(Permission::RX, 0x8, 0xA)));
+	assert!(!policy.allows(&capability(Permission::RO, 0x7, 0xA)));
+}
+
+#[test]
+fn jumps_within_a_region() {
+	// Without domains, the whole memory is a single region
+	let (machine, checker) = check(config(CALLER, "[]"));
+	assert_eq!(machine.exec_state, State::Halted);
+	assert!(checker.lock().unwrap().leaks().is_empty());
+
+	// A jal to a routine of the same region, with r2 still holding a capability to the code
+	let (machine, checker) = check(config(
+		"mov r1 PC, lea r1 6, restrict r1 E, mov r2 PC, jal r1 r3, halt, jmp r3",
+		"[(start: 0x0, end: 0x10)]",
+	));
+	assert_eq!(machine.exec_state, State::Halted);
+	assert!(checker.lock().unwrap().leaks().is_empty());
+
+	// The same call into another region leaks r2, but returning from it through the link doesn't leak anything new
+	let (_, checker) = check(config(
+		"mov r1 PC, lea r1 6, restrict r1 E, mov r2 PC, jal r1 r3, halt, mov r2 0, jmp r3",
+		"[(start: 0x0, end: 0x6), (start: 0x6, end: 0x10)]",
+	));
+	let leaks = checker.lock().unwrap().leaks().to_vec();
+	assert_eq!(leaks.len(), 1);
+	assert_eq!(leaks[0].register, Register::R(2));
+}
+
+#[test]
+fn os() {
+	let config = ron::de::from_str::<MachineConfig>(&fs::read_to_string("config/os.ron").unwrap()).unwrap();
+	let (machine, checker) = check(config);
+	assert_eq!(machine.exec_state, State::Halted);
+
+	// memcpy() returns with a capability to its own code still in r5
+	let checker = checker.lock().unwrap();
+	let leaks = checker.leaks();
+	assert_eq!(leaks.len(), 1);
+	assert_eq!(leaks[0].register, Register::R(5));
+	assert_eq!(leaks[0].capability.perm, Permission::RX);
+	assert_eq!(machine.symbol_table().format_address(leaks[0].address), "break");
+}
@@ -20,7 +20,7 @@ fn works() {
 					R(0):  Capability(E , 0x00, 0x02, 0x01), // Return to the 'halt' instruction
 					R(1):  Capability(RO, 0x50, 0x5B, 0x50), // Source, should copy 'Lorem ipsum'
 					R(2):  Capability(RW, 0x60, 0x6B, 0x60), // Destination
-					R(16): Capability(E , 0x10, 0x32, 0x10), // memcpy, 33 rows long
+					R(16): Capability(E , 0x10, 0x29, 0x10), // memcpy, 25 rows long
 				},
 				programs: {
 					0x00: Source("jmp r16, halt"),
@@ -56,7 +56,7 @@ fn works_bad_address1() {
 					R(0):  Capability(E , 0x00, 0x02, 0x01), // Return to the 'halt' instruction
 					R(1):  Capability(RO, 0x50, 0x5B, 0x51), // BAD HERE, SHOULD AUTO-CORRECT
 					R(2):  Capability(RW, 0x60, 0x6B, 0x60),
-					R(16): Capability(E , 0x10, 0x32, 0x10), // memcpy, 33 rows long
+					R(16): Capability(E , 0x10, 0x29, 0x10), // memcpy, 25 rows long
 				},
 				programs: {
 					0x00: Source("jmp r16, halt"),
@@ -92,7 +92,7 @@ fn works_bad_address2() {
 					R(0):  Capability(E , 0x00, 0x02, 0x01), // Return to the 'halt' instruction
 					R(1):  Capability(RO, 0x50, 0x5B, 0x50),
 					R(2):  Capability(RW, 0x60, 0x6B, 0x61), // BAD HERE, SHOULD AUTO-CORRECT
-					R(16): Capability(E , 0x10, 0x32, 0x10), // memcpy, 33 rows long
+					R(16): Capability(E , 0x10, 0x29, 0x10), // memcpy, 25 rows long
 				},
 				programs: {
 					0x00: Source("jmp r16, halt"),
@@ -147,7 +147,7 @@ fn fails_different_size1() {
 					R(0):  Capability(E , 0x00, 0x02, 0x01), // Return to the 'halt' instruction
 					R(1):  Capability(RO, 0x50, 0x5B, 0x50),
 					R(2):  Capability(RW, 0x60, 0x6C, 0x60), // BAD HERE
-					R(16): Capability(E , 0x10, 0x32, 0x10), // memcpy, 33 rows long
+					R(16): Capability(E , 0x10, 0x29, 0x10), // memcpy, 25 rows long
 				},
 				programs: {
 					0x00: Source("jmp r16, halt"),
@@ -179,7 +179,7 @@ fn fails_different_size2() {
 					R(0):  Capability(E , 0x00, 0x02, 0x01), // Return to the 'halt' instruction
 					R(1):  Capability(RO, 0x50, 0x5A, 0x50), // BAD HERE
 					R(2):  Capability(RW, 0x60, 0x6B, 0x60),
-					R(16): Capability(E , 0x10, 0x32, 0x10), // memcpy, 33 rows long
+					R(16): Capability(E , 0x10, 0x29, 0x10), // memcpy, 25 rows long
 				},
 				programs: {
 					0x00: Source("jmp r16, halt"),
@@ -211,7 +211,7 @@ fn fails_different_size3() {
 					R(0):  Capability(E , 0x00, 0x02, 0x01), // Return to the 'halt' instruction
 					R(1):  Capability(RO, 0x51, 0x5B, 0x50), // BAD HERE
 					R(2):  Capability(RW, 0x60, 0x6B, 0x60),
-					R(16): Capability(E , 0x10, 0x32, 0x10), // memcpy, 33 rows long
+					R(16): Capability(E , 0x10, 0x29, 0x10), // memcpy, 25 rows long
 				},
 				programs: {
 					0x00: Source("jmp r16, halt"),
@@ -243,7 +243,7 @@ fn fails_different_size4() {
 					R(0):  Capability(E , 0x00, 0x02, 0x01), // Return to the 'halt' instruction
 					R(1):  Capability(RO, 0x50, 0x5B, 0x50), // BAD HERE
 					R(2):  Capability(RW, 0x5F, 0x6B, 0x60),
-					R(16): Capability(E , 0x10, 0x32, 0x10), // memcpy, 33 rows long
+					R(16): Capability(E , 0x10, 0x29, 0x10), // memcpy, 25 rows long
 				},
 				programs: {
 					0x00: Source("jmp r16, halt"),
@@ -0,0 +1,230 @@
+use std::sync::{Arc, Mutex};
+
+use cerisemu::emulator::{
+	instruction::Instruction,
+	machine::{Interrupt, Machine, State},
+	observer::ExecObserver,
+	permission::Permission,
+	program::{Address, Capability, Register, Row, Word},
+};
+
+#[derive(Default)]
+struct Recorder {
+	instructions: Vec<(Address, Instruction, State)>,
+	register_writes: Vec<(Register, Word, Word)>,
+	memory_reads: Vec<(Address, Row)>,
+	memory_writes: Vec<(Address, Row, Row)>,
+	derivations: Vec<(Capability, Capability)>,
+	jumps: Vec<bool>,
+	jump_pcs: Vec<Word>,
+	faults: Vec<String>,
+	interrupts: Vec<Interrupt>,
+	stopped: Vec<State>,
+}
+
+impl ExecObserver for Recorder {
+	fn after_instruction(&mut self, _: &Machine, address: Address, instruction: &Instruction, state: State) {
+		self.instructions.push((address, instruction.clone(), state));
+	}
+
+	fn register_write(&mut self, _: &Machine, register: Register, old: &Word, new: &Word) {
+		if register != Register::PC {
+			self.register_writes.push((register, old.clone(), new.clone()));
+		}
+	}
+
+	fn memory_read(&mut self, _: &Machine, address: Address, row: &Row) {
+		self.memory_reads.push((address, row.clone()));
+	}
+
+	fn memory_write(&mut self, _: &Machine, address: Address, old: &Row, new: &Row) {
+		self.memory_writes.push((address, old.clone(), new.clone()));
+	}
+
+	fn capability_derived(&mut self, _: &Machine, parent: &Capability, child: &Capability) {
+		self.derivations.push((*parent, *child));
+	}
+
+	fn jump(&mut self, machine: &Machine, _: &Word, taken: bool) {
+		self.jumps.push(taken);
+		self.jump_pcs.push(machine.read_register(Register::PC));
+	}
+
+	fn fault(&mut self, _: &Machine, message: &str) {
+		self.faults.push(message.to_owned());
+	}
+
+	fn interrupt(&mut self, _: &Machine, interrupt: Interrupt, _: &Word) {
+		self.interrupts.push(interrupt);
+	}
+
+	fn stopped(&mut self, _: &Machine, state: State) {
+		self.stopped.push(state);
+	}
+}
+
+fn run(config: &str) -> (Machine, Arc<Mutex<Recorder>>) {
+	let mut machine = crate::machine(config);
+
+	let recorder = Arc::new(Mutex::new(Recorder::default()));
+	machine.add_observer(recorder.clone());
+	machine.exec_machine();
+
+	(machine, recorder)
+}
+
+#[test]
+fn observes_instructions() {
+	let (machine, recorder) = run(r#"
+			MachineConfig(
+				size: 0x10,
+				registers: {
+					R(0): Capability(RW, 0x08, 0x10, 0x08),
+				},
+				programs: {
+					0x00: Source("store R0 42, load R1 R0, lea R0 1, jnz R1 R2, halt"),
+				},
+			)
+		"#);
+
+	assert_eq!(machine.exec_state, State::Halted);
+
+	let recorder = recorder.lock().unwrap();
+
+	assert_eq!(recorder.instructions.len(), 5);
+	assert_eq!(recorder.instructions[1].0, Address(0x01));
+	assert_eq!(recorder.instructions[4].2, State::Halted);
+
+	assert_eq!(
+		recorder.memory_writes,
+		vec![(Address(0x08), Row::Word(Word::Integer(0)), Row::Word(Word::Integer(42)))]
+	);
+	assert_eq!(
+		recorder.memory_reads,
+		vec![(Address(0x08), Row::Word(Word::Integer(42)))]
+	);
+
+	assert_eq!(
+		recorder.register_writes[0],
+		(Register::R(1), Word::Integer(0), Word::Integer(42))
+	);
+
+	// PC increments are derivations too
+	assert!(recorder
+		.derivations
+		.iter()
+		.any(|(parent, child)| parent.perm == Permission::RWX && child.address == parent.address + 1));
+
+	let (parent, child) = *recorder
+		.derivations
+		.iter()
+		.find(|(parent, _)| parent.perm == Permission::RW)
+		.unwrap();
+	assert_eq!(parent.address, Address(0x08));
+	assert_eq!(child.address, Address(0x09));
+	assert_eq!(child.perm, Permission::RW);
+
+	assert_eq!(recorder.jumps, vec![false]);
+	assert!(recorder.faults.is_empty());
+	assert_eq!(recorder.stopped, vec![State::Halted]);
+}
+
+#[test]
+fn observes_jumps() {
+	let (machine, recorder) = run(r#"
+			MachineConfig(
+				size: 0x10,
+				registers: {
+					R(0): Capability(E, 0x00, 0x10, 0x03),
+				},
+				programs: {
+					0x00: Source("jnz R0 R1, mov R1 1, jnz R0 R1, halt"),
+				},
+			)
+		"#);
+
+	assert_eq!(machine.exec_state, State::Halted);
+
+	let recorder = recorder.lock().unwrap();
+
+	assert_eq!(recorder.jumps, vec![false, true]);
+
+	// The jump that isn't taken sees PC at the jump, the one taken sees PC at the target
+	let pcs = recorder
+		.jump_pcs
+		.iter()
+		.map(|pc| match pc {
+			Word::Capability(pc) => (pc.inner().perm, pc.inner().address),
+			_ => panic!("PC doesn't hold a capability: {}", pc),
+		})
+		.collect::<Vec<_>>();
+	assert_eq!(
+		pcs,
+		vec![(Permission::RWX, Address(0x00)), (Permission::RX, Address(0x03))]
+	);
+
+	// Only the jump taken turns the E-capability into an RX one
+	assert_eq!(
+		recorder
+			.derivations
+			.iter()
+			.filter(|(parent, _)| parent.perm == Permission::E)
+			.count(),
+		1
+	);
+}
+
+#[test]
+fn observes_faults_and_interrupts() {
+	let (machine, recorder) = run(r#"
+			MachineConfig(
+				size: 0x10,
+				programs: {
+					0x00: Source("load R1 R0"),
+				},
+				interrupt_table: {
+					Fail: 0x0F,
+				},
+			)
+		"#);
+
+	assert_eq!(machine.exec_state, State::Failed);
+
+	let recorder = recorder.lock().unwrap();
+
+	// Once for the load, once for the invalid PC after the interrupt
+	assert_eq!(recorder.faults.len(), 2);
+	assert_eq!(recorder.interrupts, vec![Interrupt::Fail]);
+	assert_eq!(recorder.stopped, vec![State::Failed]);
+}
+
+#[test]
+fn backtrace_is_an_observer() {
+	let (machine, _) = run(r#"MachineConfig(size: 0x10, programs: { 0x00: Source("mov R1 1, halt") })"#);
+
+	let entries = machine.backtrace().entries();
+
+	assert!(entries[0].contains(&"Instruction: mov R1 1 @ <source>:1:1".to_string()));
+	assert!(entries[0].contains(&"R1 = 1".to_string()));
+	assert_eq!(entries.last().unwrap().last().unwrap(), "Shutting down");
+}
+
+#[test]
+fn machine_with_observers_is_send() {
+	let config = r#"MachineConfig(size: 0x10, programs: { 0x00: Source("mov R1 1, halt") })"#;
+	let mut machine = crate::machine(config);
+
+	let recorder = Arc::new(Mutex::new(Recorder::default()));
+	machine.add_observer(recorder.clone());
+
+	// Embedders may run the machine on a thread of their own
+	let machine = std::thread::spawn(move || {
+		machine.exec_machine();
+		machine
+	})
+	.join()
+	.unwrap();
+
+	assert_eq!(machine.exec_state, State::Halted);
+	assert_eq!(recorder.lock().unwrap().instructions.len(), 2);
+}
@@ -0,0 +1,75 @@
+use cerisemu::emulator::{
+	self,
+	profile::{HotLoop, UNLABELLED},
+	program::Address,
+};
+
+use crate::config;
+
+// main calls callee with jal, which loops 3 times before returning through the link
+const CALL_AND_LOOP: &str = r#"
+	MachineConfig(
+		size: 0x20,
+		programs: {
+			0x00: Source("main:\nmov r1 PC\nlea r1 [callee - main]\nrestrict r1 E\njal r1 r0\nhalt\ncallee:\nmov r2 3\nloop:\nsub r2 r2 1\nmov r3 PC\nlea r3 [-1]\njnz r3 r2\njmp r0"),
+		},
+	)
+"#;
+
+#[test]
+fn profile_counts() {
+	let machine = emulator::emulate(config(CALL_AND_LOOP));
+	let profile = machine.profile();
+
+	assert_eq!(profile.total(), 19);
+
+	assert_eq!(profile.addresses()[&Address(0)], 1);
+	assert_eq!(profile.addresses()[&Address(6)], 3);
+	assert_eq!(profile.addresses()[&Address(10)], 1);
+
+	assert_eq!(profile.labels()["main"], 5);
+	assert_eq!(profile.labels()["callee"], 1);
+	assert_eq!(profile.labels()["loop"], 13);
+
+	assert_eq!(profile.opcodes()["jnz"], 3);
+	assert_eq!(profile.opcodes()["jal"], 1);
+	assert_eq!(profile.opcodes().values().sum::<usize>(), 19);
+}
+
+#[test]
+fn profile_hot_loops() {
+	let machine = emulator::emulate(config(CALL_AND_LOOP));
+
+	assert_eq!(
+		machine.profile().hot_loops(),
+		vec![HotLoop {
+			head: Address(6),
+			tail: Address(9),
+			iterations: 2,
+			instructions: 12,
+		}]
+	);
+}
+
+#[test]
+fn profile_collapsed_stacks() {
+	let machine = emulator::emulate(config(CALL_AND_LOOP));
+	let profile = machine.profile();
+
+	let mut output = Vec::new();
+	profile.write_collapsed_stacks(&mut output).unwrap();
+
+	assert_eq!(String::from_utf8(output).unwrap(), "main 5\nmain;callee 14\n");
+}
+
+#[test]
+fn profile_unlabelled() {
+	let machine = emulator::emulate(config(
+		r#"MachineConfig(size: 0x10, programs: { 0x00: Source("mov r1 1, halt") })"#,
+	));
+	let profile = machine.profile();
+
+	assert_eq!(profile.total(), 2);
+	assert_eq!(profile.labels()[UNLABELLED], 2);
+	assert!(profile.hot_loops().is_empty());
+}
@@ -0,0 +1,59 @@
+use std::fs;
+
+use cerisemu::emulator::{
+	self,
+	instruction::{Instruction, RegisterOrWord},
+	machine::State,
+	machine_config::MachineConfig,
+	program::{Program, Register, Word},
+};
+
+#[test]
+fn compiled_programs_load() {
+	let mut loaded = 0;
+
+	for entry in fs::read_dir("bin").unwrap() {
+		let path = entry.unwrap().path();
+		if path.extension().is_some_and(|extension| extension == "ron") {
+			let source = fs::read_to_string(&path).unwrap();
+			ron::de::from_str::<Program>(&source).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+			loaded += 1;
+		}
+	}
+
+	assert!(loaded > 0);
+}
+
+#[test]
+fn hello_world_runs() {
+	let program = ron::de::from_str::<Program>(&fs::read_to_string("bin/hello_world.ron").unwrap()).unwrap();
+	let machine = emulator::emulate(MachineConfig::from_program(program));
+
+	assert_eq!(machine.exec_state, State::Halted);
+	assert_eq!(machine.read_register(Register::R(1)), Word::Integer(42));
+}
+
+#[test]
+fn load_and_store_deserialize_without_offset() {
+	// Compiled programs written before load and store took an offset still load
+	let zero = RegisterOrWord::Word(Word::Integer(0));
+
+	assert_eq!(
+		ron::de::from_str::<Instruction>("Load(R(1), R(0))").unwrap(),
+		Instruction::Load(Register::R(1), Register::R(0), zero.clone())
+	);
+	assert_eq!(
+		ron::de::from_str::<Instruction>("Store(R(1), Register(R(2)))").unwrap(),
+		Instruction::Store(Register::R(1), zero, RegisterOrWord::Register(Register::R(2)))
+	);
+
+	let store = Instruction::Store(
+		Register::R(1),
+		RegisterOrWord::Word(Word::Integer(3)),
+		RegisterOrWord::Register(Register::R(2)),
+	);
+	assert_eq!(
+		ron::de::from_str::<Instruction>(&ron::ser::to_string(&store).unwrap()).unwrap(),
+		store
+	);
+}
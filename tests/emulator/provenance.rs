@@ -0,0 +1,174 @@
+use cerisemu::emulator::{
+	instruction::{Instruction, RegisterOrWord},
+	machine::Machine,
+	permission::Permission,
+	program::{Address, Capability, Register, Word},
+	provenance::{Cause, Derivation},
+};
+
+fn machine(config: &str) -> Machine {
+	let mut machine = crate::machine(config);
+	machine.boot();
+	machine.record_history();
+	machine.record_provenance();
+	machine
+}
+
+const DERIVE: &str = r#"MachineConfig(size: 0x10, programs: { 0x00: Source("mov r1 PC, lea r1 5, subseg r1 5 8, restrict r1 RO, halt") })"#;
+
+fn chain(machine: &Machine, register: Register) -> Vec<Derivation> {
+	let capability = machine.get_register_capability(register).unwrap();
+	machine.provenance().unwrap().chain(&capability)
+}
+
+#[test]
+fn no_provenance() {
+	let mut machine = crate::machine(DERIVE);
+	machine.exec_machine();
+
+	assert!(machine.provenance().is_none());
+	assert_eq!(machine.format_provenance(&machine.master_capability()), None);
+}
+
+#[test]
+fn provenance_instructions() {
+	let mut machine = machine(DERIVE);
+	machine.run_until(|_| false);
+
+	let chain = chain(&machine, Register::R(1));
+
+	assert_eq!(
+		chain.iter().map(|derivation| derivation.step).collect::<Vec<_>>(),
+		vec![3, 2, 1]
+	);
+	assert_eq!(
+		chain[0].cause,
+		Cause::Instruction {
+			address: Address(3),
+			instruction: Instruction::Restrict(Register::R(1), RegisterOrWord::Word(Word::Permission(Permission::RO))),
+		}
+	);
+	assert_eq!(
+		chain[0].child,
+		Capability {
+			perm: Permission::RO,
+			base: Address(5),
+			end: Address(8),
+			address: Address(5),
+		}
+	);
+
+	// mov only copies PC, so the chain ends at the master capability PC started with
+	assert_eq!(chain[2].parent, machine.master_capability());
+}
+
+#[test]
+fn provenance_pc_increments() {
+	let mut machine = machine(DERIVE);
+	machine.run_until(|_| false);
+
+	let chain = chain(&machine, Register::PC);
+
+	assert_eq!(chain.len(), 1);
+	assert_eq!(chain[0].cause, Cause::PcIncrements(4));
+	assert_eq!(chain[0].step, 3);
+	assert_eq!(chain[0].parent, machine.master_capability());
+
+	// A capability copied from PC in the middle of a run of increments
+	let pc_at_2 = Capability {
+		address: Address(2),
+		..machine.master_capability()
+	};
+	let chain = machine.provenance().unwrap().chain(&pc_at_2);
+
+	assert_eq!(chain.len(), 1);
+	assert_eq!(chain[0].cause, Cause::PcIncrements(2));
+	assert_eq!(chain[0].step, 1);
+}
+
+#[test]
+fn provenance_jump_to_e_capability() {
+	let mut machine = machine(
+		r#"
+			MachineConfig(
+				size: 0x10,
+				registers: {
+					R(0): Capability(E, 0x00, 0x10, 0x02),
+				},
+				programs: {
+					0x00: Source("jmp r0, fail, halt"),
+				},
+			)
+		"#,
+	);
+	machine.run_until(|_| false);
+
+	let chain = chain(&machine, Register::PC);
+
+	assert_eq!(chain.len(), 1);
+	assert!(matches!(
+		chain[0].cause,
+		Cause::Instruction {
+			instruction: Instruction::Jmp(_),
+			..
+		}
+	));
+	assert_eq!(chain[0].parent.perm, Permission::E);
+	assert_eq!(chain[0].child.perm, Permission::RX);
+
+	let formatted = machine
+		.format_provenance(&machine.get_register_capability(Register::PC).unwrap())
+		.unwrap();
+
+	assert!(formatted.contains("derived at step 0 by jmp R0 at 0x0 (<source>:1:1)"));
+	assert!(formatted.ends_with("not derived by an instruction (loaded from the config, or written by hand)"));
+}
+
+#[test]
+fn provenance_jnz_not_taken() {
+	let mut machine = machine(
+		r#"
+			MachineConfig(
+				size: 0x10,
+				registers: {
+					R(0): Capability(E, 0x00, 0x10, 0x02),
+					R(1): Integer(0),
+				},
+				programs: {
+					0x00: Source("jnz r0 r1, halt"),
+				},
+			)
+		"#,
+	);
+	machine.run_until(|_| false);
+
+	// Only PC increments were derived, the E-capability wasn't turned into an RX one
+	let entered = Capability {
+		perm: Permission::RX,
+		base: Address(0x00),
+		end: Address(0x10),
+		address: Address(0x02),
+	};
+
+	assert!(machine.provenance().unwrap().chain(&entered).is_empty());
+	assert_eq!(chain(&machine, Register::PC)[0].cause, Cause::PcIncrements(1));
+}
+
+#[test]
+fn provenance_step_back() {
+	let mut machine = machine(DERIVE);
+	machine.run_until(|_| false);
+
+	// Undo halt and restrict
+	assert!(machine.step_back());
+	assert!(machine.step_back());
+
+	assert_eq!(chain(&machine, Register::R(1)).len(), 2);
+	assert_eq!(chain(&machine, Register::PC)[0].cause, Cause::PcIncrements(3));
+
+	// Redo them
+	machine.run_until(|_| false);
+
+	assert_eq!(chain(&machine, Register::R(1)).len(), 3);
+	assert_eq!(chain(&machine, Register::PC)[0].cause, Cause::PcIncrements(4));
+}
@@ -0,0 +1,159 @@
+use cerisemu::emulator::{
+	self,
+	instruction::{Instruction, RegisterOrWord},
+	machine::{Machine, State},
+	permission::Permission,
+	program::{Register, Word},
+	rules::{self, Violation},
+	semantics::Semantics,
+	trace::TraceStep,
+};
+
+fn run(source: &str) -> Machine {
+	emulator::emulate(crate::config(&format!(
+		r#"MachineConfig(size: 0x10, programs: {{ 0x00: Source("{source}") }})"#
+	)))
+}
+
+#[test]
+fn failed_premise() {
+	let machine = run("mov r2 PC, subseg r2 4 6, lea r2 4, load r1 r2 2");
+	assert_eq!(machine.exec_state, State::Failed);
+
+	let violation = machine.failure().unwrap();
+	assert_eq!(violation.rule, "load r1 r2 ρ");
+	assert_eq!(violation.premise, rules::OFFSET_IN_BOUNDS);
+	assert_eq!(
+		violation.to_string(),
+		"load R1 R2 2: requires b ≤ a + z < e, but b = 0x4, a + z = 0x6, e = 0x6"
+	);
+
+	let rule = violation.describe_rule(Semantics::Modified).unwrap();
+	assert!(rule.starts_with("Rule load r1 r2 ρ (extended from Cerise):\n"));
+	assert!(rule.contains("\n  ✗ b ≤ a + z < e\n"));
+	assert!(rule.contains("\n    p ∈ {ro, rx, rw, rwx}\n"));
+	assert!(rule.ends_with("\n  ⇒ updPC(φ[reg.r1 ↦ w])"));
+
+	// The violation is part of the step that failed
+	let step = machine.trace().steps().last().unwrap();
+	assert_eq!(step.violation.as_ref(), Some(violation));
+	assert_eq!(step.fault.as_deref(), Some(violation.to_string().as_str()));
+
+	let json = serde_json::to_string(step).unwrap();
+	let step = serde_json::from_str::<TraceStep>(&json).unwrap();
+	assert_eq!(step.violation.as_ref(), Some(violation));
+}
+
+#[test]
+fn premise_order() {
+	// The premises are checked in the order of the rule, so the first one that doesn't hold is reported
+	let machine = run("mov r1 PC, restrict r1 E, lea r1 1");
+	assert_eq!(
+		machine.failure().unwrap().to_string(),
+		"lea R1 1: requires p ≠ e, but p = E"
+	);
+
+	let machine = run("mov r1 PC, restrict r1 RO, store r1 20 5");
+	assert_eq!(
+		machine.failure().unwrap().to_string(),
+		"store R1 20 5: requires p ∈ {rw, rwx}, but p = RO"
+	);
+
+	let machine = run("mov r1 PC, restrict r1 RW, restrict r1 RX");
+	assert_eq!(
+		machine.failure().unwrap().to_string(),
+		"restrict R1 RX: requires p' ≼ p, but p' = RX, p = RW"
+	);
+
+	let machine = run("mov r1 5, ceq r2 PC r1");
+	assert_eq!(
+		machine.failure().unwrap().to_string(),
+		"ceq R2 PC R1: requires φ.reg(r3) = (p3, b3, e3, a3), but φ.reg(r3) = 5"
+	);
+
+	// Fetching fails before any instruction is known
+	let machine = run("mov r1 PC, lea r1 3, jmp r1");
+	let violation = machine.failure().unwrap();
+	assert_eq!(violation.rule, "ExecSingle");
+	assert_eq!(violation.premise, rules::INSTRUCTION);
+	assert_eq!(violation.values[0].0, "φ.mem(a)");
+
+	// A failure recovered from with an interrupt is not the reason the machine failed
+	let machine = run("halt");
+	assert_eq!(machine.exec_state, State::Halted);
+	assert_eq!(machine.failure(), None);
+}
+
+#[test]
+fn every_instruction_has_a_rule() {
+	let r = Register::R(1);
+	let p = RegisterOrWord::Word(Word::Integer(0));
+	let instructions = [
+		Instruction::Fail,
+		Instruction::Halt,
+		Instruction::Mov(r, p.clone()),
+		Instruction::Load(r, r, p.clone()),
+		Instruction::Store(r, p.clone(), p.clone()),
+		Instruction::Jmp(r),
+		Instruction::Jnz(r, r),
+		Instruction::Jal(r, r),
+		Instruction::Restrict(r, RegisterOrWord::Word(Word::Permission(Permission::RO))),
+		Instruction::Subseg(r, p.clone(), p.clone()),
+		Instruction::Lea(r, p.clone()),
+		Instruction::Add(r, p.clone(), p.clone()),
+		Instruction::Sub(r, p.clone(), p.clone()),
+		Instruction::Lt(r, p.clone(), p.clone()),
+		Instruction::Getp(r, r),
+		Instruction::Getb(r, r),
+		Instruction::Gete(r, r),
+		Instruction::Geta(r, r),
+		Instruction::Isptr(r, r),
+		Instruction::Getl(r, r),
+		Instruction::Geto(r, r),
+		Instruction::Seta(r, p.clone()),
+		Instruction::Setbounds(r, p.clone()),
+		Instruction::Subset(r, r, r),
+		Instruction::Ceq(r, r, r),
+		Instruction::Flows(r, p.clone(), p),
+	];
+
+	for instruction in instructions {
+		let rule = rules::instruction_rule(&instruction);
+		assert!(rule.name.starts_with(instruction.mnemonic()));
+		assert_eq!(rules::find_rule(rule.name), Some(rule));
+	}
+
+	let violation = Violation::new(
+		"updPC(φ)".to_owned(),
+		&rules::UPD_PC,
+		rules::PC_CAPABILITY,
+		&[("φ.reg(pc)", "0".to_owned())],
+	);
+	assert_eq!(
+		violation.describe_rule(Semantics::Modified).unwrap(),
+		"Rule updPC(φ):\n  ✗ φ.reg(pc) = (p, b, e, a)\n  ⇒ (Running, φ[reg.pc ↦ (p, b, e, a + 1)])"
+	);
+}
+
+#[test]
+fn premises_follow_semantics() {
+	// Only Cerise bounds the address of lea
+	let lea = rules::instruction_rule(&Instruction::Lea(
+		Register::R(1),
+		RegisterOrWord::Word(Word::Integer(1)),
+	));
+	assert!(lea
+		.premises(Semantics::Cerise)
+		.any(|premise| premise == rules::LEA_ADDRESS));
+	assert!(!lea
+		.premises(Semantics::Modified)
+		.any(|premise| premise == rules::LEA_ADDRESS));
+
+	let modified = lea.describe(None, Semantics::Modified);
+	assert!(modified.starts_with("Rule lea r ρ (modified from Cerise):\n"));
+	assert!(!modified.contains(rules::LEA_ADDRESS));
+
+	let cerise = lea.describe(None, Semantics::Cerise);
+	assert!(cerise.starts_with("Rule lea r ρ:\n"));
+	assert!(cerise.contains(&format!("\n    {}\n", rules::LEA_ADDRESS)));
+}
@@ -0,0 +1,161 @@
+use std::fs;
+
+use cerisemu::emulator::{
+	self,
+	machine::State,
+	machine_config::MachineConfig,
+	permission::Permission::*,
+	program::{Address, Register, Row, Word},
+	semantics::Semantics,
+};
+
+use crate::assert_register_capability;
+
+fn config(semantics: &str, program: &str) -> MachineConfig {
+	ron::de::from_str::<MachineConfig>(&format!(
+		r#"
+			MachineConfig(
+				size: 0x200,
+				semantics: {semantics},
+				registers: {{
+					R(0): Capability(RW, 0x000, 0x200, 0x000), // Random Capability
+				}},
+				programs: {{
+					0x00: Source("{program}")
+				}},
+			)
+		"#
+	))
+	.unwrap()
+}
+
+#[test]
+fn default_is_modified() {
+	let config = ron::de::from_str::<MachineConfig>("MachineConfig(size: 0x10)").unwrap();
+
+	assert_eq!(config.semantics, Semantics::Modified);
+}
+
+#[test]
+fn getp_encodes_permission() {
+	let machine = emulator::emulate(config("Cerise", "getp R1 R0, halt"));
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Halted);
+	assert_eq!(machine.read_register(Register::R(1)), Word::Integer(4));
+
+	let machine = emulator::emulate(config("Modified", "getp R1 R0, halt"));
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Halted);
+	assert_eq!(machine.read_register(Register::R(1)), Word::Permission(RW));
+}
+
+#[test]
+fn restrict_decodes_integer() {
+	let machine = emulator::emulate(config("Cerise", "restrict R0 2, halt"));
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Halted);
+	assert_register_capability!(machine, Register::R(0), (RO, 0x000, 0x200, 0x000));
+
+	let machine = emulator::emulate(config("Modified", "restrict R0 2, halt"));
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Failed);
+}
+
+#[test]
+fn permission_literals_are_encoded() {
+	// Cerise has no permission words, so the literals are loaded as integers, in instructions and in data
+	let machine = emulator::emulate(config("Cerise", "restrict R0 RO, halt, RX"));
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Halted);
+	assert_register_capability!(machine, Register::R(0), (RO, 0x000, 0x200, 0x000));
+	assert_eq!(machine.memory[Address(2)], Row::Word(Word::Integer(3)));
+
+	let machine = emulator::emulate(config("Modified", "restrict R0 RO, halt, RX"));
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Halted);
+	assert_eq!(machine.memory[Address(2)], Row::Word(Word::Permission(RX)));
+}
+
+#[test]
+fn os_runs_under_cerise() {
+	// The kernel restricts capabilities with permission literals, e.g. restrict R0 E
+	let mut config = ron::de::from_str::<MachineConfig>(&fs::read_to_string("config/os.ron").unwrap()).unwrap();
+	config.semantics = Semantics::Cerise;
+
+	let machine = emulator::emulate(config);
+
+	assert_eq!(machine.exec_state, State::Halted);
+}
+
+#[test]
+fn restrict_unknown_integer_is_o() {
+	let machine = emulator::emulate(config("Cerise", "restrict R0 42, halt"));
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Halted);
+	assert_register_capability!(machine, Register::R(0), (O, 0x000, 0x200, 0x000));
+}
+
+#[test]
+fn getp_restrict_roundtrip() {
+	let machine = emulator::emulate(config(
+		"Cerise",
+		"mov R1 R0, restrict R1 3, getp R2 R1, restrict R0 R2, halt",
+	));
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Failed); // RX is not below RW
+
+	let machine = emulator::emulate(config(
+		"Cerise",
+		"mov R1 R0, restrict R1 2, getp R2 R1, restrict R0 R2, halt",
+	));
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Halted);
+	assert_register_capability!(machine, Register::R(0), (RO, 0x000, 0x200, 0x000));
+}
+
+#[test]
+fn flows_decodes_integers() {
+	let machine = emulator::emulate(config("Cerise", "flows R1 2 5, flows R2 5 2, halt"));
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Halted);
+	assert_eq!(machine.read_register(Register::R(1)), Word::Integer(1));
+	assert_eq!(machine.read_register(Register::R(2)), Word::Integer(0));
+}
+
+#[test]
+fn subseg_up_to_addr_max() {
+	let machine = emulator::emulate(config("Cerise", "subseg R0 0x200 0x200, halt"));
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Halted);
+	assert_register_capability!(machine, Register::R(0), (RW, 0x200, 0x200, 0x000));
+
+	let machine = emulator::emulate(config("Modified", "subseg R0 0x200 0x200, halt"));
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Failed);
+}
+
+#[test]
+fn lea_within_addr_max() {
+	let machine = emulator::emulate(config("Cerise", "lea R0 [-1], halt"));
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Failed);
+
+	let machine = emulator::emulate(config("Modified", "lea R0 [-1], lea R0 1, halt"));
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Halted);
+	assert_register_capability!(machine, Register::R(0), (RW, 0x000, 0x200, 0x000));
+}
@@ -0,0 +1,90 @@
+use cerisemu::emulator::{
+	machine::{Interrupt, State, StepOutcome},
+	permission::Permission,
+	program::{Address, Capability, Register, Word},
+};
+
+use crate::machine;
+
+#[test]
+fn step_before_boot() {
+	let mut machine = machine(r#"MachineConfig(size: 0x10, programs: { 0x00: Source("halt") })"#);
+
+	assert_eq!(machine.step(), StepOutcome::Stopped(State::Halted));
+	assert!(!machine.is_running());
+}
+
+#[test]
+fn step_outcomes() {
+	let mut machine = machine(
+		r#"
+			MachineConfig(
+				size: 0x10,
+				programs: {
+					0x00: Source("mov r1 1, halt"),
+				},
+				interrupt_table: {
+					Halt: 0x0F,
+				},
+			)
+		"#,
+	);
+
+	machine.boot();
+
+	assert_eq!(machine.step(), StepOutcome::Executed);
+	assert_eq!(machine.read_register(Register::R(1)), Word::Integer(1));
+
+	assert_eq!(machine.step(), StepOutcome::Interrupted(Interrupt::Halt));
+	assert_eq!(machine.exec_state, State::Interrupted(Interrupt::Halt));
+
+	// The interrupt destination is the integer 0, which isn't a capability so the next fetch fails
+
+	assert_eq!(machine.step(), StepOutcome::Stopped(State::Halted));
+	assert_eq!(machine.step(), StepOutcome::Stopped(State::Halted));
+}
+
+#[test]
+fn run_for() {
+	let mut machine = machine(
+		r#"MachineConfig(size: 0x10, programs: { 0x00: Source("add r1 r1 1, add r1 r1 1, add r1 r1 1, halt") })"#,
+	);
+
+	machine.boot();
+
+	assert_eq!(machine.run_for(0), StepOutcome::Executed);
+	assert_eq!(machine.run_for(2), StepOutcome::Executed);
+	assert_eq!(machine.read_register(Register::R(1)), Word::Integer(2));
+
+	assert_eq!(machine.run_for(10), StepOutcome::Stopped(State::Halted));
+	assert_eq!(machine.read_register(Register::R(1)), Word::Integer(3));
+}
+
+#[test]
+fn run_until() {
+	let mut machine = machine(r#"MachineConfig(size: 0x10, programs: { 0x00: Source("add r1 r1 1, jmp PC") })"#);
+
+	machine.boot();
+	let outcome = machine.run_until(|machine| machine.read_register(Register::R(1)) == Word::Integer(1));
+
+	assert_eq!(outcome, StepOutcome::Executed);
+	assert!(machine.is_running());
+}
+
+#[test]
+fn boot_with_pc() {
+	let mut machine = machine(r#"MachineConfig(size: 0x10, programs: { 0x00: Source("fail, mov r1 PC, halt") })"#);
+
+	machine.boot_with_pc(Capability {
+		perm: Permission::RX,
+		base: Address(0x01),
+		end: Address(0x03),
+		address: Address(0x01),
+	});
+
+	assert_eq!(machine.run_until(|_| false), StepOutcome::Stopped(State::Halted));
+	assert_eq!(
+		machine.get_register_capability(Register::R(1)).unwrap().perm,
+		Permission::RX
+	);
+}
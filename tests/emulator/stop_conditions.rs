@@ -0,0 +1,172 @@
+use cerisemu::emulator::{
+	self,
+	machine::{Machine, State, StepOutcome},
+	machine_config::MachineConfig,
+	permission::Permission,
+	program::{Address, Capability, Register, Row, Word},
+};
+
+fn config(stop_conditions: &str) -> MachineConfig {
+	ron::de::from_str::<MachineConfig>(&format!(
+		r#"
+			MachineConfig(
+				size: 0x20,
+				registers: {{
+					R(0): Capability(RW, 0x10, 0x20, 0x10),
+				}},
+				programs: {{
+					0x00: Source("mov r1 1, store R0 42, load R2 R0, mov r1 2\nlabel: restrict R0 RO, mov r1 3, load R3 R2, halt"),
+				}},
+				interrupt_table: {{
+					Fail: 0x1F,
+				}},
+				stop_conditions: [{stop_conditions}],
+			)
+		"#
+	))
+	.unwrap()
+}
+
+#[test]
+fn no_conditions() {
+	let machine = emulator::emulate(config(""));
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Failed);
+	assert_eq!(machine.stop_reason(), None);
+}
+
+#[test]
+fn stop_at_address() {
+	let machine = emulator::emulate(config("Address(0x01)"));
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Stopped);
+	assert_eq!(machine.read_register(Register::R(1)), Word::Integer(1));
+	assert!(machine.stop_reason().is_some());
+}
+
+#[test]
+fn stop_at_label() {
+	let machine = emulator::emulate(config("Label(\"label\")"));
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Stopped);
+	assert_eq!(machine.read_register(Register::R(1)), Word::Integer(2));
+}
+
+#[test]
+fn stop_on_memory_access() {
+	let machine = emulator::emulate(config("Write(0x10, 0x11)"));
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Stopped);
+	assert_eq!(machine.read_register(Register::R(2)), Word::Integer(0));
+
+	let machine = emulator::emulate(config("Read(0x00, 0x20)"));
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Stopped);
+	assert_eq!(machine.read_register(Register::R(2)), Word::Integer(42));
+	assert_eq!(machine.read_register(Register::R(1)), Word::Integer(1));
+
+	let machine = emulator::emulate(config("Write(0x11, 0x20)"));
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Failed);
+}
+
+#[test]
+fn stop_on_capability() {
+	let machine = emulator::emulate(config("Capability(RO)"));
+	machine.print_backtrace();
+
+	assert_eq!(machine.exec_state, State::Stopped);
+	assert_eq!(machine.read_register(Register::R(1)), Word::Integer(2));
+}
+
+#[test]
+fn stop_on_fault() {
+	// By instruction, rule name, or premise
+	for fault in ["load", "load r1 r2 ρ", "φ.reg(r2) = (p, b, e, a)"] {
+		let machine = emulator::emulate(config(&format!("Fault(\"{}\")", fault)));
+		machine.print_backtrace();
+
+		assert_eq!(machine.exec_state, State::Stopped);
+		assert_eq!(machine.read_register(Register::R(1)), Word::Integer(3));
+	}
+
+	for fault in ["store", "but φ.reg(r2) = 42", "b ≤ a + z < e"] {
+		let machine = emulator::emulate(config(&format!("Fault(\"{}\")", fault)));
+		machine.print_backtrace();
+
+		assert_eq!(machine.exec_state, State::Failed);
+	}
+}
+
+#[test]
+fn stop_on_fatal_fault() {
+	// Without a Fail interrupt to recover with, the step that fails ends the machine
+	let config = |stop_conditions: &str| {
+		ron::de::from_str::<MachineConfig>(&format!(
+			r#"MachineConfig(size: 0x10, programs: {{ 0x00: Source("mov r1 1, load r2 r1") }}, stop_conditions: [{}])"#,
+			stop_conditions
+		))
+		.unwrap()
+	};
+
+	let machine = emulator::emulate(config("Fault(\"load\")"));
+	assert_eq!(machine.exec_state, State::Stopped);
+	assert_eq!(
+		machine.stop_reason(),
+		Some("fault (load R2 R1: requires φ.reg(r2) = (p, b, e, a), but φ.reg(r2) = 1)")
+	);
+
+	let machine = emulator::emulate(config("Fault(\"store\"), Address(0x01)"));
+	assert_eq!(machine.exec_state, State::Stopped);
+	assert_eq!(machine.stop_reason(), Some("PC reached 0x1"));
+
+	// Starting right at the address, PC doesn't move when failing there, so it doesn't reach the address again
+	let mut machine = Machine::initialize_from_config(config("Address(0x01)"));
+	machine.boot_with_pc(Capability {
+		address: Address(0x01),
+		..machine.master_capability()
+	});
+	assert_eq!(machine.step(), StepOutcome::Stopped(State::Failed));
+	assert_eq!(machine.stop_reason(), None);
+}
+
+#[test]
+fn conditions_met_between_steps_are_ignored() {
+	let mut machine = Machine::initialize_from_config(config("Capability(RO)"));
+	machine.boot();
+
+	// Assigning a capability by hand doesn't stop the next step
+	let capability = Capability {
+		perm: Permission::RO,
+		..machine.master_capability()
+	};
+	machine.write_register(Register::R(5), Word::Capability(machine.sign_capability(capability)));
+	assert_eq!(machine.step(), StepOutcome::Executed);
+
+	assert_eq!(machine.run_for(10), StepOutcome::Stopped(State::Stopped));
+	assert_eq!(machine.read_register(Register::R(1)), Word::Integer(2));
+}
+
+#[test]
+fn forged_capabilities_are_ignored() {
+	let mut machine = Machine::initialize_from_config(config("Capability(RX)"));
+	machine.boot();
+	machine.run_for(2);
+
+	// A capability signed by another machine, which load R2 R0 puts in R2
+	let forged = Capability {
+		perm: Permission::RX,
+		..machine.master_capability()
+	};
+	machine.memory[Address(0x10)] = Row::Word(Word::Capability(Machine::new().sign_capability(forged)));
+
+	machine.run_until(|_| false);
+	assert_eq!(machine.exec_state, State::Failed);
+	assert_eq!(machine.stop_reason(), None);
+}
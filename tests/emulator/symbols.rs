@@ -0,0 +1,86 @@
+use cerisemu::emulator::{
+	machine::Machine,
+	machine_config::MachineConfig,
+	permission::Permission,
+	program::{Address, Capability, Word},
+	signed::Signed,
+};
+
+fn machine() -> Machine {
+	let config = ron::de::from_str::<MachineConfig>(
+		r#"
+			MachineConfig(
+				size: 0x40,
+				programs: {
+					0x00: Source("start:\nmov r1 PC, lea r1 [data - start], halt\ndata:\n1, 2, 3\ndata_end:"),
+					0x20: Source("other:\n0, 0"),
+				},
+			)
+		"#,
+	)
+	.unwrap();
+
+	Machine::initialize_from_config(config)
+}
+
+#[test]
+fn symbolize() {
+	let machine = machine();
+	let symbols = machine.symbol_table();
+
+	assert_eq!(symbols.symbolize(Address(0x00)).as_deref(), Some("start"));
+	assert_eq!(symbols.symbolize(Address(0x02)).as_deref(), Some("start+2"));
+	assert_eq!(symbols.symbolize(Address(0x04)).as_deref(), Some("data+1"));
+	assert_eq!(symbols.symbolize(Address(0x06)).as_deref(), Some("data_end"));
+	assert_eq!(symbols.symbolize(Address(0x21)).as_deref(), Some("other+1"));
+
+	// Addresses outside of any program with labels aren't symbolized
+	assert_eq!(symbols.symbolize(Address(0x10)), None);
+	assert_eq!(symbols.format_address(Address(0x10)), "0x10");
+}
+
+#[test]
+fn symbolic_capabilities() {
+	let machine = machine();
+	let capability = Capability {
+		perm: Permission::RW,
+		base: Address(0x03),
+		end: Address(0x06),
+		address: Address(0x05),
+	};
+
+	assert_eq!(
+		machine.symbol_table().format_capability(&capability),
+		"(RW, data, data_end, data+2)"
+	);
+	assert_eq!(
+		machine
+			.symbol_table()
+			.format_word(&Word::Capability(Signed::new_unsigned(capability))),
+		"$(RW, data, data_end, data+2)"
+	);
+	assert_eq!(machine.symbol_table().format_word(&Word::Integer(3)), "3");
+}
+
+#[test]
+fn symbolic_machine_display() {
+	let mut machine = machine();
+	machine.exec_machine();
+
+	let display = machine.to_string();
+	assert!(display.contains("R1: $(RWX, start, 0x40, data)"));
+	assert!(display.contains("| data:"));
+	assert!(display.contains("| other:"));
+
+	let entries = machine.backtrace().entries();
+	assert!(entries[0].contains(&"PC: $(RWX, start, 0x40, start)".to_string()));
+	assert!(entries[1].contains(&"R1 = $(RWX, start, 0x40, data)".to_string()));
+}
+
+#[test]
+fn symbols_are_dumped() {
+	let machine = machine();
+	let dump = ron::ser::to_string_pretty(&machine, ron::ser::PrettyConfig::default().struct_names(true)).unwrap();
+
+	assert!(dump.contains(r#"("data_end", Address(6))"#));
+}
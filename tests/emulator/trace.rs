@@ -0,0 +1,161 @@
+use cerisemu::emulator::{
+	self,
+	instruction::Instruction,
+	machine::{Interrupt, State},
+	program::{Address, Register, Row, Word},
+	trace::{Effect, TraceStep},
+};
+
+use crate::{config, machine};
+
+#[test]
+fn trace_steps() {
+	let machine = emulator::emulate(config(
+		r#"MachineConfig(size: 0x10, programs: { 0x00: Source("mov r1 PC, lea r1 5, store r1 7, halt") })"#,
+	));
+	let steps = machine.trace().steps();
+
+	assert_eq!(steps.len(), 4);
+	assert_eq!(steps.iter().map(|step| step.step).collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+
+	assert_eq!(steps[0].state, State::Running);
+	assert_eq!(steps[0].address, Some(Address(0)));
+	assert!(matches!(
+		steps[0].instruction,
+		Some(Instruction::Mov(Register::R(1), _))
+	));
+	assert!(matches!(
+		steps[0].effects[0],
+		Effect::Register {
+			register: Register::R(1),
+			value: Word::Capability(_)
+		}
+	));
+
+	assert!(steps[2].effects.contains(&Effect::Write {
+		address: Address(5),
+		row: Row::Word(Word::Integer(7))
+	}));
+
+	assert_eq!(steps[3].result, Some(State::Halted));
+	assert_eq!(steps[3].new_state, State::Halted);
+	assert_eq!(steps[3].fault, None);
+}
+
+#[test]
+fn trace_faults_and_interrupts() {
+	let machine = emulator::emulate(config(
+		r#"
+			MachineConfig(
+				size: 0x10,
+				programs: {
+					0x00: Source("fail"),
+				},
+				interrupt_table: {
+					Fail: 0x0F,
+				},
+			)
+		"#,
+	));
+	let steps = machine.trace().steps();
+
+	assert_eq!(steps.len(), 2);
+
+	assert_eq!(steps[0].result, Some(State::Failed));
+	assert_eq!(steps[0].interrupt, Some(Interrupt::Fail));
+	assert_eq!(steps[0].new_state, State::Interrupted(Interrupt::Fail));
+
+	// The interrupt destination is the integer 0, so fetching the next instruction fails
+	assert_eq!(steps[1].instruction, None);
+	assert!(steps[1]
+		.fault
+		.as_ref()
+		.unwrap()
+		.starts_with("ExecSingle: requires φ.reg(pc) = (p, b, e, a)"));
+	assert_eq!(steps[1].new_state, State::Failed);
+}
+
+#[test]
+fn trace_stop_reason() {
+	let machine = emulator::emulate(config(
+		r#"MachineConfig(size: 0x10, programs: { 0x00: Source("mov r1 1, halt") }, stop_conditions: [Address(1)])"#,
+	));
+	let last = machine.trace().steps().last().unwrap();
+
+	assert_eq!(last.new_state, State::Stopped);
+	assert_eq!(last.stop_reason.as_deref(), Some("PC reached 0x1"));
+}
+
+#[test]
+fn json_lines() {
+	let machine = emulator::emulate(config(
+		r#"MachineConfig(size: 0x10, programs: { 0x00: Source("mov r1 PC, lea r1 3, jmp r1, halt") })"#,
+	));
+
+	let mut output = Vec::new();
+	machine.trace().write_json_lines(&mut output).unwrap();
+
+	let lines = String::from_utf8(output).unwrap();
+	let lines = lines.lines().collect::<Vec<_>>();
+
+	assert_eq!(lines.len(), machine.trace().steps().len());
+
+	for (line, step) in lines.into_iter().zip(machine.trace().steps()) {
+		// Signatures aren't part of the trace, so compare the serialized steps instead of the steps themselves
+		let parsed = serde_json::from_str::<TraceStep>(line).unwrap();
+		assert_eq!(serde_json::to_string(&parsed).unwrap(), line);
+		assert_eq!(serde_json::to_string(step).unwrap(), line);
+	}
+}
+
+#[test]
+fn backtrace_renders_trace() {
+	let machine = emulator::emulate(config(
+		r#"MachineConfig(size: 0x10, programs: { 0x00: Source("mov r1 1, halt") }, interrupt_table: { Halt: 0x0F })"#,
+	));
+	let entries = machine.backtrace().entries();
+
+	assert_eq!(
+		entries,
+		vec![
+			vec![
+				"State: Running",
+				"PC: $(RWX, 0x0, 0x10, 0x0)",
+				"Instruction: mov R1 1 @ <source>:1:1",
+				"R1 = 1",
+				"New State: Running"
+			],
+			vec![
+				"State: Running",
+				"PC: $(RWX, 0x0, 0x10, 0x1)",
+				"Instruction: halt @ <source>:1:11",
+				"New State: Halted"
+			],
+			vec!["Interrupt: HALT", "New State: Interrupted(Halt)"],
+			vec![
+				"State: Interrupted(Halt)",
+				"PC: 0",
+				"Error: ExecSingle: requires φ.reg(pc) = (p, b, e, a), but φ.reg(pc) = 0"
+			],
+			vec!["HALT Interrupt not recoverable"],
+			vec!["State: Halted", "Shutting down"],
+		]
+	);
+}
+
+#[test]
+fn trace_keeps_latest_steps() {
+	let mut machine = machine(
+		r#"MachineConfig(size: 0x10, programs: { 0x00: Source("mov r1 PC, jmp r1") }, recorded_steps: Some(3))"#,
+	);
+	machine.boot();
+	machine.run_for(10);
+
+	let trace = machine.trace();
+
+	assert_eq!(trace.recorded_steps(), 10);
+	assert_eq!(
+		trace.steps().iter().map(|step| step.step).collect::<Vec<_>>(),
+		vec![7, 8, 9]
+	);
+}
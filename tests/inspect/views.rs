@@ -0,0 +1,134 @@
+use cerisemu::{
+	emulator::{
+		self,
+		dump::MachineDump,
+		machine::{Machine, State},
+		memory::Memory,
+		program::Address,
+	},
+	inspect::{self, RowFilter, View},
+};
+
+// Stores a capability on the data region and a capability on the program, then halts
+const PROGRAM: &str = r#"
+	MachineConfig(
+		size: 0x40,
+		programs: {
+			0x00: Source("main:\nmov r1 PC\nlea r1 [data - main]\nsubseg r1 [data] [data_end]\nhere:\nmov r2 PC\nlea r2 [slot - here]\nstore r2 r1\nlea r2 1\nrestrict r1 RO\nstore r2 r1\nlea r2 1\nstore r2 PC\nhalt\nslot:\n0\n0\n0\ndata:\n\"Hi!\"\n7\n'x'\ndata_end:"),
+		},
+	)
+"#;
+
+fn run() -> Machine {
+	let machine = emulator::emulate(crate::config(PROGRAM));
+	assert_eq!(machine.exec_state, State::Halted);
+	machine
+}
+
+fn range(memory: &Memory, range: &str) -> std::ops::Range<Address> {
+	inspect::resolve_range(memory, range).unwrap()
+}
+
+#[test]
+fn resolve_range() {
+	let memory = run().memory;
+
+	assert_eq!(range(&memory, "0x2..5"), Address(2)..Address(5));
+	assert_eq!(range(&memory, "7"), Address(7)..Address(8));
+	assert_eq!(range(&memory, "slot"), Address(12)..Address(15));
+	assert_eq!(range(&memory, "slot..data_end"), Address(12)..Address(20));
+	assert_eq!(range(&memory, "data_end"), Address(20)..Address(0x40));
+
+	assert!(inspect::resolve_range(&memory, "nowhere").is_err());
+	assert!(inspect::resolve_range(&memory, "5..2").is_err());
+	assert!(inspect::resolve_range(&memory, "0..0x41").is_err());
+}
+
+#[test]
+fn rows_view() {
+	let memory = run().memory;
+
+	let rows = inspect::view(&memory, range(&memory, "data"), View::Rows, RowFilter::Chars);
+	assert_eq!(rows.lines().count(), 6);
+	assert!(rows.contains("data:"));
+	assert!(rows.contains("'!'"));
+	assert!(rows.contains("..."));
+	assert!(!rows.contains(" 7"));
+
+	let empty = inspect::view(&memory, range(&memory, "data"), View::Rows, RowFilter::Capabilities);
+	assert_eq!(empty, "(Empty)");
+}
+
+#[test]
+fn hex_view() {
+	let memory = run().memory;
+
+	let hex = inspect::view(&memory, range(&memory, "data..0x40"), View::Hex, RowFilter::default());
+	let lines = hex.lines().collect::<Vec<_>>();
+
+	assert_eq!(
+		lines[0],
+		"0xf  | 0000000000000048 0000000000000069 0000000000000021 0000000000000007 | Hi!."
+	);
+	assert_eq!(
+		lines[1],
+		"0x13 | 0000000000000078 0000000000000000 0000000000000000 0000000000000000 | x..."
+	);
+	assert_eq!(lines[2], "*");
+	assert_eq!(lines.len(), 3);
+}
+
+#[test]
+fn chars_view() {
+	let memory = run().memory;
+
+	assert_eq!(
+		inspect::view(&memory, range(&memory, "0..0x40"), View::Chars, RowFilter::default()),
+		"0xf..0x12 (data) (3 chars) | \"Hi!\"\n0x13..0x14 (data+4) (1 chars) | \"x\""
+	);
+}
+
+#[test]
+fn capabilities_view() {
+	let memory = run().memory;
+
+	let capabilities = inspect::view(
+		&memory,
+		range(&memory, "slot"),
+		View::Capabilities,
+		RowFilter::default(),
+	);
+
+	assert_eq!(
+		capabilities,
+		[
+			"[0x0, 0x40) main..0x40 (64 rows, 1 capability)",
+			"  0xe (slot+2) | RWX at here+7",
+			"[0xf, 0x14) data..data_end (5 rows, 2 capabilities)",
+			"  0xc (slot) | RWX at data",
+			"  0xd (slot+1) | RO at data",
+		]
+		.join("\n")
+	);
+}
+
+#[test]
+fn inspect_dump() {
+	let machine = run();
+	let dump = ron::ser::to_string(&machine).unwrap();
+
+	let dump = ron::de::from_str::<MachineDump>(&dump).unwrap();
+	assert_eq!(dump.exec_state, State::Halted);
+
+	let memory = &dump.memory;
+	assert_eq!(range(memory, "slot"), Address(12)..Address(15));
+	assert_eq!(
+		inspect::view(memory, range(memory, "slot"), View::Capabilities, RowFilter::default()),
+		inspect::view(
+			&machine.memory,
+			range(&machine.memory, "slot"),
+			View::Capabilities,
+			RowFilter::default()
+		)
+	);
+}
@@ -1,13 +1,56 @@
 mod compiler {
+	mod debug_info;
 	mod expression;
 	mod expression_eval;
 }
 
+mod debugger {
+	mod commands;
+	mod dap;
+	mod gdb;
+	mod tui;
+}
+
 mod emulator {
+	mod coverage;
+	mod diff;
+	mod encoding;
+	mod history;
 	mod instructions;
+	mod leaks;
 	mod malloc;
 	mod memcpy;
+	mod observer;
 	mod permission;
+	mod profile;
+	mod programs;
+	mod provenance;
+	mod rules;
+	mod semantics;
+	mod step;
+	mod stop_conditions;
+	mod symbols;
+	mod trace;
+}
+
+mod inspect {
+	mod views;
+}
+
+mod visualizer {
+	mod routes;
+}
+
+use cerisemu::emulator::{machine::Machine, machine_config::MachineConfig};
+
+/// Parses a MachineConfig written in RON.
+pub fn config(config: &str) -> MachineConfig {
+	ron::de::from_str::<MachineConfig>(config).unwrap()
+}
+
+/// Initializes a machine from a MachineConfig written in RON, without running it (unlike emulator::emulate()).
+pub fn machine(config: &str) -> Machine {
+	Machine::initialize_from_config(self::config(config))
 }
 
 #[macro_export]
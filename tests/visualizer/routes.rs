@@ -0,0 +1,197 @@
+use std::io::Cursor;
+
+use cerisemu::{
+	emulator::{
+		machine::{Machine, State},
+		machine_config::MachineConfig,
+	},
+	visualizer::Visualizer,
+};
+use serde_json::{json, Value};
+
+// Two programs with a data region in between, the first one storing a capability on the second one's data
+const CONFIG: &str = r#"
+	MachineConfig(
+		size: 0x60,
+		programs: {
+			0x00: Source("main:\nmov r1 PC\nlea r1 0x20\nsubseg r1 0x20 0x24\nmov r2 r1\nlea r2 1\nrestrict r1 RO\nstore r2 r1\nhalt"),
+			0x20: Source("data:\n0\n0\n0\n0\ndata_end:"),
+			0x40: Source("7"),
+		},
+		interrupt_table: {
+			Halt: 0x07,
+		},
+	)
+"#;
+
+fn visualizer() -> Visualizer {
+	Visualizer::new(Machine::initialize_from_config(
+		ron::de::from_str::<MachineConfig>(CONFIG).unwrap(),
+	))
+}
+
+fn json(visualizer: &mut Visualizer, method: &str, target: &str, body: &str) -> Value {
+	let response = visualizer.handle(method, target, body);
+	assert_eq!(response.status, 200, "{}", response.body);
+	assert_eq!(response.content_type, "application/json");
+	serde_json::from_str(&response.body).unwrap()
+}
+
+#[test]
+fn state() {
+	let mut visualizer = visualizer();
+
+	let state = json(&mut visualizer, "GET", "/state", "");
+	assert_eq!(
+		state["location"],
+		"[step 0] Running at 0x0 <main> | Instruction(mov R1 PC)"
+	);
+	assert_eq!(state["state"], "Running");
+	assert_eq!(state["pc"], 0);
+	assert_eq!(state["size"], 0x60);
+	assert_eq!(
+		state["segments"],
+		json!([
+			{ "kind": "program", "start": 0x00, "end": 0x08, "name": "main", "file": null },
+			{ "kind": "gap", "start": 0x08, "end": 0x20 },
+			{ "kind": "program", "start": 0x20, "end": 0x24, "name": "data", "file": null },
+			{ "kind": "gap", "start": 0x24, "end": 0x40 },
+			{ "kind": "data", "start": 0x40, "end": 0x41 },
+			{ "kind": "gap", "start": 0x41, "end": 0x60 },
+		])
+	);
+	assert_eq!(
+		state["labels"],
+		json!([
+			{ "label": "main", "address": 0x00 },
+			{ "label": "data", "address": 0x20 },
+			{ "label": "data_end", "address": 0x24 },
+		])
+	);
+	assert_eq!(
+		state["interrupt_table"],
+		json!([{ "interrupt": "HALT", "address": 0x07 }])
+	);
+	assert_eq!(
+		state["capabilities"],
+		json!([{ "holder": "PC", "perm": "RWX", "base": 0x00, "end": 0x60, "address": 0x00, "text": "(RWX, main, 0x60, main)" }])
+	);
+}
+
+#[test]
+fn commands() {
+	let mut visualizer = visualizer();
+
+	let response = json(&mut visualizer, "POST", "/command", "step 7");
+	assert_eq!(response["output"], "[step 7] Running at 0x7 | Instruction(halt)");
+	assert_eq!(response["state"]["pc"], 7);
+	assert_eq!(
+		response["state"]["registers"][1],
+		json!({ "register": "R1", "value": "$(RO, data, data_end, data)" })
+	);
+	assert_eq!(
+		response["state"]["capabilities"],
+		json!([
+			{ "holder": "PC", "perm": "RWX", "base": 0x00, "end": 0x60, "address": 0x07, "text": "(RWX, main, 0x60, main+7)" },
+			{ "holder": "R1", "perm": "RO", "base": 0x20, "end": 0x24, "address": 0x20, "text": "(RO, data, data_end, data)" },
+			{ "holder": "R2", "perm": "RWX", "base": 0x20, "end": 0x24, "address": 0x21, "text": "(RWX, data, data_end, data+1)" },
+			{ "holder": "data+1", "perm": "RO", "base": 0x20, "end": 0x24, "address": 0x20, "text": "(RO, data, data_end, data)" },
+		])
+	);
+
+	let response = json(&mut visualizer, "POST", "/command", "back 2");
+	assert_eq!(response["state"]["pc"], 5);
+	assert_eq!(response["state"]["capabilities"].as_array().unwrap().len(), 3);
+
+	let response = json(&mut visualizer, "POST", "/command", "nope");
+	assert_eq!(
+		response["output"],
+		"Error: unknown command \"nope\", type \"help\" for a list of commands"
+	);
+
+	json(&mut visualizer, "POST", "/command", "continue");
+	assert_eq!(visualizer.machine().exec_state, State::Halted);
+}
+
+#[test]
+fn memory() {
+	let mut visualizer = visualizer();
+	visualizer.handle("POST", "/command", "continue");
+
+	assert_eq!(
+		json(&mut visualizer, "GET", "/memory?start=data&end=0x22", ""),
+		json!([
+			{ "address": 0x20, "symbol": "data", "row": "0" },
+			{ "address": 0x21, "symbol": "data+1", "row": "$(RO, data, data_end, data)" },
+		])
+	);
+
+	// At most 64 rows, within memory
+	let rows = json(&mut visualizer, "GET", "/memory?start=0x30&end=0x1000", "");
+	assert_eq!(rows.as_array().unwrap().len(), 0x30);
+
+	let response = visualizer.handle("GET", "/memory?start=nowhere&end=1", "");
+	assert_eq!(response.status, 400);
+	assert_eq!(response.body, "\"nowhere\" is neither an address nor a known label");
+
+	assert_eq!(visualizer.handle("GET", "/memory?end=1", "").status, 400);
+}
+
+#[test]
+fn http() {
+	let mut visualizer = visualizer();
+
+	let serve = |visualizer: &mut Visualizer, request: &str| {
+		let mut output = Vec::new();
+		visualizer.serve(Cursor::new(request), &mut output).unwrap();
+		String::from_utf8(output).unwrap()
+	};
+
+	let page = serve(&mut visualizer, "GET / HTTP/1.1\r\nHost: localhost\r\n\r\n");
+	assert!(page.starts_with("HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\n"));
+	assert!(page.contains("<svg id=\"map\""));
+	assert!(!page.contains("<script src="));
+
+	let response = serve(
+		&mut visualizer,
+		"POST /command HTTP/1.1\r\nHost: 127.0.0.1:8080\r\nOrigin: http://127.0.0.1:8080\r\nContent-Length: 6\r\n\r\nstep 2",
+	);
+	assert!(response.starts_with("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n"));
+	assert!(response.contains("\"output\":\"[step 2] Running at 0x2 | Instruction(subseg R1 32 36)\""));
+
+	assert!(serve(&mut visualizer, "GET /nope HTTP/1.1\r\n\r\n").starts_with("HTTP/1.1 404 Not Found\r\n"));
+	assert!(serve(&mut visualizer, "DELETE /state HTTP/1.1\r\n\r\n").starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+	assert!(serve(&mut visualizer, "garbage\r\n\r\n").starts_with("HTTP/1.1 400 Bad Request\r\n"));
+}
+
+#[test]
+fn http_rejects_requests() {
+	let mut visualizer = visualizer();
+
+	let serve = |visualizer: &mut Visualizer, request: &str| {
+		let mut output = Vec::new();
+		visualizer.serve(Cursor::new(request), &mut output).unwrap();
+		String::from_utf8(output).unwrap()
+	};
+
+	let state = visualizer.state();
+
+	// Other sites open in the browser cannot drive the machine, even through a name resolving to the loopback
+	for headers in [
+		"Host: 127.0.0.1:8080\r\nOrigin: http://evil.example",
+		"Host: evil.example:8080\r\nOrigin: http://evil.example:8080",
+		"Origin: http://127.0.0.1:8080",
+	] {
+		let request = format!("POST /command HTTP/1.1\r\n{}\r\nContent-Length: 4\r\n\r\nstep", headers);
+		assert!(serve(&mut visualizer, &request).starts_with("HTTP/1.1 403 Forbidden\r\n"));
+	}
+	assert_eq!(visualizer.state(), state);
+
+	// Without an Origin, e.g. from curl
+	let request = "POST /command HTTP/1.1\r\nHost: localhost:8080\r\nContent-Length: 4\r\n\r\nstep";
+	assert!(serve(&mut visualizer, request).starts_with("HTTP/1.1 200 OK\r\n"));
+
+	// The body isn't read when it is too large
+	let request = "POST /command HTTP/1.1\r\nHost: localhost\r\nContent-Length: 1000000000\r\n\r\nstep";
+	assert!(serve(&mut visualizer, request).starts_with("HTTP/1.1 413 Payload Too Large\r\n"));
+}